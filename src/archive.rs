@@ -0,0 +1,84 @@
+use crate::scanner;
+use anyhow::{Context, Result};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Reads the source files out of a tar stream without unpacking it to disk.
+/// Regular files with one of `extensions` are returned with their path inside
+/// the archive; test code (see [`scanner::is_test_path`]) is skipped unless
+/// `include_tests` is set, as are files whose recorded mtime is before `since`.
+pub fn read_tar(reader: impl Read, extensions: &[&str], include_tests: bool, since: Option<SystemTime>) -> Result<Vec<(PathBuf, String)>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut files = Vec::new();
+
+    for entry in archive.entries().context("Failed to read tar archive")? {
+        let mut entry = entry.context("Failed to read tar entry")?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path()?.into_owned();
+        let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+        if !extensions.contains(&ext) || (!include_tests && scanner::is_test_path(Path::new(""), &path)) {
+            continue;
+        }
+        if let (Some(since), Ok(mtime)) = (since, entry.header().mtime()) {
+            if UNIX_EPOCH + Duration::from_secs(mtime) < since {
+                continue;
+            }
+        }
+
+        let mut content = String::new();
+        entry.read_to_string(&mut content)
+            .with_context(|| format!("Failed to read {:?} from tar archive", path))?;
+        files.push((path, content));
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers;
+    use std::fs;
+
+    fn tar_of(files: &[(&str, &str)]) -> Result<Vec<u8>> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, fixture) in files {
+            let content = fs::read(fixture)?;
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_mtime(1_700_000_000);
+            header.set_cksum();
+            builder.append_data(&mut header, path, content.as_slice())?;
+        }
+        Ok(builder.into_inner()?)
+    }
+
+    #[test]
+    fn test_read_tar() -> Result<()> {
+        let archive = tar_of(&[
+            ("src/animals.py", "tests/python/animals.py"),
+            ("src/Animals.java", "tests/java/Animals.java"),
+            ("src/notes.txt", "tests/python/ignore_me.txt"),
+            ("src/ShapeTest.java", "tests/java/ShapeTest.java"),
+        ])?;
+
+        let files = read_tar(archive.as_slice(), &["py", "java"], false, None)?;
+        let paths: Vec<_> = files.iter().map(|(path, _)| path.to_str().unwrap()).collect();
+        assert_eq!(paths, vec!["src/animals.py", "src/Animals.java"]);
+        for (path, content) in &files {
+            let ext = path.extension().and_then(|s| s.to_str()).unwrap();
+            let classes = parsers::get_parser(ext).unwrap().parse(content)?;
+            assert!(!classes.is_empty(), "no classes parsed from {:?}", path);
+        }
+
+        let with_tests = read_tar(archive.as_slice(), &["java"], true, None)?;
+        assert_eq!(with_tests.len(), 2);
+        let recent = read_tar(archive.as_slice(), &["py", "java"], false, Some(UNIX_EPOCH + Duration::from_secs(1_800_000_000)))?;
+        assert!(recent.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+use crate::models::ClassInfo;
+use clap::ValueEnum;
+use log::error;
+use std::collections::{BTreeSet, HashMap};
+use std::str::FromStr;
+
+/// Exit code when `--fail-on cycles` finds a relationship cycle.
+pub const EXIT_CYCLES: u8 = 3;
+/// Exit code when a `--forbid` dependency is present.
+pub const EXIT_FORBIDDEN: u8 = 4;
+/// Exit code when no classes were extracted and `--allow-empty` is not given.
+pub const EXIT_EMPTY: u8 = 5;
+
+/// Conditions for `--fail-on`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FailOn {
+    /// Classes that depend on each other through any chain of relationships
+    Cycles,
+}
+
+/// A `--forbid Source->Target` rule, matched against qualified class names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForbiddenEdge {
+    pub source: String,
+    pub target: String,
+}
+
+impl FromStr for ForbiddenEdge {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.split_once("->") {
+            Some((source, target)) if !source.trim().is_empty() && !target.trim().is_empty() => Ok(ForbiddenEdge {
+                source: source.trim().to_string(),
+                target: target.trim().to_string(),
+            }),
+            _ => Err(format!("expected `Source->Target`, got `{}`", value)),
+        }
+    }
+}
+
+/// Groups of parsed classes that reach each other through relationships of any
+/// type (strongly connected components with more than one class), each sorted.
+/// A class referring to itself is not a cycle.
+pub fn find_cycles(classes: &[ClassInfo]) -> Vec<Vec<String>> {
+    let index: HashMap<&str, usize> = classes.iter().enumerate().map(|(i, c)| (c.name.as_str(), i)).collect();
+    let edges: Vec<Vec<usize>> = classes.iter()
+        .map(|c| {
+            let targets: BTreeSet<usize> = c.relationships.iter()
+                .filter_map(|r| index.get(r.target.as_str()).copied())
+                .collect();
+            targets.into_iter().collect()
+        })
+        .collect();
+
+    let mut tarjan = Tarjan {
+        edges: &edges,
+        index: vec![None; classes.len()],
+        low: vec![0; classes.len()],
+        on_stack: vec![false; classes.len()],
+        stack: Vec::new(),
+        next: 0,
+        components: Vec::new(),
+    };
+    for node in 0..classes.len() {
+        if tarjan.index[node].is_none() {
+            tarjan.visit(node);
+        }
+    }
+
+    let mut cycles: Vec<Vec<String>> = tarjan.components.into_iter()
+        .filter(|component| component.len() > 1)
+        .map(|component| {
+            let mut names: Vec<String> = component.into_iter().map(|i| classes[i].name.clone()).collect();
+            names.sort();
+            names
+        })
+        .collect();
+    cycles.sort();
+    cycles
+}
+
+struct Tarjan<'a> {
+    edges: &'a [Vec<usize>],
+    index: Vec<Option<usize>>,
+    low: Vec<usize>,
+    on_stack: Vec<bool>,
+    stack: Vec<usize>,
+    next: usize,
+    components: Vec<Vec<usize>>,
+}
+
+impl Tarjan<'_> {
+    fn visit(&mut self, node: usize) {
+        self.index[node] = Some(self.next);
+        self.low[node] = self.next;
+        self.next += 1;
+        self.stack.push(node);
+        self.on_stack[node] = true;
+
+        for &target in &self.edges[node] {
+            match self.index[target] {
+                None => {
+                    self.visit(target);
+                    self.low[node] = self.low[node].min(self.low[target]);
+                }
+                Some(target_index) if self.on_stack[target] => {
+                    self.low[node] = self.low[node].min(target_index);
+                }
+                Some(_) => {}
+            }
+        }
+
+        if Some(self.low[node]) == self.index[node] {
+            let mut component = Vec::new();
+            while let Some(member) = self.stack.pop() {
+                self.on_stack[member] = false;
+                component.push(member);
+                if member == node {
+                    break;
+                }
+            }
+            self.components.push(component);
+        }
+    }
+}
+
+/// The `forbid` rules violated by a relationship of any type.
+pub fn find_forbidden<'a>(classes: &[ClassInfo], forbid: &'a [ForbiddenEdge]) -> Vec<&'a ForbiddenEdge> {
+    forbid.iter()
+        .filter(|rule| classes.iter()
+            .filter(|c| c.name == rule.source)
+            .any(|c| c.relationships.iter().any(|r| r.target == rule.target)))
+        .collect()
+}
+
+/// Runs the `--fail-on` and `--forbid` gates, logging every violation.
+/// Returns the exit code of the first failing gate, if any.
+pub fn run_gates(classes: &[ClassInfo], fail_on: &[FailOn], forbid: &[ForbiddenEdge]) -> Option<u8> {
+    let mut exit_code = None;
+
+    if fail_on.contains(&FailOn::Cycles) {
+        let cycles = find_cycles(classes);
+        for cycle in &cycles {
+            error!("relationship cycle: {}", cycle.join(" <-> "));
+        }
+        if !cycles.is_empty() {
+            exit_code = exit_code.or(Some(EXIT_CYCLES));
+        }
+    }
+
+    let violated = find_forbidden(classes, forbid);
+    for rule in &violated {
+        error!("forbidden dependency: {} -> {}", rule.source, rule.target);
+    }
+    if !violated.is_empty() {
+        exit_code = exit_code.or(Some(EXIT_FORBIDDEN));
+    }
+
+    exit_code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::{python::PythonParser, LanguageParser};
+    use anyhow::Result;
+    use std::fs;
+
+    #[test]
+    fn test_cyclic_fixture_fails_on_cycles() -> Result<()> {
+        let classes = PythonParser.parse(&fs::read_to_string("tests/checks/cycle.py")?)?;
+        assert_eq!(find_cycles(&classes), vec![vec!["Customer".to_string(), "Order".to_string()]]);
+        assert_eq!(run_gates(&classes, &[FailOn::Cycles], &[]), Some(EXIT_CYCLES));
+        assert_eq!(run_gates(&classes, &[], &[]), None);
+
+        let layers = PythonParser.parse(&fs::read_to_string("tests/checks/layers.py")?)?;
+        assert_eq!(run_gates(&layers, &[FailOn::Cycles], &[]), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_forbidden_edge_fails() -> Result<()> {
+        let classes = PythonParser.parse(&fs::read_to_string("tests/checks/layers.py")?)?;
+        let forbid: ForbiddenEdge = "Controller->Repository".parse().unwrap();
+        assert_eq!(run_gates(&classes, &[], &[forbid]), Some(EXIT_FORBIDDEN));
+
+        let allowed: ForbiddenEdge = "Repository -> Controller".parse().unwrap();
+        assert_eq!(allowed.source, "Repository");
+        assert_eq!(run_gates(&classes, &[], &[allowed]), None);
+
+        assert!("Controller".parse::<ForbiddenEdge>().is_err());
+        assert!("->Repository".parse::<ForbiddenEdge>().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,40 @@
+use anyhow::{anyhow, Context, Result};
+use arboard::Clipboard;
+
+/// Copies the diagram to the system clipboard.
+pub fn copy_to_clipboard(text: &str) -> Result<()> {
+    copy_with(Clipboard::new(), text)
+}
+
+fn copy_with(clipboard: Result<Clipboard, arboard::Error>, text: &str) -> Result<()> {
+    let mut clipboard = clipboard.map_err(|e| {
+        anyhow!("No system clipboard is available ({}); use --output to write the diagram to a file instead", e)
+    })?;
+    clipboard.set_text(text).context("Failed to copy the diagram to the clipboard")?;
+    log::info!("Copied diagram to the clipboard.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clipboard_round_trip() -> Result<()> {
+        let Ok(mut clipboard) = Clipboard::new() else {
+            eprintln!("Skipping: no clipboard available");
+            return Ok(());
+        };
+
+        let diagram = "classDiagram\n    class Car\n";
+        copy_to_clipboard(diagram)?;
+        assert_eq!(clipboard.get_text()?, diagram);
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_clipboard_errors_cleanly() {
+        let err = copy_with(Err(arboard::Error::ClipboardNotSupported), "classDiagram\n").unwrap_err();
+        assert!(err.to_string().contains("No system clipboard is available"));
+    }
+}
@@ -0,0 +1,110 @@
+use crate::models::ClassInfo;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+/// Classes and relationships added or removed relative to a baseline model.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ModelDiff {
+    pub added_classes: Vec<String>,
+    pub removed_classes: Vec<String>,
+    pub added_relationships: Vec<String>,
+    pub removed_relationships: Vec<String>,
+}
+
+impl ModelDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_classes.is_empty()
+            && self.removed_classes.is_empty()
+            && self.added_relationships.is_empty()
+            && self.removed_relationships.is_empty()
+    }
+
+    /// Formats the diff as `+`/`-` lines, one per changed class or relationship.
+    pub fn report(&self) -> String {
+        let mut report = String::new();
+        for name in &self.added_classes {
+            writeln!(&mut report, "+ class {}", name).unwrap();
+        }
+        for name in &self.removed_classes {
+            writeln!(&mut report, "- class {}", name).unwrap();
+        }
+        for edge in &self.added_relationships {
+            writeln!(&mut report, "+ {}", edge).unwrap();
+        }
+        for edge in &self.removed_relationships {
+            writeln!(&mut report, "- {}", edge).unwrap();
+        }
+        report
+    }
+}
+
+/// Compares the current model against a baseline.
+pub fn diff_models(baseline: &[ClassInfo], current: &[ClassInfo]) -> ModelDiff {
+    let (old_classes, old_edges) = summarize(baseline);
+    let (new_classes, new_edges) = summarize(current);
+
+    ModelDiff {
+        added_classes: new_classes.difference(&old_classes).cloned().collect(),
+        removed_classes: old_classes.difference(&new_classes).cloned().collect(),
+        added_relationships: new_edges.difference(&old_edges).cloned().collect(),
+        removed_relationships: old_edges.difference(&new_edges).cloned().collect(),
+    }
+}
+
+fn summarize(classes: &[ClassInfo]) -> (BTreeSet<String>, BTreeSet<String>) {
+    let names = classes.iter().map(|c| c.name.clone()).collect();
+    let edges = classes.iter()
+        .flat_map(|c| c.relationships.iter()
+            .map(move |r| format!("{} -> {} ({:?})", c.name, r.target, r.rel_type)))
+        .collect();
+    (names, edges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Relationship, RelationshipType};
+
+    fn class(name: &str, targets: &[&str]) -> ClassInfo {
+        ClassInfo {
+            name: name.to_string(),
+            methods: vec![],
+            properties: vec![],
+            relationships: targets.iter().map(|t| Relationship {
+                target: t.to_string(),
+                rel_type: RelationshipType::Dependency,
+                label: None,
+                member_visibility: None,
+                provenance: None,
+                cardinality: None,
+            }).collect(),
+            stereotype: None,
+            language: None,
+            line: 1,
+            column: 1,
+            out_of_line: false,
+            package: None,
+        }
+    }
+
+    #[test]
+    fn test_diff_reports_added_class() {
+        let baseline = vec![class("Order", &[])];
+        let json = serde_json::to_string(&baseline).unwrap();
+        let baseline: Vec<ClassInfo> = serde_json::from_str(&json).unwrap();
+
+        let current = vec![class("Order", &["Invoice"]), class("Invoice", &[])];
+        let diff = diff_models(&baseline, &current);
+
+        assert_eq!(diff.added_classes, vec!["Invoice"]);
+        assert!(diff.removed_classes.is_empty());
+        assert_eq!(diff.added_relationships, vec!["Order -> Invoice (Dependency)"]);
+        assert!(diff.report().contains("+ class Invoice"));
+    }
+
+    #[test]
+    fn test_diff_identical_models_is_empty() {
+        let classes = vec![class("Order", &["Invoice"])];
+        assert!(diff_models(&classes, &classes).is_empty());
+    }
+}
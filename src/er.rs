@@ -0,0 +1,97 @@
+use crate::mermaid::MermaidOptions;
+use crate::models::{ClassInfo, RelationshipType};
+use std::fmt::Write;
+
+/// Renders an entity-relationship diagram: each class becomes an entity whose
+/// visible properties are its attributes. Output is sorted, like [`crate::mermaid::generate_mermaid`].
+pub fn generate_er(classes: &[ClassInfo], options: &MermaidOptions) -> String {
+    let mut diagram = String::new();
+    writeln!(&mut diagram, "erDiagram").unwrap();
+
+    let mut classes: Vec<&ClassInfo> = classes.iter().collect();
+    classes.sort_by(|a, b| a.name.cmp(&b.name));
+
+    for class in &classes {
+        writeln!(&mut diagram, "    {} {{", entity_name(&class.name)).unwrap();
+        for prop in class.properties.iter().filter(|p| options.visibilities.contains(&p.visibility)) {
+            writeln!(&mut diagram, "        {} {}", attribute_type(class, &prop.name), prop.name).unwrap();
+        }
+        writeln!(&mut diagram, "    }}").unwrap();
+    }
+
+    let mut edges: Vec<(&str, &str, &RelationshipType, Option<&str>)> = classes.iter()
+        .flat_map(|class| class.relationships.iter()
+            .filter(|rel| options.include_private_in_dependencies
+                || rel.member_visibility.is_none_or(|v| options.visibilities.contains(&v)))
+            .map(move |rel| (class.name.as_str(), rel.target.as_str(), &rel.rel_type, rel.label.as_deref())))
+        .collect();
+    edges.sort();
+    edges.dedup();
+
+    for (source, target, rel_type, label) in edges {
+        let (cardinality, default_label) = notation(rel_type);
+        writeln!(
+            &mut diagram,
+            "    {} {} {} : \"{}\"",
+            entity_name(source), cardinality, entity_name(target), label.unwrap_or(default_label)
+        ).unwrap();
+    }
+
+    // erDiagram has no notes, so the legend is a comment block
+    if options.legend {
+        writeln!(&mut diagram, "    %% Legend").unwrap();
+        for rel_type in RelationshipType::ALL {
+            writeln!(&mut diagram, "    %% {} {}", notation(&rel_type).0, rel_type.name()).unwrap();
+        }
+    }
+
+    diagram
+}
+
+/// The cardinality notation and fallback line label for a relationship type.
+fn notation(rel_type: &RelationshipType) -> (&'static str, &'static str) {
+    match rel_type {
+        RelationshipType::Inheritance => ("||--||", "inherits"),
+        RelationshipType::Realization => ("||..||", "implements"),
+        RelationshipType::Composition => ("||--|{", "owns"),
+        RelationshipType::Aggregation => ("||--o{", "has"),
+        RelationshipType::Dependency => ("}o..o{", "uses"),
+    }
+}
+
+/// Entity names may only contain letters, digits, `-` and `_`.
+fn entity_name(name: &str) -> String {
+    name.replace(|c: char| !c.is_alphanumeric() && c != '_' && c != '-', "_")
+}
+
+/// Properties carry no type in the model; a field-labelled edge names it when present.
+fn attribute_type(class: &ClassInfo, property: &str) -> String {
+    class.relationships.iter()
+        .filter(|r| matches!(r.rel_type, RelationshipType::Composition | RelationshipType::Aggregation))
+        .find(|r| r.label.as_deref() == Some(property))
+        .map_or_else(|| "any".to_string(), |r| entity_name(&r.target))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::{java::JavaParser, LanguageParser};
+    use anyhow::Result;
+
+    #[test]
+    fn test_generate_er() -> Result<()> {
+        let classes = JavaParser.parse("
+public class Order extends Document {
+    public Customer customer;
+    public int total;
+}
+")?;
+        let output = generate_er(&classes, &MermaidOptions::default());
+        assert!(output.starts_with("erDiagram\n"));
+        assert!(output.contains("    Order {\n        Customer customer\n        any total\n    }"));
+        assert!(output.contains("    Order ||--|| Document : \"inherits\""));
+        assert!(output.contains("    Order ||--o{ Customer : \"customer\""));
+        assert_eq!(entity_name("shop::Order"), "shop__Order");
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+use clap::ValueEnum;
+use env_logger::{Builder, Logger};
+use log::{Level, LevelFilter};
+use std::io::Write;
+
+/// Verbosity for `--log-level`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => LevelFilter::Error,
+            LogLevel::Warn => LevelFilter::Warn,
+            LogLevel::Info => LevelFilter::Info,
+            LogLevel::Debug => LevelFilter::Debug,
+            LogLevel::Trace => LevelFilter::Trace,
+        }
+    }
+}
+
+/// Builds the stderr logger: `info` by default, then `RUST_LOG` directives,
+/// then `--log-level`. Info messages print bare; other levels get a prefix
+/// (`Warning: ...`), matching the tool's plain progress output.
+pub fn build_logger(level: Option<LogLevel>, rust_log: Option<&str>) -> Logger {
+    let mut builder = Builder::new();
+    builder.filter_level(LevelFilter::Info);
+    if let Some(filters) = rust_log {
+        builder.parse_filters(filters);
+    }
+    if let Some(level) = level {
+        builder.filter_level(level.into());
+    }
+    builder.format(|buf, record| match record.level() {
+        Level::Info => writeln!(buf, "{}", record.args()),
+        Level::Error => writeln!(buf, "Error: {}", record.args()),
+        Level::Warn => writeln!(buf, "Warning: {}", record.args()),
+        Level::Debug => writeln!(buf, "Debug: {}", record.args()),
+        Level::Trace => writeln!(buf, "Trace: {}", record.args()),
+    });
+    builder.build()
+}
+
+/// Installs [`build_logger`] as the global logger.
+pub fn init(level: Option<LogLevel>) {
+    let rust_log = std::env::var("RUST_LOG").ok();
+    let logger = build_logger(level, rust_log.as_deref());
+    log::set_max_level(logger.filter());
+    // Only fails if a logger is already installed
+    let _ = log::set_boxed_logger(Box::new(logger));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::{Log, Metadata};
+
+    fn enabled(logger: &Logger, level: Level) -> bool {
+        logger.enabled(&Metadata::builder().level(level).target("marco_polo").build())
+    }
+
+    #[test]
+    fn test_error_level_suppresses_info_but_not_errors() {
+        let logger = build_logger(Some(LogLevel::Error), None);
+        assert!(enabled(&logger, Level::Error));
+        assert!(!enabled(&logger, Level::Warn));
+        assert!(!enabled(&logger, Level::Info));
+    }
+
+    #[test]
+    fn test_default_level_and_rust_log() {
+        let logger = build_logger(None, None);
+        assert!(enabled(&logger, Level::Info));
+        assert!(!enabled(&logger, Level::Debug));
+
+        let logger = build_logger(None, Some("debug"));
+        assert!(enabled(&logger, Level::Debug));
+
+        // The flag wins over RUST_LOG
+        let logger = build_logger(Some(LogLevel::Warn), Some("debug"));
+        assert!(!enabled(&logger, Level::Info));
+    }
+}
@@ -1,13 +1,80 @@
-use clap::Parser;
-use std::path::PathBuf;
-use anyhow::Result;
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches, Parser, ValueEnum};
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
 use std::fs;
-use crate::models::Visibility;
+use std::io::{self, Write};
+use std::process::ExitCode;
+use std::time::{Duration, Instant, SystemTime};
+use crate::models::{ClassInfo, FileReport, RelationshipType, Visibility};
+use log::{info, warn};
 
 mod models;
 mod scanner;
 mod parsers;
 mod mermaid;
+mod transform;
+mod svg;
+mod diff;
+mod stats;
+mod clipboard;
+mod remote;
+mod profile;
+mod rules;
+mod er;
+mod logging;
+mod checks;
+mod markdown;
+mod structurizr;
+mod archive;
+mod tui;
+#[cfg(test)]
+mod testing;
+
+const DEFAULT_EXTENSIONS: &str = "py,java,cpp,rb";
+
+/// What to do when more classes are found than `--max-classes` allows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OnOverflow {
+    /// Keep only the most connected classes
+    Truncate,
+    /// Abort without writing a diagram
+    Error,
+}
+
+/// Language used to parse `.h` headers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum HeaderLanguage {
+    Cpp,
+    C,
+}
+
+/// Output format of the generated diagram.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Mermaid class diagram source
+    Mermaid,
+    /// SVG rendered by mermaid-cli (`mmdc`)
+    Svg,
+    /// JSON export of the extracted model (usable as a `--baseline`)
+    Json,
+    /// Mermaid entity-relationship diagram
+    Er,
+    /// Structurizr DSL workspace with a component view
+    Structurizr,
+}
+
+impl OutputFormat {
+    /// File extension used when naming output in `--output-dir`.
+    fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Mermaid | OutputFormat::Er => "mmd",
+            OutputFormat::Svg => "svg",
+            OutputFormat::Json => "json",
+            OutputFormat::Structurizr => "dsl",
+        }
+    }
+}
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "A CLI tool to cartograph codebases", long_about = None)]
@@ -16,52 +83,979 @@ struct Args {
     #[arg(default_value = ".")]
     path: PathBuf,
 
-    /// Output file path for the Mermaid diagram
+    /// Shallow-clone this git repository into a temporary directory and scan it instead of PATH
+    #[arg(long)]
+    git_url: Option<String>,
+
+    /// Branch or tag to check out with --git-url
+    #[arg(long, requires = "git_url")]
+    git_ref: Option<String>,
+
+    /// Read the sources from a tar archive on stdin instead of scanning PATH
+    #[arg(long, conflicts_with = "git_url")]
+    tar_stdin: bool,
+
+    /// Also scan test code (`test/` directories, `*_test.*`, `*Test.java`, `test_*.py`, `*_spec.rb`)
+    #[arg(long)]
+    include_tests: bool,
+
+    /// Only scan files modified within a duration (`7d`, `24h`, `30m`) or since a date (`2024-05-01`)
+    #[arg(long, value_parser = scanner::parse_since)]
+    since: Option<SystemTime>,
+
+    /// Output file path for the Mermaid diagram (`-` for stdout)
     #[arg(short, long, default_value = "output.mmd")]
     output: PathBuf,
 
+    /// Write `diagram.<ext>` (extension chosen by --format) into this directory, creating it if needed
+    #[arg(long, conflicts_with = "output")]
+    output_dir: Option<PathBuf>,
+
+    /// Copy the diagram to the system clipboard instead of writing `--output`
+    #[arg(long)]
+    clipboard: bool,
+
+    /// Write Windows (CRLF) line endings instead of LF
+    #[arg(long)]
+    crlf: bool,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Mermaid)]
+    format: OutputFormat,
+
     /// File extensions to include (comma-separated)
-    #[arg(short, long, value_delimiter = ',', default_value = "py,java,cpp,rb")]
+    #[arg(short, long, env = "MARCO_POLO_EXTENSIONS", value_delimiter = ',', default_value = DEFAULT_EXTENSIONS)]
     extensions: Vec<String>,
 
     /// Visibility levels to include (comma-separated: public,protected,private,internal)
-    #[arg(short, long, value_delimiter = ',', default_values_t = vec![Visibility::Public])]
+    #[arg(short, long, env = "MARCO_POLO_VISIBILITY", value_delimiter = ',', default_values_t = vec![Visibility::Public])]
     visibility: Vec<Visibility>,
+
+    /// Whether `--visibility` was given on the command line rather than taken
+    /// from `MARCO_POLO_VISIBILITY` or the default; set by [`Args::from_argv`]
+    #[arg(skip)]
+    visibility_from_command_line: bool,
+
+    /// Leave properties out of class boxes
+    #[arg(long)]
+    no_properties: bool,
+
+    /// Relationship types to draw (comma-separated: inheritance,realization,composition,aggregation,dependency)
+    #[arg(long, value_delimiter = ',')]
+    relationships: Vec<RelationshipType>,
+
+    /// Draw every relationship to an unparsed type to one shared `<<external>>` node
+    #[arg(long)]
+    collapse_external: bool,
+
+    /// Public API view: public methods with inheritance/realization edges only (implies --no-properties)
+    #[arg(long)]
+    interfaces_summary: bool,
+
+    /// Merge classes sharing a qualified name (e.g. C++ header declarations and source definitions)
+    #[arg(long)]
+    merge_classes: bool,
+
+    /// Remove abstract classes/interfaces in the middle of inheritance chains, drawing subclasses to the next kept base
+    #[arg(long)]
+    flatten_inheritance: bool,
+
+    /// Remove this leading namespace (e.g. `com.example`) from class names and relationship targets
+    #[arg(long)]
+    strip_prefix: Option<String>,
+
+    /// Detect and remove the longest namespace shared by all classes
+    #[arg(long, conflicts_with = "strip_prefix")]
+    strip_common_prefix: bool,
+
+    /// Add a tooltip with the defining `path:line` to each class
+    #[arg(long)]
+    tooltips: bool,
+
+    /// Render single-abstract-method interfaces as a compact `<<functional>>` stereotype
+    #[arg(long)]
+    collapse_single_method_interfaces: bool,
+
+    /// Tag each class with the language it was parsed from
+    #[arg(long)]
+    annotate_source_lang: bool,
+
+    /// Maximum number of classes to render
+    #[arg(long)]
+    max_classes: Option<usize>,
+
+    /// Behaviour when --max-classes is exceeded
+    #[arg(long, value_enum, default_value_t = OnOverflow::Truncate)]
+    on_overflow: OnOverflow,
+
+    /// Only draw dependencies seen at least this many times between the same two classes
+    #[arg(long, default_value_t = 1)]
+    min_dependency_count: usize,
+
+    /// Skip a file (with a warning) when parsing it takes longer than this many milliseconds
+    #[arg(long, value_name = "MS")]
+    parser_timeout: Option<u64>,
+
+    /// Maximum nesting depth when resolving generic/template types
+    #[arg(long, default_value_t = parsers::DEFAULT_MAX_TYPE_DEPTH)]
+    max_type_depth: usize,
+
+    /// Read YARD `@param [Type]`/`@return [Type]` comments as Ruby dependencies
+    #[arg(long)]
+    ruby_yard: bool,
+
+    /// Parse `.h` headers as C++ or C
+    #[arg(long, value_enum, default_value_t = HeaderLanguage::Cpp)]
+    h_as: HeaderLanguage,
+
+    /// Record dependencies on types instantiated inside Java method bodies (slower)
+    #[arg(long)]
+    deep_deps: bool,
+
+    /// Extra type names to treat as builtins and drop from relationships (comma-separated)
+    #[arg(long, value_delimiter = ',')]
+    extra_builtins: Vec<String>,
+
+    /// Type names to keep even though they are builtins, e.g. `String` (comma-separated)
+    #[arg(long, value_delimiter = ',')]
+    builtin_unfilter: Vec<String>,
+
+    /// Keep C++ classes and structs defined inside function bodies
+    #[arg(long)]
+    include_local_classes: bool,
+
+    /// Collapse C++ getter/setter methods around a private field into one public property
+    #[arg(long)]
+    collapse_accessor_pairs: bool,
+
+    /// Truncate member lines longer than this many characters
+    #[arg(long)]
+    wrap_width: Option<usize>,
+
+    /// Write inheritance/realization arrows from the parent (`Parent <|-- Child`) or the child (`Child --|> Parent`)
+    #[arg(long, value_enum, default_value_t = mermaid::ArrowFrom::Parent)]
+    arrow_from: mermaid::ArrowFrom,
+
+    /// Nest classes in Mermaid `namespace` blocks following their qualified names (`A::B::C`, `a.b.C`)
+    #[arg(long)]
+    group_namespaces: bool,
+
+    /// Show short class names in boxes, keeping qualified names as node IDs
+    #[arg(long)]
+    collapse_namespaces_in_labels: bool,
+
+    /// Repeat members inherited from parsed base classes, marked `(inherited)`
+    #[arg(long)]
+    show_inherited_members: bool,
+
+    /// Draw composition/aggregation field names as roles on the target end
+    #[arg(long)]
+    role_notes: bool,
+
+    /// Note which field, parameter or base clause each relationship comes from
+    #[arg(long)]
+    explain: bool,
+
+    /// Append a legend explaining each relationship arrow
+    #[arg(long)]
+    legend: bool,
+
+    /// Add notes from a file of `ClassName: note text` lines
+    #[arg(long)]
+    notes: Option<PathBuf>,
+
+    /// Mark `@Override` methods with `<<override>>` (`@Deprecated` ones are always marked)
+    #[arg(long)]
+    show_overrides: bool,
+
+    /// Annotate properties with qualifiers such as C++ `mutable` and `volatile`
+    #[arg(long)]
+    show_qualifiers: bool,
+
+    /// Draw relationships from members hidden by --visibility (e.g. private fields)
+    #[arg(long)]
+    include_private_in_dependencies: bool,
+
+    /// Relationship rules file (default: `marco_polo.toml` in the scanned root, if present)
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Replace the diagram between `<!-- marco-polo:start -->` and `<!-- marco-polo:end -->` in this Markdown file
+    #[arg(long, conflicts_with_all = ["output", "output_dir", "clipboard", "split_by_package"])]
+    update: Option<PathBuf>,
+
+    /// Wrap the diagram in this file's text, substituted for its `{{diagram}}` placeholder
+    #[arg(long, conflicts_with = "update")]
+    output_template: Option<PathBuf>,
+
+    /// Write one diagram per top-level package/namespace (`output_<package>.mmd`)
+    #[arg(long)]
+    split_by_package: bool,
+
+    /// Split by package as with --split-by-package, but only when more than N classes are found
+    #[arg(long, value_name = "N", conflicts_with_all = ["split_by_package", "update"])]
+    auto_split: Option<usize>,
+
+    /// With --split-by-package, keep cross-package relationships, drawn to `<<external>>` stubs
+    #[arg(long, requires = "split_by_package")]
+    cross_package_stubs: bool,
+
+    /// Exit with a non-zero code when a condition holds (`cycles`: exit code 3)
+    #[arg(long, value_enum, value_delimiter = ',')]
+    fail_on: Vec<checks::FailOn>,
+
+    /// Exit with a non-zero code (4) when a `Source->Target` relationship exists (repeatable)
+    #[arg(long)]
+    forbid: Vec<checks::ForbiddenEdge>,
+
+    /// Report classes/relationships added or removed since a JSON model exported with `--format json`
+    #[arg(long)]
+    baseline: Option<PathBuf>,
+
+    /// Write the `--baseline` report to this file instead of stderr
+    #[arg(long, requires = "baseline")]
+    diff_output: Option<PathBuf>,
+
+    /// Print per-class and total metrics as JSON instead of a diagram, then exit
+    #[arg(long)]
+    stats_only: bool,
+
+    /// Print wall-clock parse time per language to stderr when done
+    #[arg(long)]
+    profile: bool,
+
+    /// Log verbosity (default: info; `RUST_LOG` is honoured when this is not given)
+    #[arg(long, value_enum)]
+    log_level: Option<logging::LogLevel>,
+
+    /// Exit with status 0 even when no classes are found
+    #[arg(long)]
+    allow_empty: bool,
+
+    /// Do not write a diagram when no classes are found
+    #[arg(long)]
+    skip_empty_output: bool,
+
+    /// Browse the parsed classes in an interactive terminal UI instead of writing a diagram
+    #[arg(long, conflicts_with_all = ["clipboard", "update", "split_by_package"])]
+    tui: bool,
+
+    /// List supported languages and their file extensions, then exit
+    #[arg(long)]
+    list_languages: bool,
+}
+
+impl Args {
+    /// Parses `argv` like [`Parser::parse_from`], also recording where `--visibility` came from.
+    fn from_argv<I, T>(argv: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<std::ffi::OsString> + Clone,
+    {
+        let matches = Self::command().get_matches_from(argv);
+        let mut args = Self::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+        args.visibility_from_command_line = matches.value_source("visibility") == Some(ValueSource::CommandLine);
+        args
+    }
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
-    eprintln!("Scanning path: {:?}", args.path);
+fn main() -> Result<ExitCode> {
+    let args = Args::from_argv(std::env::args_os());
+    logging::init(args.log_level);
+    run(args)
+}
 
-    // 1. Find Files
+fn run(args: Args) -> Result<ExitCode> {
+    if args.list_languages {
+        let defaults: Vec<&str> = DEFAULT_EXTENSIONS.split(',').collect();
+        print!("{}", parsers::languages_report(&defaults));
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    // Checked before anything is scanned or rendered (`svg` would run mmdc first)
+    if args.update.is_some() && !matches!(args.format, OutputFormat::Mermaid | OutputFormat::Er) {
+        anyhow::bail!("--update embeds Mermaid source; use --format mermaid or er");
+    }
+
+    // Removed when dropped, including when a later step fails
+    let checkout = match &args.git_url {
+        Some(url) => Some(remote::shallow_clone(url, args.git_ref.as_deref())?),
+        None => None,
+    };
+    let root = checkout.as_ref().map_or(args.path.as_path(), |c| c.path());
+
+    // 1. Find Files; sources read from a tar stream are already in memory
     let extensions: Vec<&str> = args.extensions.iter().map(|s| s.as_str()).collect();
-    let files = scanner::find_source_files(&args.path, &extensions)?;
-    eprintln!("Found {} files with extensions {:?}.", files.len(), extensions);
+    let sources: Vec<(PathBuf, Option<String>)> = if args.tar_stdin {
+        info!("Reading a tar archive from stdin");
+        let files = archive::read_tar(io::stdin().lock(), &extensions, args.include_tests, args.since)?;
+        info!("Found {} files with extensions {:?}.", files.len(), extensions);
+        files.into_iter().map(|(path, content)| (path, Some(content))).collect()
+    } else {
+        info!("Scanning path: {:?}", root);
+        let files = scanner::find_source_files(root, &extensions, args.include_tests, args.since)?;
+        info!("Found {} files with extensions {:?}.", files.len(), extensions);
+        files.into_iter().map(|path| (path, None)).collect()
+    };
 
-    let mut all_classes = Vec::new();
+    let parse_options = parsers::ParseOptions {
+        ruby_yard: args.ruby_yard,
+        max_type_depth: args.max_type_depth,
+        deep_deps: args.deep_deps,
+        extra_builtins: args.extra_builtins.clone(),
+        builtin_unfilter: args.builtin_unfilter.clone(),
+        include_local_classes: args.include_local_classes,
+        collapse_accessor_pairs: args.collapse_accessor_pairs,
+        timeout: args.parser_timeout.map(Duration::from_millis),
+    };
+    let mut reports = Vec::new();
+    let mut profile = profile::ParseProfile::default();
 
     // 2. Parse Each File
-    for file_path in files {
+    for (file_path, content) in sources {
         let ext = file_path.extension().and_then(|s| s.to_str()).unwrap_or("");
         
-        if let Some(parser) = parsers::get_parser(ext) {
-            eprintln!("Parsing: {:?}", file_path);
-            let content = fs::read_to_string(&file_path)?;
-            let classes = parser.parse(&content)?;
-            all_classes.extend(classes);
+        let parser = match (ext, args.h_as) {
+            ("h", HeaderLanguage::C) => parsers::get_parser_by_name("c"),
+            _ => parsers::get_parser(ext),
+        };
+
+        if let Some(parser) = parser {
+            info!("Parsing: {:?}", file_path);
+            let content = match content {
+                Some(content) => content,
+                None => fs::read_to_string(&file_path)?,
+            };
+            let start = Instant::now();
+            let classes = match parser.parse_with_options(&content, &parse_options) {
+                Ok(classes) => classes,
+                Err(err) if err.downcast_ref::<parsers::ParseTimeout>().is_some() => {
+                    warn!("Skipping {:?}: {}", file_path, err.root_cause());
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            profile.record(parser.name(), start.elapsed());
+            reports.push(FileReport { path: file_path, classes });
         } else {
-            eprintln!("Skipping {:?}: No parser found for extension '{}'", file_path, ext);
+            info!("Skipping {:?}: No parser found for extension '{}'", file_path, ext);
         }
     }
 
-    eprintln!("Extracted {} classes.", all_classes.len());
+    if args.profile {
+        eprint!("{}", profile.summary());
+    }
+
+    let all_classes: Vec<_> = reports.iter()
+        .flat_map(|report| report.classes.iter().cloned())
+        .collect();
+    let mut all_classes = transform::drop_undeclared_out_of_line(all_classes);
+
+    let config_path = args.config.clone().or_else(|| {
+        Some(root.join(rules::CONFIG_FILE)).filter(|path| path.is_file())
+    });
+    if let Some(config_path) = config_path {
+        let config = rules::load_config(&config_path)?;
+        rules::apply_rules(&mut all_classes, &config.rules);
+    }
+
+    if args.min_dependency_count > 1 {
+        all_classes = transform::drop_weak_dependencies(all_classes, args.min_dependency_count);
+    }
+
+    if args.merge_classes {
+        all_classes = transform::merge_classes(all_classes);
+    }
+
+    if args.flatten_inheritance {
+        all_classes = transform::flatten_inheritance(all_classes);
+    }
+
+    // Before any renaming, so --forbid rules match the qualified names
+    if let Some(code) = checks::run_gates(&all_classes, &args.fail_on, &args.forbid) {
+        return Ok(ExitCode::from(code));
+    }
+
+    let prefix = if args.strip_common_prefix {
+        transform::common_prefix(&all_classes)
+    } else {
+        args.strip_prefix.clone()
+    };
+    if let Some(prefix) = prefix {
+        info!("Stripping namespace prefix '{}'.", prefix);
+        all_classes = transform::strip_prefix(all_classes, &prefix);
+        // Tooltips look classes up in the reports by their rendered names
+        for report in &mut reports {
+            report.classes = transform::strip_prefix(std::mem::take(&mut report.classes), &prefix);
+        }
+    }
+
+    info!("Extracted {} classes.", all_classes.len());
+
+    let exit_code = if all_classes.is_empty() && !args.allow_empty {
+        warn!("No classes found; exiting with status {} (pass --allow-empty to succeed).", checks::EXIT_EMPTY);
+        ExitCode::from(checks::EXIT_EMPTY)
+    } else {
+        ExitCode::SUCCESS
+    };
+    if all_classes.is_empty() && args.skip_empty_output {
+        info!("Skipping output for an empty diagram.");
+        return Ok(exit_code);
+    }
+
+    if args.stats_only {
+        println!("{}", serde_json::to_string_pretty(&stats::compute_stats(&all_classes))?);
+        return Ok(exit_code);
+    }
+
+    // Against the full model, so classes cut by --max-classes are not "removed"
+    if let Some(baseline_path) = &args.baseline {
+        let baseline_json = fs::read_to_string(baseline_path)
+            .with_context(|| format!("Failed to read baseline {:?}", baseline_path))?;
+        let baseline: Vec<ClassInfo> = serde_json::from_str(&baseline_json)
+            .with_context(|| format!("Invalid baseline model {:?}", baseline_path))?;
+        let model_diff = diff::diff_models(&baseline, &all_classes);
+        if model_diff.is_empty() {
+            info!("No changes since baseline {:?}.", baseline_path);
+        }
+        let report = model_diff.report();
+        match &args.diff_output {
+            Some(path) => fs::write(path, &report)?,
+            None => eprint!("{}", report),
+        }
+    }
+
+    if let Some(max) = args.max_classes {
+        if all_classes.len() > max {
+            warn!("{} classes exceed --max-classes {}.", all_classes.len(), max);
+            match args.on_overflow {
+                OnOverflow::Truncate => {
+                    all_classes = transform::keep_most_connected(all_classes, max);
+                    info!("Keeping the {} most connected classes.", max);
+                }
+                OnOverflow::Error => anyhow::bail!("Too many classes to render ({} > {})", all_classes.len(), max),
+            }
+        }
+    }
+
+    // `--interfaces-summary` is a preset; explicit `--visibility`/`--relationships` flags still win
+    let relationship_types = match (&args.relationships[..], args.interfaces_summary) {
+        ([], true) => vec![RelationshipType::Inheritance, RelationshipType::Realization],
+        (types, _) => types.to_vec(),
+    };
+    if !relationship_types.is_empty() {
+        for class in &mut all_classes {
+            class.relationships.retain(|r| relationship_types.contains(&r.rel_type));
+        }
+    }
+    if args.no_properties || args.interfaces_summary {
+        for class in &mut all_classes {
+            class.properties.clear();
+        }
+    }
+    if args.collapse_external {
+        all_classes = transform::collapse_external(all_classes);
+    }
+
+    let notes = match &args.notes {
+        Some(path) => {
+            let text = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read notes {:?}", path))?;
+            let notes = mermaid::parse_notes(&text)
+                .with_context(|| format!("Invalid notes file {:?}", path))?;
+            for class in notes.keys().filter(|name| !all_classes.iter().any(|c| c.name == **name)) {
+                warn!("note for unknown class '{}' in {:?}", class, path);
+            }
+            notes
+        }
+        None => Default::default(),
+    };
 
     // 3. Generate Diagram
-    let diagram = mermaid::generate_mermaid(&all_classes, &args.visibility);
+    let mermaid_options = mermaid::MermaidOptions {
+        visibilities: if args.interfaces_summary && !args.visibility_from_command_line {
+            vec![Visibility::Public]
+        } else {
+            args.visibility.clone()
+        },
+        collapse_functional_interfaces: args.collapse_single_method_interfaces,
+        annotate_source_lang: args.annotate_source_lang,
+        include_private_in_dependencies: args.include_private_in_dependencies,
+        wrap_width: args.wrap_width,
+        show_overrides: args.show_overrides,
+        show_qualifiers: args.show_qualifiers,
+        arrow_from: args.arrow_from,
+        group_namespaces: args.group_namespaces,
+        collapse_namespaces_in_labels: args.collapse_namespaces_in_labels,
+        show_inherited_members: args.show_inherited_members,
+        role_notes: args.role_notes,
+        legend: args.legend,
+        notes,
+        explain: args.explain,
+    };
+    if args.tui {
+        tui::run(&all_classes, &mermaid_options)?;
+        return Ok(exit_code);
+    }
+
+    let template = match &args.output_template {
+        Some(path) => Some((path, fs::read_to_string(path)
+            .with_context(|| format!("Failed to read template {:?}", path))?)),
+        None => None,
+    };
+    let render = |classes: &[ClassInfo]| -> Result<String> {
+        let diagram = render_diagram(&args, classes, &reports, &mermaid_options)?;
+        match &template {
+            Some((path, template)) => {
+                let filled = markdown::fill_template(template, &diagram)
+                    .with_context(|| format!("Invalid template {:?}", path))?;
+                Ok(line_endings(&filled, args.crlf))
+            }
+            None => Ok(diagram),
+        }
+    };
+
+    let auto_split = args.auto_split.filter(|&max| all_classes.len() > max);
+    if args.split_by_package || auto_split.is_some() {
+        let flag = if args.split_by_package { "--split-by-package" } else { "--auto-split" };
+        if args.clipboard || args.output == Path::new("-") {
+            anyhow::bail!("{} writes one file per package; it cannot be used with --clipboard or stdout", flag);
+        }
+        let written = write_per_package(&output_path(&args)?, &all_classes, args.cross_package_stubs, render)?;
+        if let Some(max) = auto_split {
+            info!("{} classes exceed --auto-split {}; split by package into:", all_classes.len(), max);
+            for path in &written {
+                info!("  {}", path.display());
+            }
+        }
+        info!("Wrote {} package diagrams.", written.len());
+        return Ok(exit_code);
+    }
+
+    let diagram = render(&all_classes)?;
+
+    if let Some(path) = &args.update {
+        let document = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {:?}", path))?;
+        let updated = markdown::replace_between_markers(&document, &diagram, args.crlf)
+            .with_context(|| format!("Cannot update {:?}", path))?;
+        fs::write(path, updated)?;
+        info!("Updated the diagram in {:?}", path);
+        return Ok(exit_code);
+    }
 
     // 5. Write Output
-    fs::write(&args.output, diagram)?;
-    eprintln!("Successfully wrote Mermaid diagram to {:?}", args.output);
+    if args.clipboard {
+        clipboard::copy_to_clipboard(&diagram)?;
+    } else {
+        write_output(&output_path(&args)?, &diagram, &mut io::stdout())?;
+    }
+
+    Ok(exit_code)
+}
+
+/// Renders `classes` in the requested format. Tooltips are limited to the rendered classes.
+fn render_diagram(args: &Args, classes: &[ClassInfo], reports: &[FileReport], options: &mermaid::MermaidOptions) -> Result<String> {
+    let diagram = match args.format {
+        OutputFormat::Json => render_json(classes, args.explain)?,
+        OutputFormat::Er => er::generate_er(classes, options),
+        OutputFormat::Structurizr => structurizr::generate_structurizr(classes, options),
+        OutputFormat::Mermaid | OutputFormat::Svg => render_class_diagram(args, classes, reports, options)?,
+    };
+    Ok(line_endings(&diagram, args.crlf))
+}
+
+/// The model as JSON; relationship provenance is only kept with `--explain`.
+fn render_json(classes: &[ClassInfo], explain: bool) -> Result<String> {
+    if explain {
+        return Ok(serde_json::to_string_pretty(classes)? + "\n");
+    }
+    let mut classes = classes.to_vec();
+    for rel in classes.iter_mut().flat_map(|c| c.relationships.iter_mut()) {
+        rel.provenance = None;
+    }
+    Ok(serde_json::to_string_pretty(&classes)? + "\n")
+}
+
+/// A Mermaid class diagram with optional tooltips, rendered to SVG for `--format svg`.
+fn render_class_diagram(args: &Args, classes: &[ClassInfo], reports: &[FileReport], options: &mermaid::MermaidOptions) -> Result<String> {
+    let mut diagram = mermaid::generate_mermaid(classes, options);
+    if args.tooltips {
+        let rendered: Vec<FileReport> = reports.iter()
+            .map(|report| FileReport {
+                path: report.path.clone(),
+                classes: report.classes.iter().filter(|c| classes.iter().any(|r| r.name == c.name)).cloned().collect(),
+            })
+            .collect();
+        diagram.push_str(&mermaid::generate_tooltips(&rendered, options));
+    }
+    if args.format == OutputFormat::Svg {
+        diagram = svg::render_svg(svg::MMDC, &diagram)?;
+    }
+    Ok(diagram)
+}
 
+/// Normalises every line ending to `\n`, or to `\r\n` with `crlf`.
+fn line_endings(text: &str, crlf: bool) -> String {
+    let text = text.replace("\r\n", "\n");
+    if crlf {
+        text.replace('\n', "\r\n")
+    } else {
+        text
+    }
+}
+
+/// Writes one diagram per package next to `base`: `output.mmd` becomes
+/// `output_ui.mmd`, `output_core.mmd`, ... Returns the written paths.
+fn write_per_package(
+    base: &Path,
+    classes: &[ClassInfo],
+    external_stubs: bool,
+    render: impl Fn(&[ClassInfo]) -> Result<String>,
+) -> Result<Vec<PathBuf>> {
+    let stem = base.file_stem().and_then(|s| s.to_str()).unwrap_or("output");
+    let extension = base.extension().and_then(|s| s.to_str()).map(|e| format!(".{}", e)).unwrap_or_default();
+
+    let mut written = Vec::new();
+    for (package, classes) in transform::split_by_package(classes, external_stubs) {
+        let package = package.replace(|c: char| !c.is_alphanumeric() && c != '_' && c != '-', "_");
+        let path = base.with_file_name(format!("{}_{}{}", stem, package, extension));
+        fs::write(&path, render(&classes)?)
+            .with_context(|| format!("Failed to write {:?}", path))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// Resolves where to write the diagram: `--output`, or `diagram.<ext>` inside `--output-dir`.
+fn output_path(args: &Args) -> Result<PathBuf> {
+    match &args.output_dir {
+        Some(dir) => {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create output directory {:?}", dir))?;
+            Ok(dir.join(format!("diagram.{}", args.format.extension())))
+        }
+        None => Ok(args.output.clone()),
+    }
+}
+
+/// Writes the diagram to `path`, or to `stdout` when `path` is `-`.
+fn write_output(path: &Path, diagram: &str, stdout: &mut impl Write) -> Result<()> {
+    if path == Path::new("-") {
+        stdout.write_all(diagram.as_bytes())?;
+        stdout.flush()?;
+    } else {
+        fs::write(path, diagram)?;
+        info!("Successfully wrote diagram to {:?}", path);
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_env_defaults() {
+        // Other tests parse arguments concurrently, so the process environment is
+        // left alone: check the declared variables, then that their values parse
+        // as the flags do (clap lets a flag win over its variable)
+        let command = Args::command();
+        let env_of = |id: &str| {
+            command.get_arguments()
+                .find(|arg| arg.get_id() == id)
+                .and_then(|arg| arg.get_env())
+                .and_then(|env| env.to_str())
+                .map(str::to_string)
+        };
+        assert_eq!(env_of("extensions").as_deref(), Some("MARCO_POLO_EXTENSIONS"));
+        assert_eq!(env_of("visibility").as_deref(), Some("MARCO_POLO_VISIBILITY"));
+
+        let from_values = Args::try_parse_from(["marco-polo", "-e", "java,rb", "-v", "public,private"]).unwrap();
+        assert_eq!(from_values.extensions, vec!["java", "rb"]);
+        assert_eq!(from_values.visibility, vec![Visibility::Public, Visibility::Private]);
+    }
+
+    #[test]
+    fn test_output_dir_names_file_by_format() -> Result<()> {
+        let temp = TempDir::new("out");
+        let dir = temp.path().join("diagrams");
+        let dir_arg = dir.to_str().unwrap();
+
+        let args = Args::try_parse_from(["marco-polo", "--format", "json", "--output-dir", dir_arg]).unwrap();
+        let path = output_path(&args)?;
+        assert_eq!(path, dir.join("diagram.json"));
+        assert!(dir.is_dir());
+
+        let args = Args::try_parse_from(["marco-polo", "--output-dir", dir_arg]).unwrap();
+        assert_eq!(output_path(&args)?, dir.join("diagram.mmd"));
+
+        assert!(Args::try_parse_from(["marco-polo", "-o", "a.mmd", "--output-dir", dir_arg]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_output_dash_goes_to_stdout() -> Result<()> {
+        let mut stdout = Vec::new();
+        write_output(Path::new("-"), "classDiagram\n", &mut stdout)?;
+
+        assert_eq!(String::from_utf8(stdout)?, "classDiagram\n");
+        assert!(!Path::new("-").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_by_package_writes_a_file_per_package() -> Result<()> {
+        let classes = parsers::get_parser("cpp").unwrap().parse("
+namespace ui { class Widget { public: core::Config* config; }; }
+namespace core { class Config {}; }
+")?;
+        let temp = TempDir::new("split");
+        let dir = temp.path();
+
+        let written = write_per_package(&dir.join("output.mmd"), &classes, false, |classes| {
+            Ok(mermaid::generate_mermaid(classes, &mermaid::MermaidOptions::default()))
+        })?;
+        assert_eq!(written, vec![dir.join("output_core.mmd"), dir.join("output_ui.mmd")]);
+        let ui = fs::read_to_string(dir.join("output_ui.mmd"))?;
+        assert!(ui.contains("class ui::Widget"));
+        assert!(!ui.contains("core::Config"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_auto_split() -> Result<()> {
+        let temp = TempDir::new("auto-split");
+        let dir = temp.path();
+        let src = dir.join("src");
+        fs::create_dir_all(&src)?;
+        fs::write(src.join("app.cpp"), "
+namespace ui { class Widget {}; class Canvas {}; }
+namespace core { class Config {}; }
+")?;
+        let output = dir.join("output.mmd");
+        let run_with = |threshold: &str| -> Result<Vec<String>> {
+            run(Args::parse_from([
+                "marco-polo".into(), src.clone().into_os_string(),
+                "-o".into(), output.clone().into_os_string(),
+                "--auto-split".into(), threshold.into(),
+            ]))?;
+            let mut files: Vec<String> = fs::read_dir(dir)?
+                .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+                .filter(|name| name.ends_with(".mmd"))
+                .collect();
+            files.sort();
+            for file in &files {
+                fs::remove_file(dir.join(file))?;
+            }
+            Ok(files)
+        };
+
+        assert_eq!(run_with("2")?, vec!["output_core.mmd", "output_ui.mmd"]);
+        assert_eq!(run_with("3")?, vec!["output.mmd"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty_scan_exit_code() -> Result<()> {
+        let temp = TempDir::new("empty");
+        let src = temp.path().join("src");
+        fs::create_dir_all(&src)?;
+        let output = temp.path().join("output.mmd");
+        let run_with = |flags: &[&str]| {
+            let mut argv = vec!["marco-polo".into(), src.clone().into_os_string(), "-o".into(), output.clone().into_os_string()];
+            argv.extend(flags.iter().map(Into::into));
+            run(Args::parse_from(argv))
+        };
+
+        assert_eq!(run_with(&[])?, ExitCode::from(checks::EXIT_EMPTY));
+        assert_eq!(fs::read_to_string(&output)?.trim(), "classDiagram");
+        fs::remove_file(&output)?;
+        assert_eq!(run_with(&["--allow-empty"])?, ExitCode::SUCCESS);
+        fs::remove_file(&output)?;
+        assert_eq!(run_with(&["--skip-empty-output"])?, ExitCode::from(checks::EXIT_EMPTY));
+        assert!(!output.exists());
+        assert_eq!(run_with(&["--stats-only"])?, ExitCode::from(checks::EXIT_EMPTY));
+        Ok(())
+    }
+
+    #[test]
+    fn test_baseline_diff_ignores_max_classes() -> Result<()> {
+        let temp = TempDir::new("baseline");
+        let dir = temp.path();
+        let src = dir.join("src");
+        fs::create_dir_all(&src)?;
+        fs::write(src.join("shop.py"), "class Order:\n    pass\nclass Cart:\n    pass\nclass Item:\n    pass\n")?;
+        let baseline = dir.join("baseline.json");
+        let report = dir.join("diff.txt");
+
+        run(Args::parse_from([
+            "marco-polo".into(), src.clone().into_os_string(),
+            "-o".into(), baseline.clone().into_os_string(), "--format".into(), "json".into(),
+        ]))?;
+        run(Args::parse_from([
+            "marco-polo".into(), src.into_os_string(),
+            "-o".into(), dir.join("output.mmd").into_os_string(),
+            "--baseline".into(), baseline.into_os_string(),
+            "--diff-output".into(), report.clone().into_os_string(),
+            "--max-classes".into(), "1".into(),
+        ]))?;
+        assert_eq!(fs::read_to_string(&report)?, "");
+        Ok(())
+    }
+
+    #[test]
+    fn test_tooltips_follow_stripped_names() -> Result<()> {
+        let temp = TempDir::new("tooltips");
+        let src = temp.path().join("src");
+        fs::create_dir_all(&src)?;
+        fs::write(src.join("shop.cpp"), "namespace app { class Order {}; }\n")?;
+        let output = temp.path().join("output.mmd");
+
+        run(Args::parse_from([
+            "marco-polo".into(), src.into_os_string(),
+            "-o".into(), output.clone().into_os_string(),
+            "--tooltips".into(), "--strip-prefix".into(), "app".into(),
+        ]))?;
+        let written = fs::read_to_string(&output)?;
+        assert!(written.contains("    class Order"));
+        assert!(written.contains("    click Order href"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_forbid_matches_qualified_names_with_strip_prefix() -> Result<()> {
+        let temp = TempDir::new("forbid");
+        fs::write(temp.path().join("app.cpp"), "namespace app { class Repo {}; class Controller { app::Repo* repo; }; }\n")?;
+
+        let code = run(Args::parse_from([
+            "marco-polo".into(), temp.path().as_os_str().to_owned(),
+            "-o".into(), temp.path().join("output.mmd").into_os_string(),
+            "--forbid".into(), "app::Controller->app::Repo".into(),
+            "--strip-prefix".into(), "app".into(),
+        ]))?;
+        assert_eq!(code, ExitCode::from(checks::EXIT_FORBIDDEN));
+        Ok(())
+    }
+
+    #[test]
+    fn test_output_template() -> Result<()> {
+        let temp = TempDir::new("template");
+        let dir = temp.path();
+        let src = dir.join("src");
+        fs::create_dir_all(&src)?;
+        fs::write(src.join("shop.py"), "class Order:\n    pass\n")?;
+        let template = dir.join("template.md");
+        let output = dir.join("output.md");
+        let run_with = |template_text: &str| {
+            fs::write(&template, template_text)?;
+            run(Args::parse_from([
+                "marco-polo".into(), src.clone().into_os_string(),
+                "-o".into(), output.clone().into_os_string(),
+                "--output-template".into(), template.clone().into_os_string(),
+            ]))
+        };
+
+        run_with("---\ntitle: Classes\n---\n{{< mermaid >}}\n{{diagram}}\n{{< /mermaid >}}\n")?;
+        let wrapped = fs::read_to_string(&output)?;
+        assert!(wrapped.starts_with("---\ntitle: Classes\n---\n{{< mermaid >}}\nclassDiagram\n"));
+        assert!(wrapped.contains("    class Order"));
+        assert!(wrapped.ends_with("\n{{< /mermaid >}}\n"));
+        let missing = run_with("{{< mermaid >}}\n{{< /mermaid >}}\n");
+        assert!(format!("{:#}", missing.unwrap_err()).contains("{{diagram}}"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_interfaces_summary() -> Result<()> {
+        let temp = TempDir::new("interfaces");
+        let dir = temp.path();
+        fs::write(dir.join("OrderService.java"), "
+public class OrderService extends BaseService implements Service {
+    public Repository repository;
+    public void place(Order order) {}
+    private void audit() {}
+}
+")?;
+        let output = dir.join("output.mmd");
+        let run_with = |flags: &[&str]| -> Result<String> {
+            let mut argv = vec!["marco-polo".into(), dir.as_os_str().to_owned(), "-o".into(), output.clone().into_os_string()];
+            argv.extend(flags.iter().map(Into::into));
+            run(Args::from_argv(argv))?;
+            Ok(fs::read_to_string(&output)?)
+        };
+        let summary = run_with(&["--interfaces-summary"])?;
+        assert!(summary.contains("        +place()\n"));
+        assert!(!summary.contains("audit"));
+        assert!(!summary.contains("repository"));
+        assert!(summary.contains("    BaseService <|-- OrderService\n"));
+        assert!(summary.contains("    Service <|.. OrderService\n"));
+        assert!(!summary.contains("..>"));
+        assert!(!summary.contains("o--"));
+
+        assert!(!Args::from_argv(["marco-polo", "--interfaces-summary"]).visibility_from_command_line);
+        assert!(Args::from_argv(["marco-polo", "-v", "private"]).visibility_from_command_line);
+
+        let overridden = run_with(&["--interfaces-summary", "--relationships", "dependency", "-v", "public,private"])?;
+        assert!(overridden.contains("-audit()"));
+        assert!(overridden.contains("    Order ..> OrderService\n"));
+        assert!(!overridden.contains("<|"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_checks_format_before_scanning() {
+        // The path does not exist, so getting past the check would fail differently
+        let missing = std::env::temp_dir().join(format!("marco-polo-update-missing-{}", std::process::id()));
+        let err = run(Args::parse_from([
+            "marco-polo".into(), missing.into_os_string(),
+            "--update".into(), "README.md".into(),
+            "--format".into(), "svg".into(),
+        ])).unwrap_err();
+        assert!(err.to_string().contains("--update embeds Mermaid source"));
+    }
+
+    #[test]
+    fn test_line_endings() -> Result<()> {
+        let classes = parsers::get_parser("py").unwrap().parse("class A:\r\n    pass\r\nclass B(A):\r\n    pass\r\n")?;
+        let diagram = mermaid::generate_mermaid(&classes, &mermaid::MermaidOptions::default());
+
+        let lf = line_endings(&diagram, false);
+        assert!(!lf.contains('\r'));
+        assert_eq!(lf, diagram);
+
+        let crlf = line_endings(&diagram, true);
+        assert_eq!(crlf.matches("\r\n").count(), diagram.matches('\n').count());
+        assert!(!crlf.replace("\r\n", "").contains('\n'));
+        // Already-CRLF text is not doubled up
+        assert_eq!(line_endings(&crlf, true), crlf);
+
+        // Every text format goes through the same conversion
+        for format in ["mermaid", "json", "er", "structurizr"] {
+            let args = Args::parse_from(["marco-polo", "--crlf", "--format", format]);
+            let rendered = render_diagram(&args, &classes, &[], &mermaid::MermaidOptions::default())?;
+            assert!(rendered.contains("\r\n"), "{} output should use CRLF", format);
+            assert!(!rendered.replace("\r\n", "").contains('\n'), "{} output has a bare LF", format);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parser_timeout_skips_file() -> Result<()> {
+        let temp = TempDir::new("timeout");
+        let dir = temp.path();
+        let huge: String = (0..200_000).map(|i| format!("class C{}(Base):\n    x: Foo = 1\n", i)).collect();
+        fs::write(dir.join("huge.py"), huge)?;
+        let output = dir.join("output.mmd");
+
+        let args = Args::parse_from([
+            "marco-polo".into(), dir.as_os_str().to_owned(),
+            "-o".into(), output.clone().into_os_string(),
+            "-e".into(), "py".into(), "--parser-timeout".into(), "1".into(),
+        ]);
+        assert_eq!(run(args)?, ExitCode::from(checks::EXIT_EMPTY));
+        assert_eq!(fs::read_to_string(&output)?.trim(), "classDiagram");
+        Ok(())
+    }
+}
@@ -0,0 +1,112 @@
+use anyhow::{bail, Result};
+
+pub const START_MARKER: &str = "<!-- marco-polo:start -->";
+pub const END_MARKER: &str = "<!-- marco-polo:end -->";
+pub const TEMPLATE_PLACEHOLDER: &str = "{{diagram}}";
+
+/// Replaces everything between the start and end markers of `document` with a
+/// fenced `mermaid` block holding `diagram`, with `\r\n` fence lines when `crlf`
+/// is set. The markers and the text around them are kept as they are.
+pub fn replace_between_markers(document: &str, diagram: &str, crlf: bool) -> Result<String> {
+    let Some(start) = document.find(START_MARKER) else {
+        bail!("Missing `{}` marker", START_MARKER);
+    };
+    let region_start = start + START_MARKER.len();
+    let Some(end) = document[region_start..].find(END_MARKER).map(|i| region_start + i) else {
+        bail!("Missing `{}` marker after `{}`", END_MARKER, START_MARKER);
+    };
+
+    let newline = if crlf { "\r\n" } else { "\n" };
+    let diagram = diagram.trim_end_matches(['\r', '\n']);
+    Ok(format!(
+        "{}{nl}```mermaid{nl}{}{nl}```{nl}{}",
+        &document[..region_start],
+        diagram,
+        &document[end..],
+        nl = newline
+    ))
+}
+
+/// Substitutes `diagram` for every `{{diagram}}` placeholder in `template`,
+/// e.g. to wrap it in a Hugo shortcode or front matter.
+pub fn fill_template(template: &str, diagram: &str) -> Result<String> {
+    if !template.contains(TEMPLATE_PLACEHOLDER) {
+        bail!("Missing `{}` placeholder", TEMPLATE_PLACEHOLDER);
+    }
+    Ok(template.replace(TEMPLATE_PLACEHOLDER, diagram.trim_end_matches('\n')))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_marked_region_changes() -> Result<()> {
+        let document = "\
+# Architecture
+
+Intro prose.
+
+<!-- marco-polo:start -->
+```mermaid
+classDiagram
+    class Old
+```
+<!-- marco-polo:end -->
+
+Closing prose.
+";
+        let updated = replace_between_markers(document, "classDiagram\n    class New\n", false)?;
+        assert_eq!(updated, "\
+# Architecture
+
+Intro prose.
+
+<!-- marco-polo:start -->
+```mermaid
+classDiagram
+    class New
+```
+<!-- marco-polo:end -->
+
+Closing prose.
+");
+        // Updating again is stable
+        assert_eq!(replace_between_markers(&updated, "classDiagram\n    class New\n", false)?, updated);
+        Ok(())
+    }
+
+    #[test]
+    fn test_crlf_fences() -> Result<()> {
+        let document = "# Title\r\n<!-- marco-polo:start -->\r\n<!-- marco-polo:end -->\r\n";
+        let updated = replace_between_markers(document, "classDiagram\r\n    class New\r\n", true)?;
+        assert_eq!(updated, "\
+# Title\r
+<!-- marco-polo:start -->\r
+```mermaid\r
+classDiagram\r
+    class New\r
+```\r
+<!-- marco-polo:end -->\r
+");
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_markers_error() {
+        assert!(replace_between_markers("# Title\n", "classDiagram\n", false).is_err());
+        assert!(replace_between_markers("<!-- marco-polo:start -->\n", "classDiagram\n", false).is_err());
+        assert!(replace_between_markers("<!-- marco-polo:end -->\n<!-- marco-polo:start -->\n", "classDiagram\n", false).is_err());
+    }
+
+    #[test]
+    fn test_fill_template() -> Result<()> {
+        let template = "---\ntitle: Classes\n---\n{{< mermaid >}}\n{{diagram}}\n{{< /mermaid >}}\n";
+        let filled = fill_template(template, "classDiagram\n    class A\n")?;
+        assert_eq!(filled, "---\ntitle: Classes\n---\n{{< mermaid >}}\nclassDiagram\n    class A\n{{< /mermaid >}}\n");
+
+        let err = fill_template("{{< mermaid >}}\n", "classDiagram\n").unwrap_err();
+        assert!(err.to_string().contains("{{diagram}}"));
+        Ok(())
+    }
+}
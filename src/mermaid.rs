@@ -1,73 +1,451 @@
-use crate::models::{ClassInfo, RelationshipType, Visibility};
+use crate::models::{ClassInfo, FileReport, MethodInfo, PropertyInfo, RelationshipType, Visibility};
 use std::fmt::Write;
-use std::collections::HashSet;
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 
-pub fn generate_mermaid(classes: &[ClassInfo], enabled_visibilities: &[Visibility]) -> String {
+/// Rendering switches for [`generate_mermaid`].
+#[derive(Debug, Clone)]
+pub struct MermaidOptions {
+    /// Member visibility levels to include
+    pub visibilities: Vec<Visibility>,
+    /// Render single-abstract-method interfaces as a bare `<<functional>>` box
+    pub collapse_functional_interfaces: bool,
+    /// Attach a note naming the source language to each class
+    pub annotate_source_lang: bool,
+    /// Keep relationships derived from members hidden by `visibilities`
+    pub include_private_in_dependencies: bool,
+    /// Truncate member lines longer than this many characters with an ellipsis
+    pub wrap_width: Option<usize>,
+    /// Mark `@Override` methods with `<<override>>`
+    pub show_overrides: bool,
+    /// Annotate properties with qualifiers beyond `const`, such as `mutable`
+    pub show_qualifiers: bool,
+    /// Which end inheritance and realization arrows start from
+    pub arrow_from: ArrowFrom,
+    /// Wrap classes in nested `namespace` blocks following their qualified names
+    pub group_namespaces: bool,
+    /// Label class boxes with their short name, keeping the qualified name as the node ID
+    pub collapse_namespaces_in_labels: bool,
+    /// Repeat members inherited from parsed base classes, marked `(inherited)`
+    pub show_inherited_members: bool,
+    /// Draw composition/aggregation labels as a role on the target end instead of a line label
+    pub role_notes: bool,
+    /// Append a legend explaining each arrow
+    pub legend: bool,
+    /// Hand-written notes keyed by qualified class name (see [`parse_notes`])
+    pub notes: BTreeMap<String, String>,
+    /// Precede each edge with `%%` comments naming the constructs it was inferred from
+    pub explain: bool,
+}
+
+impl Default for MermaidOptions {
+    fn default() -> Self {
+        Self {
+            visibilities: vec![Visibility::Public],
+            collapse_functional_interfaces: false,
+            annotate_source_lang: false,
+            include_private_in_dependencies: false,
+            wrap_width: None,
+            show_overrides: false,
+            show_qualifiers: false,
+            arrow_from: ArrowFrom::Parent,
+            group_namespaces: false,
+            collapse_namespaces_in_labels: false,
+            show_inherited_members: false,
+            role_notes: false,
+            legend: false,
+            notes: BTreeMap::new(),
+            explain: false,
+        }
+    }
+}
+
+/// Source, target, type, label and cardinality of a drawn edge.
+type EdgeKey<'a> = (&'a str, &'a str, &'a RelationshipType, Option<&'a str>, Option<&'a str>);
+
+/// Renders a class diagram. Classes and edges are emitted in sorted order so
+/// the same input always yields byte-identical output, whatever its order.
+pub fn generate_mermaid(classes: &[ClassInfo], options: &MermaidOptions) -> String {
+    let enabled_visibilities = &options.visibilities;
     let mut diagram = String::new();
     writeln!(&mut diagram, "classDiagram").unwrap();
 
-    // 1. Define Classes
-    for class in classes {
-        writeln!(&mut diagram, "    class {} {{", class.name).unwrap();
-        
-        // Properties
-        for prop in &class.properties {
-            if enabled_visibilities.contains(&prop.visibility) {
-                let symbol = visibility_symbol(&prop.visibility);
-                writeln!(&mut diagram, "        {}{}", symbol, prop.name).unwrap();
+    let by_name: HashMap<&str, &ClassInfo> = classes.iter().map(|c| (c.name.as_str(), c)).collect();
+    let mut classes: Vec<&ClassInfo> = classes.iter().collect();
+    classes.sort_by(|a, b| a.name.cmp(&b.name));
+    let targets: BTreeSet<&str> = classes.iter()
+        .flat_map(|c| c.relationships.iter().map(|r| r.target.as_str()))
+        .collect();
+    let ids = node_ids(classes.iter().map(|c| c.name.as_str()).chain(targets), options);
+
+    // 1. Define Classes, optionally grouped by namespace
+    let blocks = classes.iter().map(|class| (class.name.as_str(), class_block(class, &ids[class.name.as_str()], &by_name, options)));
+    if options.group_namespaces {
+        // Mermaid does not nest namespaces, so each gets one flat block named
+        // by its full path (`A.B`); classes outside any namespace come first
+        let mut namespaces: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (name, block) in blocks {
+            namespaces.entry(namespace_path(name, &by_name).join(".")).or_default().push(block);
+        }
+        for (namespace, blocks) in &namespaces {
+            if namespace.is_empty() {
+                blocks.iter().for_each(|block| diagram.push_str(block));
+                continue;
             }
+            writeln!(&mut diagram, "    namespace {} {{", namespace).unwrap();
+            for line in blocks.iter().flat_map(|block| block.lines()) {
+                writeln!(&mut diagram, "    {}", line).unwrap();
+            }
+            writeln!(&mut diagram, "    }}").unwrap();
+        }
+    } else {
+        for (_, block) in blocks {
+            diagram.push_str(&block);
         }
+    }
 
-        // Methods
-        for method in &class.methods {
-            if enabled_visibilities.contains(&method.visibility) {
-                let symbol = visibility_symbol(&method.visibility);
-                writeln!(&mut diagram, "        {}{}()", symbol, method.name).unwrap();
+    if options.annotate_source_lang {
+        for class in &classes {
+            if let Some(language) = &class.language {
+                writeln!(&mut diagram, "    note for {} \"{}\"", ids[class.name.as_str()], language).unwrap();
             }
         }
+    }
 
-        writeln!(&mut diagram, "    }}").unwrap();
+    for class in &classes {
+        if let Some(note) = options.notes.get(&class.name) {
+            writeln!(&mut diagram, "    note for {} \"{}\"", ids[class.name.as_str()], note.replace('"', "#quot;")).unwrap();
+        }
     }
 
-    // 2. Define Relationships
-    let mut seen = HashSet::new();
-    for class in classes {
-        for rel in &class.relationships {
-            let arrow = match rel.rel_type {
-                RelationshipType::Inheritance => "<|--",
-                RelationshipType::Composition => "*--",
-                RelationshipType::Aggregation => "o--",
-                RelationshipType::Dependency => "..>",
-            };
+    // 2. Define Relationships, sorted by (source, target, type), with the provenance of each
+    let mut edges: BTreeMap<EdgeKey, BTreeSet<&str>> = BTreeMap::new();
+    for class in &classes {
+        for rel in class.relationships.iter().filter(|rel| options.include_private_in_dependencies
+            || rel.member_visibility.is_none_or(|v| enabled_visibilities.contains(&v)))
+        {
+            let provenance = edges.entry((class.name.as_str(), rel.target.as_str(), &rel.rel_type, rel.label.as_deref(), rel.cardinality.as_deref()))
+                .or_default();
+            provenance.extend(rel.provenance.as_deref());
+        }
+    }
+
+    for ((source, target, rel_type, label, cardinality), provenance) in edges {
+        if options.explain {
+            for construct in provenance {
+                writeln!(&mut diagram, "    %% {} -> {}: {}", source, target, construct).unwrap();
+            }
+        }
+        let (source, target) = (&ids[source], &ids[target]);
+
+        // With role notes the field names the target's role, so it sits next to the
+        // target node, followed by the multiplicity
+        let is_role = matches!(rel_type, RelationshipType::Composition | RelationshipType::Aggregation);
+        let role = label.filter(|_| options.role_notes && is_role);
+        let target_end: Vec<&str> = role.into_iter().chain(cardinality).collect();
+        let target_end = if target_end.is_empty() {
+            String::new()
+        } else {
+            format!("\"{}\" ", target_end.join(" "))
+        };
+        let mut line = match child_first_arrow(rel_type, options) {
+            // `Child --|> Parent`: the same edge written from the other end
+            Some(arrow) => format!("    {} {} {}{}", source, arrow, target_end, target),
+            None => format!("    {} {}{} {}", target, target_end, rel_type.mermaid_arrow(), source),
+        };
+        if let Some(label) = label.filter(|_| role.is_none()) {
+            write!(&mut line, " : {}", label).unwrap();
+        }
+        writeln!(&mut diagram, "{}", line).unwrap();
+    }
 
-            let line = if let Some(label) = &rel.label {
-                format!("    {} {} {} : {}", rel.target, arrow, class.name, label)
+    if options.legend {
+        diagram.push_str(&legend(options));
+    }
+
+    diagram
+}
+
+/// The `class Name { ... }` block for one class, at the top level's indentation.
+fn class_block(class: &ClassInfo, id: &str, by_name: &HashMap<&str, &ClassInfo>, options: &MermaidOptions) -> String {
+    let mut block = String::new();
+    let short = short_name(&class.name);
+    if options.collapse_namespaces_in_labels && short != class.name {
+        writeln!(&mut block, "    class {}[\"{}\"] {{", id, short).unwrap();
+    } else {
+        writeln!(&mut block, "    class {} {{", id).unwrap();
+    }
+
+    if options.collapse_functional_interfaces && is_functional_interface(class) {
+        writeln!(&mut block, "        <<functional>>").unwrap();
+        writeln!(&mut block, "    }}").unwrap();
+        return block;
+    }
+
+    if let Some(stereotype) = &class.stereotype {
+        writeln!(&mut block, "        <<{}>>", stereotype).unwrap();
+    }
+
+    let (inherited_properties, inherited_methods) = if options.show_inherited_members {
+        inherited_members(class, by_name)
+    } else {
+        (Vec::new(), Vec::new())
+    };
+
+    // Properties
+    let properties = class.properties.iter().map(|p| (p, false))
+        .chain(inherited_properties.into_iter().map(|p| (p, true)));
+    for (prop, inherited) in properties {
+        if options.visibilities.contains(&prop.visibility) {
+            let symbol = prop.visibility.uml_symbol();
+            // Mermaid marks static members with a trailing `$`
+            let name = if prop.is_static { format!("{}$", prop.name) } else { prop.name.clone() };
+            let mut qualifiers: Vec<&str> = Vec::new();
+            if prop.is_const {
+                qualifiers.push("const");
+            }
+            if options.show_qualifiers {
+                qualifiers.extend(prop.qualifiers.iter().map(|q| q.as_str()));
+            }
+            let mut member = if qualifiers.is_empty() {
+                format!("{}{}", symbol, name)
             } else {
-                format!("    {} {} {}", rel.target, arrow, class.name)
+                format!("{}{} : {}", symbol, name, qualifiers.join(" "))
             };
+            if inherited {
+                member.push_str(" (inherited)");
+            }
+            writeln!(&mut block, "        {}", fit_width(member, "", options.wrap_width)).unwrap();
+        }
+    }
 
-            if seen.insert(line.clone()) {
-                writeln!(&mut diagram, "{}", line).unwrap();
+    // Methods
+    let methods = class.methods.iter().map(|m| (m, false))
+        .chain(inherited_methods.into_iter().map(|m| (m, true)));
+    for (method, inherited) in methods {
+        if options.visibilities.contains(&method.visibility) {
+            let symbol = method.visibility.uml_symbol();
+            // Everything after the name survives truncation: without `()` Mermaid shows an attribute
+            let mut suffix = String::from("()");
+            if method.annotations.iter().any(|a| a == "Deprecated") {
+                suffix.push_str(" <<deprecated>>");
+            }
+            if options.show_overrides && method.annotations.iter().any(|a| a == "Override") {
+                suffix.push_str(" <<override>>");
+            }
+            if inherited {
+                suffix.push_str(" (inherited)");
             }
+            let member = format!("{}{}", symbol, method.name);
+            writeln!(&mut block, "        {}", fit_width(member, &suffix, options.wrap_width)).unwrap();
         }
     }
 
-    diagram
+    writeln!(&mut block, "    }}").unwrap();
+    block
 }
 
-fn visibility_symbol(visibility: &Visibility) -> &'static str {
-    match visibility {
-        Visibility::Public => "+",
-        Visibility::Protected => "#",
-        Visibility::Private => "-",
-        Visibility::Internal => "~",
+/// Parses a `--notes` file: one `ClassName: note text` per line. Blank lines
+/// and lines starting with `#` are skipped.
+pub fn parse_notes(text: &str) -> Result<BTreeMap<String, String>> {
+    let mut notes = BTreeMap::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        // The first `:` that is not half of a `::` scope separator
+        let bytes = line.as_bytes();
+        let separator = (0..bytes.len()).find(|&i| {
+            bytes[i] == b':' && bytes.get(i + 1) != Some(&b':') && (i == 0 || bytes[i - 1] != b':')
+        });
+        let Some((class, note)) = separator
+            .map(|i| (&line[..i], &line[i + 1..]))
+            .filter(|(class, _)| !class.trim().is_empty())
+        else {
+            bail!("line {}: expected `ClassName: note text`", number + 1);
+        };
+        notes.insert(class.trim().to_string(), note.trim().to_string());
     }
+    Ok(notes)
+}
+
+/// A floating note, unattached to any class, mapping each arrow to its meaning
+/// as the edges draw it.
+fn legend(options: &MermaidOptions) -> String {
+    let lines: Vec<String> = RelationshipType::ALL.iter()
+        .map(|t| {
+            let arrow = child_first_arrow(t, options).unwrap_or(t.mermaid_arrow());
+            // `#lt;` keeps Mermaid from reading `<|` as markup
+            format!("{} {}", arrow.replace('<', "#lt;"), t.name())
+        })
+        .collect();
+    format!("    note \"Legend\\n{}\"\n", lines.join("\\n"))
+}
+
+/// Members of parsed ancestor classes (nearest first) not redefined by `class`
+/// or a closer ancestor. Bases outside the parsed set contribute nothing.
+fn inherited_members<'a>(
+    class: &ClassInfo,
+    by_name: &HashMap<&str, &'a ClassInfo>,
+) -> (Vec<&'a PropertyInfo>, Vec<&'a MethodInfo>) {
+    let mut seen_properties: HashSet<&str> = class.properties.iter().map(|p| p.name.as_str()).collect();
+    let mut seen_methods: HashSet<&str> = class.methods.iter().map(|m| m.name.as_str()).collect();
+    let mut properties = Vec::new();
+    let mut methods = Vec::new();
+
+    let mut visited: HashSet<&str> = HashSet::from([class.name.as_str()]);
+    let mut queue: VecDeque<&str> = base_names(class).collect();
+    while let Some(base_name) = queue.pop_front() {
+        if !visited.insert(base_name) {
+            continue;
+        }
+        let Some(&base) = by_name.get(base_name) else {
+            continue;
+        };
+        for prop in &base.properties {
+            if seen_properties.insert(&prop.name) {
+                properties.push(prop);
+            }
+        }
+        for method in &base.methods {
+            if seen_methods.insert(&method.name) {
+                methods.push(method);
+            }
+        }
+        queue.extend(base_names(base));
+    }
+    (properties, methods)
+}
+
+fn base_names(class: &ClassInfo) -> impl Iterator<Item = &str> {
+    class.relationships.iter()
+        .filter(|r| r.rel_type.is_generalization())
+        .map(|r| r.target.as_str())
+}
+
+/// The namespaces enclosing a qualified name, outermost first: `A::B::Widget`
+/// is in `["A", "B"]`. A prefix that is itself a class (`Outer.Inner`) ends
+/// the chain, so nested classes stay beside their enclosing class.
+fn namespace_path<'a>(name: &'a str, by_name: &HashMap<&str, &ClassInfo>) -> Vec<&'a str> {
+    let mut path = Vec::new();
+    let mut segment_start = 0;
+    for (i, separator) in name.match_indices(['.', ':']) {
+        if separator == ":" && name[i + 1..].starts_with(':') {
+            continue;
+        }
+        let end = if separator == ":" { i - 1 } else { i };
+        if by_name.contains_key(&name[..end]) {
+            break;
+        }
+        // A leading `::` names the global namespace
+        if end > segment_start {
+            path.push(&name[segment_start..end]);
+        }
+        segment_start = i + 1;
+    }
+    path
+}
+
+/// Which end of an inheritance or realization arrow is written first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[clap(rename_all = "lower")]
+pub enum ArrowFrom {
+    /// `Parent <|-- Child`, Mermaid's usual order
+    Parent,
+    /// `Child --|> Parent`
+    Child,
+}
+
+/// The reversed arrow for a generalization drawn child first, if requested.
+fn child_first_arrow(rel_type: &RelationshipType, options: &MermaidOptions) -> Option<&'static str> {
+    match (options.arrow_from, rel_type) {
+        (ArrowFrom::Child, RelationshipType::Inheritance) => Some("--|>"),
+        (ArrowFrom::Child, RelationshipType::Realization) => Some("..|>"),
+        _ => None,
+    }
+}
+
+/// The Mermaid node ID for each of `names`. With collapsed labels, separators in
+/// the qualified name become `_` so the ID stays valid; names that then collide
+/// (`a.b_c` and `a_b.c`) get a numeric suffix in the order given.
+fn node_ids<'a>(names: impl IntoIterator<Item = &'a str>, options: &MermaidOptions) -> HashMap<&'a str, String> {
+    let mut ids = HashMap::new();
+    let mut taken = HashSet::new();
+    for name in names {
+        if ids.contains_key(name) {
+            continue;
+        }
+        if !options.collapse_namespaces_in_labels {
+            ids.insert(name, name.to_string());
+            continue;
+        }
+        let base = name.replace(|c: char| !c.is_alphanumeric() && c != '_', "_");
+        let mut id = base.clone();
+        let mut n = 2;
+        while !taken.insert(id.clone()) {
+            id = format!("{}_{}", base, n);
+            n += 1;
+        }
+        ids.insert(name, id);
+    }
+    ids
+}
+
+/// The last segment of a qualified name (`a.b.C` or `a::b::C` become `C`).
+fn short_name(name: &str) -> &str {
+    name.rsplit(['.', ':']).next().unwrap_or(name)
+}
+
+/// Cuts `member` followed by `suffix` down to `width` characters, shortening
+/// only `member` (ending it with `…`). The suffix is always kept whole.
+fn fit_width(member: String, suffix: &str, width: Option<usize>) -> String {
+    let suffix_len = suffix.chars().count();
+    match width {
+        Some(width) if member.chars().count() + suffix_len > width => {
+            let keep = width.saturating_sub(suffix_len + 1).max(1);
+            let mut short: String = member.chars().take(keep).collect();
+            short.push('…');
+            short.push_str(suffix);
+            short
+        }
+        _ => member + suffix,
+    }
+}
+
+/// An interface with exactly one abstract method, which covers every
+/// `@FunctionalInterface` that does not inherit its abstract method.
+fn is_functional_interface(class: &ClassInfo) -> bool {
+    class.is_interface()
+        && class.methods.iter().filter(|m| m.is_abstract).count() == 1
+}
+
+/// Generates `click` directives that show each class's `path:line` as a tooltip.
+/// Class IDs match [`generate_mermaid`]'s for the same classes.
+pub fn generate_tooltips(reports: &[FileReport], options: &MermaidOptions) -> String {
+    let names: BTreeSet<&str> = reports.iter()
+        .flat_map(|report| report.classes.iter().map(|c| c.name.as_str()))
+        .collect();
+    let ids = node_ids(names, options);
+    let mut tooltips = String::new();
+    let mut seen = HashSet::new();
+    for report in reports {
+        let path = report.path.display().to_string().replace('"', "'");
+        for class in &report.classes {
+            if seen.insert(class.name.as_str()) {
+                writeln!(&mut tooltips, "    click {} href \"{}\" \"{}:{}\"", ids[class.name.as_str()], path, path, class.line).unwrap();
+            }
+        }
+    }
+    tooltips
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::models::{Relationship, RelationshipType, MethodInfo, PropertyInfo};
+    use std::path::PathBuf;
 
     #[test]
     fn test_generate_mermaid_complex() {
@@ -75,34 +453,652 @@ mod tests {
             ClassInfo {
                 name: "Car".to_string(),
                 methods: vec![
-                    MethodInfo { name: "drive".to_string(), visibility: Visibility::Public },
-                    MethodInfo { name: "service".to_string(), visibility: Visibility::Private },
+                    MethodInfo { name: "drive".to_string(), visibility: Visibility::Public, is_abstract: false, annotations: vec![] },
+                    MethodInfo { name: "service".to_string(), visibility: Visibility::Private, is_abstract: false, annotations: vec![] },
                 ],
                 properties: vec![
-                    PropertyInfo { name: "engine".to_string(), visibility: Visibility::Public },
+                    PropertyInfo { name: "engine".to_string(), visibility: Visibility::Public, is_const: false, is_static: false, qualifiers: Vec::new() },
+                    PropertyInfo { name: "WHEELS".to_string(), visibility: Visibility::Public, is_const: true, is_static: false, qualifiers: Vec::new() },
                 ],
                 relationships: vec![
                     Relationship {
                         target: "Engine".to_string(),
                         rel_type: RelationshipType::Aggregation,
                         label: Some("engine".to_string()),
+                        member_visibility: Some(Visibility::Public),
+                        provenance: None,
+                        cardinality: None,
                     },
                     Relationship {
                         target: "Vehicle".to_string(),
                         rel_type: RelationshipType::Inheritance,
                         label: None,
+                        member_visibility: None,
+                        provenance: None,
+                        cardinality: None,
                     }
                 ],
+                stereotype: None,
+                language: None,
+                line: 1,
+                column: 1,
+                out_of_line: false,
+                package: None,
             },
         ];
 
-        let enabled = vec![Visibility::Public];
-        let output = generate_mermaid(&classes, &enabled);
+        let output = generate_mermaid(&classes, &MermaidOptions::default());
         
         assert!(output.contains("+drive()"));
         assert!(!output.contains("-service()"));
-        assert!(output.contains("+engine"));
+        assert!(output.contains("+engine\n"));
+        assert!(output.contains("+WHEELS : const"));
         assert!(output.contains("Engine o-- Car : engine"));
         assert!(output.contains("Vehicle <|-- Car"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_generate_tooltips() {
+        let reports = vec![FileReport {
+            path: PathBuf::from("src/car.py"),
+            classes: vec![ClassInfo {
+                name: "Car".to_string(),
+                methods: vec![],
+                properties: vec![],
+                relationships: vec![],
+                stereotype: None,
+                language: None,
+                line: 12,
+                column: 1,
+                out_of_line: false,
+                package: None,
+            }],
+        }];
+
+        let output = generate_tooltips(&reports, &MermaidOptions::default());
+        assert_eq!(output, "    click Car href \"src/car.py\" \"src/car.py:12\"\n");
+    }
+
+    #[test]
+    fn test_collapse_functional_interfaces() {
+        let classes = vec![ClassInfo {
+            name: "Callback".to_string(),
+            methods: vec![
+                MethodInfo { name: "call".to_string(), visibility: Visibility::Public, is_abstract: true, annotations: vec![] },
+            ],
+            properties: vec![],
+            relationships: vec![],
+            stereotype: Some("interface".to_string()),
+            language: None,
+            line: 1,
+            column: 1,
+            out_of_line: false,
+            package: None,
+        }];
+
+        let output = generate_mermaid(&classes, &MermaidOptions::default());
+        assert!(output.contains("<<interface>>"));
+        assert!(output.contains("+call()"));
+
+        let options = MermaidOptions { collapse_functional_interfaces: true, ..MermaidOptions::default() };
+        let output = generate_mermaid(&classes, &options);
+        assert!(output.contains("<<functional>>"));
+        assert!(!output.contains("<<interface>>"));
+        assert!(!output.contains("+call()"));
+    }
+
+    #[test]
+    fn test_annotate_source_lang() {
+        let classes = vec![ClassInfo {
+            name: "User".to_string(),
+            methods: vec![],
+            properties: vec![],
+            relationships: vec![],
+            stereotype: None,
+            language: Some("python".to_string()),
+            line: 1,
+            column: 1,
+            out_of_line: false,
+            package: None,
+        }];
+
+        let output = generate_mermaid(&classes, &MermaidOptions::default());
+        assert!(!output.contains("note for"));
+
+        let options = MermaidOptions { annotate_source_lang: true, ..MermaidOptions::default() };
+        let output = generate_mermaid(&classes, &options);
+        assert!(output.contains("    note for User \"python\""));
+    }
+
+    #[test]
+    fn test_output_is_deterministic() {
+        let rel = |target: &str, rel_type: RelationshipType| Relationship {
+            target: target.to_string(),
+            rel_type,
+            label: None,
+            member_visibility: None,
+            provenance: None,
+            cardinality: None,
+        };
+        let class = |name: &str, relationships: Vec<Relationship>| ClassInfo {
+            name: name.to_string(),
+            methods: vec![],
+            properties: vec![],
+            relationships,
+            stereotype: None,
+            language: None,
+            line: 1,
+            column: 1,
+            out_of_line: false,
+            package: None,
+        };
+
+        let classes = vec![
+            class("Order", vec![rel("Item", RelationshipType::Aggregation), rel("Base", RelationshipType::Inheritance), rel("Item", RelationshipType::Dependency)]),
+            class("Cart", vec![rel("Order", RelationshipType::Dependency), rel("Item", RelationshipType::Composition)]),
+            class("Item", vec![]),
+        ];
+        let mut shuffled: Vec<ClassInfo> = classes.iter().rev().cloned().collect();
+        for class in &mut shuffled {
+            class.relationships.reverse();
+        }
+
+        let first = generate_mermaid(&classes, &MermaidOptions::default());
+        let second = generate_mermaid(&shuffled, &MermaidOptions::default());
+        assert_eq!(first, second);
+
+        let cart_item = first.find("Item *-- Cart").unwrap();
+        let order_item = first.find("Item o-- Order").unwrap();
+        assert!(cart_item < order_item);
+    }
+
+    #[test]
+    fn test_private_member_relationships_follow_visibility() {
+        let classes = vec![ClassInfo {
+            name: "Car".to_string(),
+            methods: vec![],
+            properties: vec![PropertyInfo { name: "engine".to_string(), visibility: Visibility::Private, is_const: false, is_static: false, qualifiers: Vec::new() }],
+            relationships: vec![
+                Relationship {
+                    target: "Engine".to_string(),
+                    rel_type: RelationshipType::Aggregation,
+                    label: Some("engine".to_string()),
+                    member_visibility: Some(Visibility::Private),
+                    provenance: None,
+                    cardinality: None,
+                },
+                Relationship {
+                    target: "Vehicle".to_string(),
+                    rel_type: RelationshipType::Inheritance,
+                    label: None,
+                    member_visibility: None,
+                    provenance: None,
+                    cardinality: None,
+                },
+            ],
+            stereotype: None,
+            language: None,
+            line: 1,
+            column: 1,
+            out_of_line: false,
+            package: None,
+        }];
+
+        let output = generate_mermaid(&classes, &MermaidOptions::default());
+        assert!(!output.contains("Engine o-- Car"));
+        assert!(output.contains("Vehicle <|-- Car"));
+
+        let options = MermaidOptions { include_private_in_dependencies: true, ..MermaidOptions::default() };
+        let output = generate_mermaid(&classes, &options);
+        assert!(output.contains("Engine o-- Car : engine"));
+    }
+
+    #[test]
+    fn test_wrap_width_truncates_long_members() {
+        let classes = vec![ClassInfo {
+            name: "Client".to_string(),
+            methods: vec![
+                MethodInfo { name: "fetch_all_records_with_retries".to_string(), visibility: Visibility::Public, is_abstract: false, annotations: vec![] },
+                MethodInfo { name: "get".to_string(), visibility: Visibility::Public, is_abstract: false, annotations: vec![] },
+            ],
+            properties: vec![],
+            relationships: vec![],
+            stereotype: None,
+            language: None,
+            line: 1,
+            column: 1,
+            out_of_line: false,
+            package: None,
+        }];
+
+        let output = generate_mermaid(&classes, &MermaidOptions::default());
+        assert!(output.contains("+fetch_all_records_with_retries()"));
+
+        let options = MermaidOptions { wrap_width: Some(12), ..MermaidOptions::default() };
+        let output = generate_mermaid(&classes, &options);
+        assert!(output.contains("        +fetch_al…()\n"));
+        assert!(output.contains("        +get()\n"));
+    }
+
+    #[test]
+    fn test_wrap_width_keeps_method_suffix() {
+        let classes = vec![ClassInfo {
+            name: "Client".to_string(),
+            methods: vec![MethodInfo {
+                name: "fetch_all_records_with_retries".to_string(),
+                visibility: Visibility::Public,
+                is_abstract: false,
+                annotations: vec!["Deprecated".to_string()],
+            }],
+            properties: vec![PropertyInfo {
+                name: "connection_timeout_millis".to_string(),
+                visibility: Visibility::Public,
+                is_const: false,
+                is_static: false,
+                qualifiers: Vec::new(),
+            }],
+            relationships: vec![],
+            stereotype: None,
+            language: None,
+            line: 1,
+            column: 1,
+            out_of_line: false,
+            package: None,
+        }];
+
+        let options = MermaidOptions { wrap_width: Some(24), ..MermaidOptions::default() };
+        let output = generate_mermaid(&classes, &options);
+        assert!(output.contains("        +fetch…() <<deprecated>>\n"));
+        assert!(output.contains("        +connection_timeout_mil…\n"));
+    }
+
+    #[test]
+    fn test_method_annotation_markers() {
+        let annotated = |name: &str, annotation: &str| MethodInfo {
+            name: name.to_string(),
+            visibility: Visibility::Public,
+            is_abstract: false,
+            annotations: vec![annotation.to_string()],
+        };
+        let classes = vec![ClassInfo {
+            name: "Task".to_string(),
+            methods: vec![annotated("start", "Deprecated"), annotated("run", "Override")],
+            properties: vec![],
+            relationships: vec![],
+            stereotype: None,
+            language: None,
+            line: 1,
+            column: 1,
+            out_of_line: false,
+            package: None,
+        }];
+
+        let output = generate_mermaid(&classes, &MermaidOptions::default());
+        assert!(output.contains("+start() <<deprecated>>"));
+        assert!(output.contains("        +run()\n"));
+
+        let options = MermaidOptions { show_overrides: true, ..MermaidOptions::default() };
+        let output = generate_mermaid(&classes, &options);
+        assert!(output.contains("+run() <<override>>"));
+    }
+
+    #[test]
+    fn test_property_qualifiers() {
+        let property = |name: &str, is_const: bool, is_static: bool, qualifiers: &[&str]| PropertyInfo {
+            name: name.to_string(),
+            visibility: Visibility::Public,
+            is_const,
+            is_static,
+            qualifiers: qualifiers.iter().map(|q| q.to_string()).collect(),
+        };
+        let classes = vec![ClassInfo {
+            name: "Cache".to_string(),
+            methods: vec![],
+            properties: vec![
+                property("N", true, true, &[]),
+                property("lock", false, false, &["mutable"]),
+                property("flag", true, false, &["volatile"]),
+            ],
+            relationships: vec![],
+            stereotype: None,
+            language: None,
+            line: 1,
+            column: 1,
+            out_of_line: false,
+            package: None,
+        }];
+
+        let output = generate_mermaid(&classes, &MermaidOptions::default());
+        assert!(output.contains("        +N$ : const\n"));
+        assert!(output.contains("        +lock\n"));
+        assert!(output.contains("        +flag : const\n"));
+
+        let options = MermaidOptions { show_qualifiers: true, ..MermaidOptions::default() };
+        let output = generate_mermaid(&classes, &options);
+        assert!(output.contains("        +lock : mutable\n"));
+        assert!(output.contains("        +flag : const volatile\n"));
+    }
+
+    #[test]
+    fn test_arrow_from_child() {
+        let rel = |target: &str, rel_type: RelationshipType| Relationship {
+            target: target.to_string(),
+            rel_type,
+            label: None,
+            member_visibility: None,
+            provenance: None,
+            cardinality: None,
+        };
+        let classes = vec![ClassInfo {
+            name: "Dog".to_string(),
+            methods: vec![],
+            properties: vec![],
+            relationships: vec![
+                rel("Animal", RelationshipType::Inheritance),
+                rel("Pet", RelationshipType::Realization),
+                rel("Owner", RelationshipType::Aggregation),
+            ],
+            stereotype: None,
+            language: None,
+            line: 1,
+            column: 1,
+            out_of_line: false,
+            package: None,
+        }];
+
+        let output = generate_mermaid(&classes, &MermaidOptions::default());
+        assert!(output.contains("    Animal <|-- Dog\n"));
+        assert!(output.contains("    Pet <|.. Dog\n"));
+
+        let options = MermaidOptions { arrow_from: ArrowFrom::Child, ..MermaidOptions::default() };
+        let output = generate_mermaid(&classes, &options);
+        assert!(output.contains("    Dog --|> Animal\n"));
+        assert!(output.contains("    Dog ..|> Pet\n"));
+        // Other relationships keep their orientation
+        assert!(output.contains("    Owner o-- Dog\n"));
+    }
+
+    #[test]
+    fn test_group_namespaces() {
+        let class = |name: &str, targets: &[&str]| ClassInfo {
+            name: name.to_string(),
+            methods: vec![],
+            properties: vec![],
+            relationships: targets.iter().map(|t| Relationship {
+                target: t.to_string(),
+                rel_type: RelationshipType::Dependency,
+                label: None,
+                member_visibility: None,
+                provenance: None,
+                cardinality: None,
+            }).collect(),
+            stereotype: None,
+            language: None,
+            line: 1,
+            column: 1,
+            out_of_line: false,
+            package: None,
+        };
+        let classes = vec![
+            class("A::B::Widget", &["A::Config"]),
+            class("A::Config", &[]),
+            class("Main", &["A::B::Widget"]),
+        ];
+        let options = MermaidOptions { group_namespaces: true, ..MermaidOptions::default() };
+        let output = generate_mermaid(&classes, &options);
+        assert!(output.starts_with("\
+classDiagram
+    class Main {
+    }
+    namespace A {
+        class A::Config {
+        }
+    }
+    namespace A.B {
+        class A::B::Widget {
+        }
+    }
+"));
+        // Edges keep the qualified names
+        assert!(output.contains("    A::Config ..> A::B::Widget\n"));
+        assert!(output.contains("    A::B::Widget ..> Main\n"));
+
+        let by_name: HashMap<&str, &ClassInfo> = classes.iter().map(|c| (c.name.as_str(), c)).collect();
+        assert_eq!(namespace_path("com.shop.Order.Line", &HashMap::new()), vec!["com", "shop", "Order"]);
+        assert_eq!(namespace_path("A::B::Widget::Part", &by_name), vec!["A", "B"]);
+        assert_eq!(namespace_path("::Global", &by_name), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_collapse_namespaces_in_labels() {
+        let classes = vec![ClassInfo {
+            name: "com.example.service.OrderService".to_string(),
+            methods: vec![],
+            properties: vec![],
+            relationships: vec![Relationship {
+                target: "com.example.model.Order".to_string(),
+                rel_type: RelationshipType::Dependency,
+                label: None,
+                member_visibility: None,
+                provenance: None,
+                cardinality: None,
+            }],
+            stereotype: None,
+            language: None,
+            line: 1,
+            column: 1,
+            out_of_line: false,
+            package: None,
+        }];
+
+        let options = MermaidOptions { collapse_namespaces_in_labels: true, ..MermaidOptions::default() };
+        let output = generate_mermaid(&classes, &options);
+        assert!(output.contains("    class com_example_service_OrderService[\"OrderService\"] {"));
+        assert!(output.contains("    com_example_model_Order ..> com_example_service_OrderService"));
+    }
+
+    #[test]
+    fn test_collapsed_node_ids_stay_unique() {
+        let class = |name: &str| ClassInfo {
+            name: name.to_string(),
+            methods: vec![],
+            properties: vec![],
+            relationships: vec![],
+            stereotype: None,
+            language: None,
+            line: 1,
+            column: 1,
+            out_of_line: false,
+            package: None,
+        };
+        let classes = vec![class("a_b.c"), class("a.b_c")];
+
+        let options = MermaidOptions { collapse_namespaces_in_labels: true, ..MermaidOptions::default() };
+        let output = generate_mermaid(&classes, &options);
+        assert!(output.contains("    class a_b_c[\"b_c\"]"));
+        assert!(output.contains("    class a_b_c_2[\"c\"]"));
+
+        let reports = vec![FileReport { path: PathBuf::from("src/a.py"), classes }];
+        let tooltips = generate_tooltips(&reports, &options);
+        assert!(tooltips.contains("    click a_b_c href"));
+        assert!(tooltips.contains("    click a_b_c_2 href"));
+    }
+
+    #[test]
+    fn test_show_inherited_members() -> anyhow::Result<()> {
+        use crate::parsers::{python::PythonParser, LanguageParser};
+        let classes = PythonParser.parse("
+@dataclass
+class Entity:
+    id: int
+    def save(self): ...
+
+@dataclass
+class User(Entity):
+    name: str
+    def save(self): ...
+")?;
+
+        let plain = generate_mermaid(&classes, &MermaidOptions::default());
+        assert!(!plain.contains("(inherited)"));
+
+        let options = MermaidOptions { show_inherited_members: true, ..MermaidOptions::default() };
+        let output = generate_mermaid(&classes, &options);
+        let user = output.split("class User {").nth(1).unwrap().split('}').next().unwrap();
+        assert!(user.contains("+name\n"));
+        assert!(user.contains("+id (inherited)"));
+        // Overridden methods are listed once, as the subclass's own
+        assert!(user.contains("+save()\n"));
+        assert!(!user.contains("+save() (inherited)"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_role_notes() {
+        let car = ClassInfo {
+            name: "Car".to_string(),
+            methods: vec![],
+            properties: vec![],
+            relationships: vec![
+                Relationship {
+                    target: "Engine".to_string(),
+                    rel_type: RelationshipType::Aggregation,
+                    label: Some("engine".to_string()),
+                    member_visibility: None,
+                    provenance: None,
+                    cardinality: None,
+                },
+                Relationship {
+                    target: "Driver".to_string(),
+                    rel_type: RelationshipType::Dependency,
+                    label: Some("driver".to_string()),
+                    member_visibility: None,
+                    provenance: None,
+                    cardinality: None,
+                },
+            ],
+            stereotype: None,
+            language: None,
+            line: 1,
+            column: 1,
+            out_of_line: false,
+            package: None,
+        };
+
+        let plain = generate_mermaid(std::slice::from_ref(&car), &MermaidOptions::default());
+        assert!(plain.contains("    Engine o-- Car : engine"));
+
+        let options = MermaidOptions { role_notes: true, ..MermaidOptions::default() };
+        let output = generate_mermaid(&[car], &options);
+        assert!(output.contains("    Engine \"engine\" o-- Car\n"));
+        // Dependencies keep the plain line label
+        assert!(output.contains("    Driver ..> Car : driver"));
+    }
+
+    #[test]
+    fn test_cardinality() {
+        let rel = |target: &str, label: &str, cardinality: Option<&str>| Relationship {
+            target: target.to_string(),
+            rel_type: RelationshipType::Aggregation,
+            label: Some(label.to_string()),
+            member_visibility: None,
+            provenance: None,
+            cardinality: cardinality.map(String::from),
+        };
+        let customer = ClassInfo {
+            name: "Customer".to_string(),
+            methods: vec![],
+            properties: vec![],
+            relationships: vec![rel("Account", "account", Some("0..1")), rel("Profile", "profile", None)],
+            stereotype: None,
+            language: None,
+            line: 1,
+            column: 1,
+            out_of_line: false,
+            package: None,
+        };
+
+        let plain = generate_mermaid(std::slice::from_ref(&customer), &MermaidOptions::default());
+        assert!(plain.contains("    Account \"0..1\" o-- Customer : account\n"));
+        assert!(plain.contains("    Profile o-- Customer : profile\n"));
+
+        let options = MermaidOptions { role_notes: true, ..MermaidOptions::default() };
+        let roles = generate_mermaid(&[customer], &options);
+        assert!(roles.contains("    Account \"account 0..1\" o-- Customer\n"));
+    }
+
+    #[test]
+    fn test_legend() {
+        let plain = generate_mermaid(&[], &MermaidOptions::default());
+        assert!(!plain.contains("Legend"));
+
+        let options = MermaidOptions { legend: true, ..MermaidOptions::default() };
+        let output = generate_mermaid(&[], &options);
+        let legend = output.lines().last().unwrap();
+        assert!(legend.starts_with("    note \"Legend"));
+        for name in ["inheritance", "composition", "aggregation", "dependency"] {
+            assert!(legend.contains(name));
+        }
+        assert!(legend.contains("#lt;|-- inheritance"));
+
+        let options = MermaidOptions { legend: true, arrow_from: ArrowFrom::Child, ..MermaidOptions::default() };
+        let output = generate_mermaid(&[], &options);
+        let legend = output.lines().last().unwrap();
+        assert!(legend.contains("--|> inheritance"));
+        assert!(legend.contains("..|> realization"));
+        assert!(!legend.contains("#lt;|"));
+    }
+
+    #[test]
+    fn test_notes_file() -> anyhow::Result<()> {
+        let notes = parse_notes("
+# Owned by the payments team
+Order: Aggregate root for \"checkout\"
+Ghost: not parsed
+")?;
+        assert_eq!(notes.len(), 2);
+        assert!(parse_notes("no separator here").is_err());
+        assert!(parse_notes("ui::Widget").is_err());
+
+        let scoped = parse_notes("ui::Widget: note with a::b inside")?;
+        assert_eq!(scoped.get("ui::Widget").map(String::as_str), Some("note with a::b inside"));
+
+        let order = ClassInfo {
+            name: "Order".to_string(),
+            methods: vec![],
+            properties: vec![],
+            relationships: vec![],
+            stereotype: None,
+            language: None,
+            line: 1,
+            column: 1,
+            out_of_line: false,
+            package: None,
+        };
+        let options = MermaidOptions { notes, ..MermaidOptions::default() };
+        let output = generate_mermaid(&[order], &options);
+        assert!(output.contains("    note for Order \"Aggregate root for #quot;checkout#quot;\"\n"));
+        assert!(!output.contains("Ghost"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_explain_names_the_source_field() -> Result<()> {
+        let classes = crate::parsers::get_parser("py").unwrap().parse("
+class Engine:
+    pass
+
+class Car:
+    engine: Engine
+")?;
+        let car = classes.iter().find(|c| c.name == "Car").unwrap();
+        assert_eq!(car.relationships[0].rel_type, RelationshipType::Aggregation);
+        assert_eq!(car.relationships[0].provenance.as_deref(), Some("field engine: Engine"));
+
+        let options = MermaidOptions { explain: true, ..MermaidOptions::default() };
+        let diagram = generate_mermaid(&classes, &options);
+        assert!(diagram.contains("    %% Car -> Engine: field engine: Engine\n    Engine o-- Car : engine\n"));
+        assert!(!generate_mermaid(&classes, &MermaidOptions::default()).contains("%%"));
+        Ok(())
+    }
+}
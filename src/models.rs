@@ -1,16 +1,60 @@
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, ValueEnum, Serialize, Deserialize)]
+#[clap(rename_all = "lower")]
+#[serde(rename_all = "lowercase")]
 pub enum RelationshipType {
     Inheritance, // <|--
+    Realization, // <|..
     Composition, // *--
     Aggregation, // o--
     Dependency,  // ..>
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+impl RelationshipType {
+    /// Every variant, in declaration order.
+    pub const ALL: [RelationshipType; 5] = [
+        RelationshipType::Inheritance,
+        RelationshipType::Realization,
+        RelationshipType::Composition,
+        RelationshipType::Aggregation,
+        RelationshipType::Dependency,
+    ];
+
+    /// The lowercase name used on the command line and in JSON.
+    pub fn name(&self) -> &'static str {
+        match self {
+            RelationshipType::Inheritance => "inheritance",
+            RelationshipType::Realization => "realization",
+            RelationshipType::Composition => "composition",
+            RelationshipType::Aggregation => "aggregation",
+            RelationshipType::Dependency => "dependency",
+        }
+    }
+
+    /// The Mermaid arrow drawn from the target to the owning class.
+    pub fn mermaid_arrow(&self) -> &'static str {
+        match self {
+            RelationshipType::Inheritance => "<|--",
+            RelationshipType::Realization => "<|..",
+            RelationshipType::Composition => "*--",
+            RelationshipType::Aggregation => "o--",
+            RelationshipType::Dependency => "..>",
+        }
+    }
+
+    /// Inheritance or realization: the owning class is a kind of the target.
+    pub fn is_generalization(&self) -> bool {
+        matches!(self, RelationshipType::Inheritance | RelationshipType::Realization)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
 #[clap(rename_all = "lower")]
+#[serde(rename_all = "lowercase")]
 pub enum Visibility {
     Public,    // +
     Protected, // #
@@ -18,6 +62,29 @@ pub enum Visibility {
     Internal,  // ~
 }
 
+impl Visibility {
+    /// The UML prefix for a member with this visibility.
+    pub fn uml_symbol(&self) -> char {
+        match self {
+            Visibility::Public => '+',
+            Visibility::Protected => '#',
+            Visibility::Private => '-',
+            Visibility::Internal => '~',
+        }
+    }
+
+    /// The visibility named by an access keyword such as `private`.
+    pub fn from_keyword(keyword: &str) -> Option<Self> {
+        match keyword {
+            "public" => Some(Visibility::Public),
+            "protected" => Some(Visibility::Protected),
+            "private" => Some(Visibility::Private),
+            "internal" => Some(Visibility::Internal),
+            _ => None,
+        }
+    }
+}
+
 impl std::fmt::Display for Visibility {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -29,36 +96,300 @@ impl std::fmt::Display for Visibility {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Relationship {
     pub target: String,
     pub rel_type: RelationshipType,
     pub label: Option<String>,
+    /// Visibility of the member (field, method) the relationship comes from;
+    /// `None` for class-level edges such as inheritance
+    pub member_visibility: Option<Visibility>,
+    /// The construct the relationship was inferred from (e.g. "field engine: Engine"),
+    /// shown by `--explain`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<String>,
+    /// Multiplicity at the target end, e.g. `0..1` for an optional field
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cardinality: Option<String>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MethodInfo {
     pub name: String,
     pub visibility: Visibility,
+    pub is_abstract: bool,
+    /// Annotation names without the `@` (e.g. "Deprecated", "Override")
+    #[serde(default)]
+    pub annotations: Vec<String>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PropertyInfo {
     pub name: String,
     pub visibility: Visibility,
+    /// Declared `final` (Java) or `const`/`constexpr` (C++)
+    pub is_const: bool,
+    /// Class-level rather than per instance (Python `ClassVar`, C++ `static`)
+    #[serde(default)]
+    pub is_static: bool,
+    /// Other declared qualifiers, such as C++ `mutable` and `volatile`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub qualifiers: Vec<String>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ClassInfo {
     pub name: String,
     pub methods: Vec<MethodInfo>,
     pub properties: Vec<PropertyInfo>,
     pub relationships: Vec<Relationship>,
+    /// UML stereotype rendered as `<<stereotype>>` (e.g. "interface")
+    pub stereotype: Option<String>,
+    /// Name of the parser that produced the class (e.g. "python")
+    pub language: Option<String>,
+    /// 1-based line where the class is defined
+    pub line: u32,
+    /// 1-based column where the class is defined
+    pub column: u32,
+    /// Only known from out-of-line C++ member definitions (`void Widget::draw() {}`);
+    /// dropped unless a declaration of the class is scanned too
+    #[serde(skip)]
+    pub out_of_line: bool,
+    /// Package or namespace the class is declared in (`com.example`, `ui::detail`),
+    /// for languages that have one; enclosing classes are not part of it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+}
+
+impl ClassInfo {
+    /// Whether the stereotype marks an interface, alone or qualified (`sealed interface`).
+    pub fn is_interface(&self) -> bool {
+        self.stereotype.as_deref().is_some_and(|s| s.split_whitespace().any(|word| word == "interface"))
+    }
+
+    /// Unions `others` (other declarations of the same class) into this one.
+    /// Methods and properties are matched by name (the model records no
+    /// signatures); a repeated method gains any annotations it lacked.
+    /// Duplicate relationships are dropped, keeping a known member visibility
+    /// over an unknown one. This class keeps its position unless it is only known
+    /// from out-of-line definitions; a missing stereotype, language or package is
+    /// taken from the others. Lookups go through hash indexes
+    /// built once, so merging is linear in the total number of members.
+    pub fn merge(&mut self, others: impl IntoIterator<Item = ClassInfo>) {
+        let mut methods: HashMap<String, usize> = self.methods.iter().enumerate()
+            .map(|(i, m)| (m.name.clone(), i))
+            .collect();
+        let mut properties: HashSet<String> = self.properties.iter().map(|p| p.name.clone()).collect();
+        // The same key the diagram dedups edges by, so merged and rendered edges agree
+        let key_of = |r: &Relationship| (r.target.clone(), r.rel_type.clone(), r.label.clone(), r.cardinality.clone());
+        let mut relationships: HashMap<_, usize> = self.relationships.iter().enumerate()
+            .map(|(i, r)| (key_of(r), i))
+            .collect();
+
+        for other in others {
+            for method in other.methods {
+                match methods.get(&method.name) {
+                    Some(&i) => {
+                        let existing = &mut self.methods[i];
+                        for annotation in method.annotations {
+                            if !existing.annotations.contains(&annotation) {
+                                existing.annotations.push(annotation);
+                            }
+                        }
+                    }
+                    None => {
+                        methods.insert(method.name.clone(), self.methods.len());
+                        self.methods.push(method);
+                    }
+                }
+            }
+            for prop in other.properties {
+                if properties.insert(prop.name.clone()) {
+                    self.properties.push(prop);
+                }
+            }
+            for rel in other.relationships {
+                let key = key_of(&rel);
+                match relationships.get(&key) {
+                    Some(&i) => {
+                        let existing = &mut self.relationships[i];
+                        if existing.member_visibility.is_none() {
+                            existing.member_visibility = rel.member_visibility;
+                        }
+                        if existing.provenance.is_none() {
+                            existing.provenance = rel.provenance;
+                        }
+                    }
+                    None => {
+                        relationships.insert(key, self.relationships.len());
+                        self.relationships.push(rel);
+                    }
+                }
+            }
+            if self.stereotype.is_none() {
+                self.stereotype = other.stereotype;
+            }
+            if self.language.is_none() {
+                self.language = other.language;
+            }
+            if self.out_of_line && !other.out_of_line {
+                self.line = other.line;
+                self.column = other.column;
+                self.package = other.package;
+                self.out_of_line = false;
+            } else if self.package.is_none() {
+                self.package = other.package;
+            }
+        }
+    }
 }
 
-#[allow(dead_code)]
 #[derive(Debug)]
 pub struct FileReport {
     pub path: PathBuf,
     pub classes: Vec<ClassInfo>,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mermaid_arrows() {
+        assert_eq!(RelationshipType::Inheritance.mermaid_arrow(), "<|--");
+        assert_eq!(RelationshipType::Realization.mermaid_arrow(), "<|..");
+        assert_eq!(RelationshipType::Composition.mermaid_arrow(), "*--");
+        assert_eq!(RelationshipType::Aggregation.mermaid_arrow(), "o--");
+        assert_eq!(RelationshipType::Dependency.mermaid_arrow(), "..>");
+
+        // Every variant draws a distinct arrow
+        let arrows: HashSet<_> = RelationshipType::ALL.iter().map(|t| t.mermaid_arrow()).collect();
+        assert_eq!(arrows.len(), RelationshipType::ALL.len());
+    }
+
+    #[test]
+    fn test_visibility_symbols_and_keywords() {
+        let symbols: Vec<char> = Visibility::value_variants().iter().map(|v| v.uml_symbol()).collect();
+        assert_eq!(symbols, vec!['+', '#', '-', '~']);
+        for visibility in Visibility::value_variants() {
+            assert_eq!(Visibility::from_keyword(&visibility.to_string()), Some(*visibility));
+        }
+        assert_eq!(Visibility::from_keyword("friend"), None);
+        assert_eq!(Visibility::from_keyword("Public"), None);
+    }
+
+    #[test]
+    fn test_relationship_type_value_names() {
+        assert_eq!(RelationshipType::from_str("dependency", true), Ok(RelationshipType::Dependency));
+        assert!(RelationshipType::from_str("uses", true).is_err());
+    }
+
+    fn class(name: &str) -> ClassInfo {
+        ClassInfo {
+            name: name.to_string(),
+            methods: vec![],
+            properties: vec![],
+            relationships: vec![],
+            stereotype: None,
+            language: None,
+            line: 1,
+            column: 1,
+            out_of_line: false,
+            package: None,
+        }
+    }
+
+    fn method(name: &str, annotations: &[&str]) -> MethodInfo {
+        MethodInfo {
+            name: name.to_string(),
+            visibility: Visibility::Public,
+            is_abstract: false,
+            annotations: annotations.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+
+    fn property(name: &str) -> PropertyInfo {
+        PropertyInfo { name: name.to_string(), visibility: Visibility::Public, is_const: false, is_static: false, qualifiers: Vec::new() }
+    }
+
+    fn relationship(target: &str, member_visibility: Option<Visibility>) -> Relationship {
+        Relationship {
+            target: target.to_string(),
+            rel_type: RelationshipType::Dependency,
+            label: None,
+            member_visibility,
+            provenance: None,
+            cardinality: None,
+        }
+    }
+
+    #[test]
+    fn test_merge_unions_members() {
+        let mut header = class("Widget");
+        header.methods = vec![method("draw", &[])];
+        header.properties = vec![property("size")];
+
+        let mut source = class("Widget");
+        source.methods = vec![method("draw", &["Override"]), method("resize", &[])];
+        source.properties = vec![property("size"), property("theme")];
+
+        header.merge([source]);
+        let methods: Vec<_> = header.methods.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(methods, vec!["draw", "resize"]);
+        assert_eq!(header.methods[0].annotations, vec!["Override"]);
+        let properties: Vec<_> = header.properties.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(properties, vec!["size", "theme"]);
+    }
+
+    #[test]
+    fn test_merge_dedups_relationships() {
+        let mut first = class("Widget");
+        first.relationships = vec![relationship("Canvas", None)];
+
+        let mut second = class("Widget");
+        second.relationships = vec![relationship("Canvas", Some(Visibility::Private)), relationship("Theme", None)];
+
+        first.merge([second]);
+        assert_eq!(first.relationships.len(), 2);
+        // The known visibility wins over the unknown one
+        assert_eq!(first.relationships[0].member_visibility, Some(Visibility::Private));
+
+        // Edges differing only by cardinality stay apart, as they do in the diagram
+        let mut optional = class("Widget");
+        optional.relationships = vec![Relationship { cardinality: Some("0..1".to_string()), ..relationship("Canvas", None) }];
+        first.merge([optional]);
+        assert_eq!(first.relationships.len(), 3);
+    }
+
+    #[test]
+    fn test_merge_keeps_stereotype_and_position() {
+        let mut runtime = class("Shape");
+        runtime.line = 3;
+        let mut stub = class("Shape");
+        stub.stereotype = Some("dataclass".to_string());
+        stub.language = Some("python".to_string());
+        stub.line = 10;
+
+        runtime.merge([stub]);
+        assert_eq!(runtime.stereotype.as_deref(), Some("dataclass"));
+        assert_eq!(runtime.language.as_deref(), Some("python"));
+        assert_eq!(runtime.line, 3);
+
+        let mut interface = class("Shape");
+        interface.stereotype = Some("interface".to_string());
+        interface.merge([class("Shape")]);
+        assert_eq!(interface.stereotype.as_deref(), Some("interface"));
+    }
+
+    #[test]
+    fn test_deserialize_json_without_newer_fields() {
+        let json = r#"[{"name": "Order", "methods": [{"name": "total", "visibility": "public", "is_abstract": false}],
+            "properties": [{"name": "id", "visibility": "public", "is_const": false}],
+            "relationships": [{"target": "Item", "rel_type": "composition", "label": null}],
+            "stereotype": null, "language": "java", "line": 1, "column": 1}]"#;
+        let classes: Vec<ClassInfo> = serde_json::from_str(json).unwrap();
+        assert!(classes[0].methods[0].annotations.is_empty());
+        assert!(!classes[0].properties[0].is_static);
+        assert_eq!(classes[0].relationships[0].cardinality, None);
+    }
+}
@@ -0,0 +1,227 @@
+use std::sync::OnceLock;
+use tree_sitter::{Parser, Query, QueryCursor, Node};
+use crate::models::{ClassInfo, Relationship, RelationshipType, Visibility, PropertyInfo};
+use anyhow::{Result, Context};
+use super::{is_builtin, parse_tree, LanguageParser, ParseOptions};
+
+// Only struct definitions (with a body); `struct Foo *p` is a reference
+const C_STRUCT_QUERY_STR: &str = "(struct_specifier body: (field_declaration_list)) @struct";
+
+pub struct CParser;
+
+impl LanguageParser for CParser {
+    fn name(&self) -> &str {
+        "c"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["c"]
+    }
+
+    fn parse_with_options(&self, content: &str, options: &ParseOptions) -> Result<Vec<ClassInfo>> {
+        let mut parser = Parser::new();
+        let language = tree_sitter_c::language();
+        parser.set_language(language)
+            .context("Error loading C grammar")?;
+
+        let tree = parse_tree(&mut parser, content, options)
+            .context("Failed to parse C content")?;
+
+        let root_node = tree.root_node();
+        let mut classes = Vec::new();
+
+        static STRUCT_QUERY: OnceLock<Query> = OnceLock::new();
+        let query = STRUCT_QUERY.get_or_init(|| {
+            Query::new(tree_sitter_c::language(), C_STRUCT_QUERY_STR)
+                .expect("Static C struct query is invalid")
+        });
+
+        let mut query_cursor = QueryCursor::new();
+        let matches = query_cursor.matches(query, root_node, content.as_bytes());
+
+        for m in matches {
+            let struct_node = m.captures[0].node;
+
+            // `struct Foo {..}` or `typedef struct {..} Foo;`; anonymous member structs are skipped
+            let Some(name) = struct_name(struct_node, content) else {
+                continue;
+            };
+
+            let mut properties = Vec::new();
+            let mut relationships = Vec::new();
+
+            if let Some(body_node) = struct_node.child_by_field_name("body") {
+                let mut cursor = body_node.walk();
+                for field in body_node.children(&mut cursor) {
+                    if field.kind() != "field_declaration" {
+                        continue;
+                    }
+                    let type_node = field.child_by_field_name("type");
+
+                    let mut decl_cursor = field.walk();
+                    for declarator in field.children_by_field_name("declarator", &mut decl_cursor) {
+                        let Some(name_node) = find_node_by_kind(declarator, "field_identifier") else {
+                            continue;
+                        };
+                        let field_name = get_node_text(name_node, content);
+
+                        // C has no access modifiers: every field is public
+                        properties.push(PropertyInfo {
+                            name: field_name.clone(),
+                            visibility: Visibility::Public,
+                            is_const: false,
+                            is_static: false,
+                            qualifiers: Vec::new(),
+                        });
+
+                        if let Some(func_decl) = find_node_by_kind(declarator, "function_declarator") {
+                            // Function pointer: depend on its parameter and return types
+                            let mut targets = Vec::new();
+                            if let Some(params) = func_decl.child_by_field_name("parameters") {
+                                let mut p_cursor = params.walk();
+                                for param in params.children(&mut p_cursor) {
+                                    if let Some(t) = param.child_by_field_name("type").and_then(|t| type_name(t, content, options)) {
+                                        targets.push(t);
+                                    }
+                                }
+                            }
+                            targets.extend(type_node.and_then(|t| type_name(t, content, options)));
+
+                            for target in targets {
+                                relationships.push(Relationship {
+                                    target,
+                                    rel_type: RelationshipType::Dependency,
+                                    label: None,
+                                    member_visibility: Some(Visibility::Public),
+                                    provenance: Some(format!("function pointer field {}", field_name)),
+                                    cardinality: None,
+                                });
+                            }
+                        } else if let Some(target) = type_node.and_then(|t| type_name(t, content, options)) {
+                            let rel_type = if find_node_by_kind(declarator, "pointer_declarator").is_some() {
+                                RelationshipType::Aggregation
+                            } else {
+                                RelationshipType::Composition
+                            };
+                            relationships.push(Relationship {
+                                target,
+                                rel_type,
+                                label: Some(field_name.clone()),
+                                member_visibility: Some(Visibility::Public),
+                                provenance: type_node.map(|t| format!("field {}: {}", field_name, get_node_text(t, content))),
+                                cardinality: None,
+                            });
+                        }
+                    }
+                }
+            }
+
+            classes.push(ClassInfo {
+                name,
+                methods: Vec::new(),
+                properties,
+                relationships,
+                stereotype: None,
+                language: Some(self.name().to_string()),
+                line: struct_node.start_position().row as u32 + 1,
+                column: struct_node.start_position().column as u32 + 1,
+                out_of_line: false,
+                package: None,
+            });
+        }
+
+        Ok(classes)
+    }
+}
+
+/// The struct's own tag, falling back to the typedef name for anonymous structs.
+fn struct_name(struct_node: Node, content: &str) -> Option<String> {
+    if let Some(name_node) = struct_node.child_by_field_name("name") {
+        return Some(get_node_text(name_node, content));
+    }
+    let typedef = struct_node.parent().filter(|p| p.kind() == "type_definition")?;
+    let declarator = typedef.child_by_field_name("declarator")?;
+    find_node_by_kind(declarator, "type_identifier").map(|n| get_node_text(n, content))
+}
+
+/// The user-defined type named by a type node (`Foo`, `struct Foo`), if any.
+fn type_name(type_node: Node, content: &str, options: &ParseOptions) -> Option<String> {
+    let name_node = match type_node.kind() {
+        "type_identifier" => Some(type_node),
+        "struct_specifier" | "union_specifier" | "enum_specifier" => type_node.child_by_field_name("name"),
+        _ => None,
+    }?;
+    let name = get_node_text(name_node, content);
+    (!is_builtin(&name, is_builtin_type, options)).then_some(name)
+}
+
+fn is_builtin_type(type_name: &str) -> bool {
+    matches!(
+        type_name,
+        "size_t" | "ssize_t" | "ptrdiff_t" | "FILE" | "va_list" | "bool" |
+        "uint8_t" | "uint16_t" | "uint32_t" | "uint64_t" |
+        "int8_t" | "int16_t" | "int32_t" | "int64_t"
+    )
+}
+
+fn find_node_by_kind<'a>(node: Node<'a>, kind: &str) -> Option<Node<'a>> {
+    if node.kind() == kind { return Some(node); }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        if let Some(found) = find_node_by_kind(child, kind) { return Some(found); }
+    }
+    None
+}
+
+fn get_node_text(node: Node, content: &str) -> String {
+    node.utf8_text(content.as_bytes())
+        .ok()
+        .unwrap_or("")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_named_struct() -> Result<()> {
+        let content = "
+struct Node {
+    int value;
+    struct Node *next;
+    Payload data;
+    void (*on_free)(Allocator *allocator);
+};
+";
+        let classes = CParser.parse(content)?;
+        assert_eq!(classes.len(), 1);
+        let node = &classes[0];
+        assert_eq!(node.name, "Node");
+        assert_eq!(node.language.as_deref(), Some("c"));
+        assert_eq!(node.properties.len(), 4);
+        assert!(node.properties.iter().all(|p| p.visibility == Visibility::Public));
+        assert!(node.relationships.iter().any(|r| r.target == "Node" && r.rel_type == RelationshipType::Aggregation));
+        assert!(node.relationships.iter().any(|r| r.target == "Payload" && r.rel_type == RelationshipType::Composition));
+        assert!(node.relationships.iter().any(|r| r.target == "Allocator" && r.rel_type == RelationshipType::Dependency));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_typedef_anonymous_struct() -> Result<()> {
+        let content = "
+typedef struct {
+    Vec2 position;
+    size_t mass;
+} Body;
+";
+        let classes = CParser.parse(content)?;
+        assert_eq!(classes.len(), 1);
+        let body = &classes[0];
+        assert_eq!(body.name, "Body");
+        assert!(body.properties.iter().any(|p| p.name == "position"));
+        assert!(body.relationships.iter().any(|r| r.target == "Vec2"));
+        assert!(!body.relationships.iter().any(|r| r.target == "size_t"));
+        Ok(())
+    }
+}
@@ -2,27 +2,33 @@ use std::sync::OnceLock;
 use tree_sitter::{Parser, Query, QueryCursor, Node};
 use crate::models::{ClassInfo, Relationship, RelationshipType, Visibility, MethodInfo, PropertyInfo};
 use anyhow::{Result, Context};
-use super::LanguageParser;
+use super::{apply_relationship_directives, is_builtin, parse_tree, type_depth_exceeded, LanguageParser, ParseOptions};
 
 const CPP_CLASS_QUERY_STR: &str = "
     (class_specifier) @class
     (struct_specifier) @struct
+    (union_specifier) @union
 ";
+const CPP_FUNCTION_QUERY_STR: &str = "(function_definition) @function";
 
 pub struct CppParser;
 
 impl LanguageParser for CppParser {
+    fn name(&self) -> &str {
+        "cpp"
+    }
+
     fn extensions(&self) -> &[&str] {
         &["cpp", "cc", "cxx", "h", "hpp"]
     }
 
-    fn parse(&self, content: &str) -> Result<Vec<ClassInfo>> {
+    fn parse_with_options(&self, content: &str, options: &ParseOptions) -> Result<Vec<ClassInfo>> {
         let mut parser = Parser::new();
         let language = tree_sitter_cpp::language();
         parser.set_language(language)
             .context("Error loading C++ grammar")?;
 
-        let tree = parser.parse(content, None)
+        let tree = parse_tree(&mut parser, content, options)
             .context("Failed to parse C++ content")?;
 
         let root_node = tree.root_node();
@@ -40,31 +46,51 @@ impl LanguageParser for CppParser {
         for m in matches {
             let class_node = m.captures[0].node;
             
-            // Extract Full Name (Namespace Aware)
-            let mut name_parts = Vec::new();
-            let mut curr = Some(class_node);
+            // `typedef struct {..} Point;` takes its typedef name; truly anonymous types are skipped
+            let Some(own_name) = class_name(class_node, content) else {
+                continue;
+            };
+
+            // Local classes inside function bodies are implementation details
+            if !options.include_local_classes && is_local_class(class_node) {
+                continue;
+            }
+
+            // Extract Full Name (Namespace Aware); anonymous namespaces add no scope
+            let mut name_parts = vec![own_name];
+            let mut namespaces = Vec::new();
+            let mut curr = class_node.parent();
             while let Some(n) = curr {
-                if n.kind() == "class_specifier" || n.kind() == "struct_specifier" || n.kind() == "namespace_definition" {
-                    if let Some(name_node) = n.child_by_field_name("name") {
-                        name_parts.push(get_node_text(name_node, content));
+                match n.kind() {
+                    "class_specifier" | "struct_specifier" | "union_specifier" => name_parts.extend(class_name(n, content)),
+                    "namespace_definition" => {
+                        let name = n.child_by_field_name("name").map(|name| get_node_text(name, content));
+                        name_parts.extend(name.clone());
+                        namespaces.extend(name);
                     }
+                    _ => {}
                 }
                 curr = n.parent();
             }
             name_parts.reverse();
+            namespaces.reverse();
             let full_name = name_parts.join("::");
 
             let mut methods = Vec::new();
             let mut properties = Vec::new();
             let mut relationships = Vec::new();
+            let mut initialized_members = Vec::new();
 
-            // 1. Inheritance
-            if let Some(bases_node) = find_node_by_kind(class_node, "base_class_clause") {
+            // 1. Inheritance (a direct child: nested classes have their own)
+            let mut b_cursor = class_node.walk();
+            let bases = class_node.children(&mut b_cursor).find(|c| c.kind() == "base_class_clause");
+            if let Some(bases_node) = bases {
                 extract_inheritance(bases_node, content, &mut relationships);
             }
 
             // 2. Body
             if let Some(body_node) = class_node.child_by_field_name("body") {
+                // Scoped to this body: nested classes start from their own default
                 let mut current_visibility = if class_node.kind() == "class_specifier" {
                     Visibility::Private
                 } else {
@@ -77,12 +103,7 @@ impl LanguageParser for CppParser {
                         "access_specifier" => {
                             let text = get_node_text(child, content);
                             let keyword = text.trim_matches(':').trim();
-                            current_visibility = match keyword {
-                                "public" => Visibility::Public,
-                                "protected" => Visibility::Protected,
-                                "private" => Visibility::Private,
-                                _ => current_visibility,
-                            };
+                            current_visibility = Visibility::from_keyword(keyword).unwrap_or(current_visibility);
                         }
                         "field_declaration" => {
                             if let Some(declarator) = child.child_by_field_name("declarator") {
@@ -94,15 +115,17 @@ impl LanguageParser for CppParser {
                                         methods.push(MethodInfo {
                                             name: method_name,
                                             visibility: current_visibility,
+                                            is_abstract: false,
+                                            annotations: Vec::new(),
                                         });
                                         
                                         // Extract parameter types for dependency relationships
                                         if let Some(params) = find_node_by_kind(func_decl, "parameter_list") {
-                                            extract_parameter_types(params, content, &mut relationships);
+                                            extract_parameter_types(params, content, &mut relationships, Some(current_visibility), options);
                                         }
 
                                         // Extract return type for dependency
-                                        extract_return_type(child, content, &mut relationships);
+                                        extract_return_type(child, content, &mut relationships, Some(current_visibility), options);
 
                                         continue;
                                     }
@@ -115,11 +138,17 @@ impl LanguageParser for CppParser {
                                     properties.push(PropertyInfo {
                                         name: field_name.clone(),
                                         visibility: current_visibility,
+                                        is_const: has_qualifier(child, content, &["const", "constexpr"]),
+                                        is_static: has_qualifier(child, content, &["static"]),
+                                        qualifiers: ["mutable", "volatile"].iter()
+                                            .filter(|q| has_qualifier(child, content, &[q]))
+                                            .map(|q| q.to_string())
+                                            .collect(),
                                     });
 
                                     if let Some(type_node) = child.child_by_field_name("type") {
                                         let mut type_nodes = Vec::new();
-                                        extract_type(type_node, content, &mut type_nodes);
+                                        extract_type(type_node, content, &mut type_nodes, options, 0);
                                         let is_composition = has_initializer(declarator);
                                         let is_pointer_or_ref = is_pointer_or_reference_wrapper(declarator);
                                         
@@ -136,6 +165,9 @@ impl LanguageParser for CppParser {
                                                 target: type_name,
                                                 rel_type: rel_type.clone(),
                                                 label: Some(field_name.clone()),
+                                                member_visibility: Some(current_visibility),
+                                                provenance: Some(format!("field {}: {}", field_name, get_node_text(type_node, content))),
+                                                cardinality: None,
                                             });
                                         }
                                     }
@@ -143,6 +175,7 @@ impl LanguageParser for CppParser {
                             }
                         }
                         "function_definition" | "declaration" => {
+                            initialized_members.extend(initialized_fields(child, content));
                             if let Some(declarator) = find_function_declarator(child) {
                                 if let Some(name_node) = find_node_by_kind(declarator, "field_identifier")
                                     .or_else(|| find_node_by_kind(declarator, "identifier")) {
@@ -152,13 +185,15 @@ impl LanguageParser for CppParser {
                                         methods.push(MethodInfo {
                                             name: method_name,
                                             visibility: current_visibility,
+                                            is_abstract: false,
+                                            annotations: Vec::new(),
                                         });
                                     }
 
                                     if let Some(params) = find_node_by_kind(declarator, "parameter_list") {
-                                        extract_parameter_types(params, content, &mut relationships);
+                                        extract_parameter_types(params, content, &mut relationships, Some(current_visibility), options);
                                     }
-                                    extract_return_type(child, content, &mut relationships);
+                                    extract_return_type(child, content, &mut relationships, Some(current_visibility), options);
                                 }
                             }
                         }
@@ -167,71 +202,251 @@ impl LanguageParser for CppParser {
                 }
             }
 
+            upgrade_initialized_members(&mut relationships, &initialized_members);
+
+            // Template parameters (`T` in `template<typename T>`) are placeholders, not classes
+            let template_params = template_parameters(class_node, content);
+            relationships.retain(|r| !template_params.contains(&r.target));
+
+            apply_relationship_directives(class_node, content, &["class_specifier", "struct_specifier", "union_specifier"], &mut relationships);
+
+            if options.collapse_accessor_pairs {
+                collapse_accessor_pairs(&mut methods, &mut properties, &mut relationships);
+            }
+
             classes.push(ClassInfo {
                 name: full_name,
                 methods,
                 properties,
                 relationships,
+                stereotype: None,
+                language: Some(self.name().to_string()),
+                line: class_node.start_position().row as u32 + 1,
+                column: class_node.start_position().column as u32 + 1,
+                out_of_line: false,
+                package: (!namespaces.is_empty()).then(|| namespaces.join("::")),
             });
         }
 
+        // 3. Out-of-line member definitions (`void Widget::draw() {}`), typically found in
+        // source files whose class is declared in a header. Their signatures are attributed
+        // to the owning class so the two halves can be merged by qualified name. An owner not
+        // declared in this file may still be a namespace (`void utils::helper() {}`), so it is
+        // marked out-of-line and only kept if the scan finds its declaration.
+        static FUNCTION_QUERY: OnceLock<Query> = OnceLock::new();
+        let function_query = FUNCTION_QUERY.get_or_init(|| {
+            Query::new(tree_sitter_cpp::language(), CPP_FUNCTION_QUERY_STR)
+                .expect("Static C++ function query is invalid")
+        });
+
+        let mut function_cursor = QueryCursor::new();
+        for m in function_cursor.matches(function_query, root_node, content.as_bytes()) {
+            let func_node = m.captures[0].node;
+            if let Some((owner, declarator)) = qualified_method_owner(func_node, content) {
+                let mut relationships = Vec::new();
+                if let Some(params) = find_node_by_kind(declarator, "parameter_list") {
+                    extract_parameter_types(params, content, &mut relationships, None, options);
+                }
+                extract_return_type(func_node, content, &mut relationships, None, options);
+
+                if let Some(class) = classes.iter_mut().find(|c| c.name == owner) {
+                    upgrade_initialized_members(&mut class.relationships, &initialized_fields(func_node, content));
+                    class.relationships.extend(relationships);
+                } else {
+                    // Whether `ui` in `ui::Widget` is a namespace or an enclosing class is
+                    // only known from the declaration, which replaces this on merge
+                    let package = owner.rsplit_once("::").map(|(scope, _)| scope.to_string());
+                    classes.push(ClassInfo {
+                        name: owner,
+                        methods: Vec::new(),
+                        properties: Vec::new(),
+                        relationships,
+                        stereotype: None,
+                        language: Some(self.name().to_string()),
+                        line: func_node.start_position().row as u32 + 1,
+                        column: func_node.start_position().column as u32 + 1,
+                        out_of_line: true,
+                        package,
+                    });
+                }
+            }
+        }
+
         Ok(classes)
     }
 }
 
+/// The class's own name with template arguments stripped, falling back to the
+/// typedef name for an unnamed struct.
+fn class_name(class_node: Node, content: &str) -> Option<String> {
+    if let Some(name_node) = class_node.child_by_field_name("name") {
+        // Specializations (`class Container<int>`) are named after their template
+        return Some(strip_template_args(&get_node_text(name_node, content)));
+    }
+    let typedef = class_node.parent().filter(|p| p.kind() == "type_definition")?;
+    let declarator = typedef.child_by_field_name("declarator")?;
+    find_node_by_kind(declarator, "type_identifier").map(|n| get_node_text(n, content))
+}
+
+/// Members a constructor's initializer list gives a newly created object
+/// (`: widget_(new Widget())`); members initialized from anything else, such
+/// as an injected parameter, stay borrowed.
+fn initialized_fields(func_node: Node, content: &str) -> Vec<String> {
+    let mut cursor = func_node.walk();
+    let Some(list) = func_node.children(&mut cursor).find(|c| c.kind() == "field_initializer_list") else {
+        return Vec::new();
+    };
+    let mut l_cursor = list.walk();
+    let fields = list.named_children(&mut l_cursor)
+        .filter(|c| c.kind() == "field_initializer" && creates_object(*c, content))
+        .filter_map(|initializer| find_node_by_kind(initializer, "field_identifier"))
+        .map(|name| get_node_text(name, content))
+        .collect();
+    fields
+}
+
+/// Whether an initializer's argument is a `new` expression or a
+/// `make_unique`/`make_shared` call.
+fn creates_object(initializer: Node, content: &str) -> bool {
+    let mut cursor = initializer.walk();
+    let Some(args) = initializer.named_children(&mut cursor).find(|c| matches!(c.kind(), "argument_list" | "initializer_list")) else {
+        return false;
+    };
+    let mut a_cursor = args.walk();
+    let first = args.named_children(&mut a_cursor).next();
+    first.is_some_and(|arg| match arg.kind() {
+        "new_expression" => true,
+        "call_expression" => arg.child_by_field_name("function").is_some_and(|function| {
+            let name = strip_template_args(&get_node_text(function, content));
+            matches!(name.rsplit("::").next(), Some("make_unique" | "make_shared"))
+        }),
+        _ => false,
+    })
+}
+
+/// A member the constructor creates is owned by the class, even when held by pointer.
+fn upgrade_initialized_members(relationships: &mut [Relationship], members: &[String]) {
+    for rel in relationships.iter_mut() {
+        if rel.rel_type == RelationshipType::Aggregation && rel.label.as_ref().is_some_and(|l| members.contains(l)) {
+            rel.rel_type = RelationshipType::Composition;
+        }
+    }
+}
+
+fn is_local_class(class_node: Node) -> bool {
+    let mut curr = class_node.parent();
+    while let Some(n) = curr {
+        if n.kind() == "function_definition" {
+            return true;
+        }
+        curr = n.parent();
+    }
+    false
+}
+
+/// Resolves the owning class of an out-of-line member definition such as
+/// `void ui::Widget::draw()`, prefixed with any enclosing namespaces.
+/// Member functions are only declared inside the class body, so the visibility lives there.
+fn qualified_method_owner<'a>(func_node: Node<'a>, content: &str) -> Option<(String, Node<'a>)> {
+    let mut scopes = Vec::new();
+    let mut curr = func_node.parent();
+    while let Some(n) = curr {
+        match n.kind() {
+            "class_specifier" | "struct_specifier" | "union_specifier" | "function_definition" => return None,
+            "namespace_definition" => {
+                if let Some(name_node) = n.child_by_field_name("name") {
+                    scopes.push(get_node_text(name_node, content));
+                }
+            }
+            _ => {}
+        }
+        curr = n.parent();
+    }
+    scopes.reverse();
+
+    let declarator = find_function_declarator(func_node)?;
+    let name_node = declarator.child_by_field_name("declarator")?;
+    if name_node.kind() != "qualified_identifier" {
+        return None;
+    }
+
+    let qualified_name = get_node_text(name_node, content);
+    let (owner, _method) = qualified_name.rsplit_once("::")?;
+    scopes.push(owner.to_string());
+    Some((scopes.join("::"), declarator))
+}
+
+/// Extracts base classes; virtual bases (`public virtual Base`) are labelled `virtual`.
 fn extract_inheritance(node: Node, content: &str, relationships: &mut Vec<Relationship>) {
+    extract_bases(node, content, relationships, false);
+}
+
+fn extract_bases(node: Node, content: &str, relationships: &mut Vec<Relationship>, virtual_base: bool) {
+    let mut virtual_base = virtual_base;
     let mut cursor = node.walk();
     for child in node.children(&mut cursor) {
-        if child.kind() == "type_identifier" || child.kind() == "qualified_identifier" {
-            let parent = get_node_text(child, content);
-            relationships.push(Relationship {
-                target: parent,
-                rel_type: RelationshipType::Inheritance,
-                label: None,
-            });
-        } else {
-            extract_inheritance(child, content, relationships);
+        match child.kind() {
+            "virtual" => virtual_base = true,
+            "," => virtual_base = false,
+            "type_identifier" | "qualified_identifier" | "template_type" => {
+                let parent = strip_template_args(&get_node_text(child, content));
+                relationships.push(Relationship {
+                    target: parent,
+                    rel_type: RelationshipType::Inheritance,
+                    label: virtual_base.then(|| "virtual".to_string()),
+                    member_visibility: None,
+                    provenance: Some("base clause".to_string()),
+                    cardinality: None,
+                });
+            }
+            _ => extract_bases(child, content, relationships, virtual_base),
         }
     }
 }
 
-fn extract_type(node: Node, content: &str, types: &mut Vec<String>) {
+fn extract_type(node: Node, content: &str, types: &mut Vec<String>, options: &ParseOptions, depth: usize) {
+    if type_depth_exceeded(node, depth, options) {
+        return;
+    }
     match node.kind() {
-        "type_identifier" | "qualified_identifier" => {
-             let type_name = get_node_text(node, content);
-             if !is_builtin_type(&type_name) {
+        "type_identifier" | "qualified_identifier" | "template_type" => {
+             // `ns::Container<Widget>` links to `ns::Container` and to `Widget`
+             let type_name = strip_template_args(&get_node_text(node, content));
+             if !is_builtin(&type_name, is_builtin_type, options) {
                  types.push(type_name);
              }
-        }
-        "template_type" => {
-             if let Some(args) = node.child_by_field_name("arguments") {
+             if let Some(args) = find_node_by_kind(node, "template_argument_list") {
                  let mut cursor = args.walk();
                  for child in args.children(&mut cursor) {
-                     extract_type(child, content, types);
+                     extract_type(child, content, types, options, depth + 1);
                  }
              }
         }
         _ => {
             let mut cursor = node.walk();
             for child in node.children(&mut cursor) {
-                extract_type(child, content, types);
+                extract_type(child, content, types, options, depth);
             }
         }
     }
 }
 
-fn extract_parameter_types(params_node: Node, content: &str, relationships: &mut Vec<Relationship>) {
+fn extract_parameter_types(params_node: Node, content: &str, relationships: &mut Vec<Relationship>, visibility: Option<Visibility>, options: &ParseOptions) {
+    let method = params_node.parent().map(|d| function_name(d, content)).unwrap_or_default();
     let mut cursor = params_node.walk();
     for child in params_node.children(&mut cursor) {
         if child.kind() == "parameter_declaration" {
             if let Some(type_node) = child.child_by_field_name("type") {
                  let mut types = Vec::new();
-                 extract_type(type_node, content, &mut types);
+                 extract_type(type_node, content, &mut types, options, 0);
                  for type_name in types {
                     relationships.push(Relationship {
                         target: type_name,
                         rel_type: RelationshipType::Dependency,
                         label: None,
+                        member_visibility: visibility,
+                        provenance: Some(format!("param in method {}", method)),
+                        cardinality: None,
                     });
                 }
             }
@@ -239,22 +454,54 @@ fn extract_parameter_types(params_node: Node, content: &str, relationships: &mut
     }
 }
 
-fn extract_return_type(node: Node, content: &str, relationships: &mut Vec<Relationship>) {
+fn extract_return_type(node: Node, content: &str, relationships: &mut Vec<Relationship>, visibility: Option<Visibility>, options: &ParseOptions) {
+    let method = find_function_declarator(node).map(|d| function_name(d, content)).unwrap_or_default();
     if let Some(type_node) = node.child_by_field_name("type") {
          let mut types = Vec::new();
-         extract_type(type_node, content, &mut types);
+         extract_type(type_node, content, &mut types, options, 0);
          for type_name in types {
              if type_name != "void" {
                 relationships.push(Relationship {
                     target: type_name,
                     rel_type: RelationshipType::Dependency,
                     label: None,
+                    member_visibility: visibility,
+                    provenance: Some(format!("return type of method {}", method)),
+                    cardinality: None,
                 });
              }
          }
     }
 }
 
+/// Whether a field declaration carries one of `qualifiers`, either as a type
+/// qualifier (`const`, `mutable`, ...) or a storage class (`static`). Behind a
+/// pointer, a leading `const`/`volatile` qualifies the pointee (`const T* p` is
+/// a mutable pointer), so only those after the last `*` count (`T* const p`).
+fn has_qualifier(field: Node, content: &str, qualifiers: &[&str]) -> bool {
+    let wanted = |q: &Node| qualifiers.contains(&get_node_text(*q, content).as_str());
+    let pointer = field.child_by_field_name("declarator").and_then(innermost_pointer);
+    let mut cursor = field.walk();
+    let leading = field.children(&mut cursor)
+        .filter(|child| matches!(child.kind(), "type_qualifier" | "storage_class_specifier"))
+        .filter(|q| pointer.is_none() || !matches!(get_node_text(*q, content).as_str(), "const" | "volatile"))
+        .any(|q| wanted(&q));
+    leading || pointer.is_some_and(|pointer| {
+        let mut p_cursor = pointer.walk();
+        let own = pointer.children(&mut p_cursor)
+            .filter(|child| child.kind() == "type_qualifier")
+            .any(|q| wanted(&q));
+        own
+    })
+}
+
+/// The pointer or reference declarator closest to the declared name, if any.
+fn innermost_pointer(declarator: Node) -> Option<Node> {
+    declarator.child_by_field_name("declarator")
+        .and_then(innermost_pointer)
+        .or_else(|| matches!(declarator.kind(), "pointer_declarator" | "reference_declarator").then_some(declarator))
+}
+
 fn has_initializer(declarator: Node) -> bool {
     declarator.kind() == "init_declarator"
 }
@@ -272,6 +519,33 @@ fn is_pointer_or_reference_wrapper(node: Node) -> bool {
     }
 }
 
+/// Names of the type parameters of every template enclosing `node`.
+fn template_parameters(node: Node, content: &str) -> Vec<String> {
+    let mut params = Vec::new();
+    let mut curr = node.parent();
+    while let Some(n) = curr {
+        if n.kind() == "template_declaration" {
+            if let Some(list) = n.child_by_field_name("parameters") {
+                let mut cursor = list.walk();
+                for param in list.children(&mut cursor) {
+                    if param.kind().ends_with("type_parameter_declaration") {
+                        if let Some(id) = find_node_by_kind(param, "type_identifier") {
+                            params.push(get_node_text(id, content));
+                        }
+                    }
+                }
+            }
+        }
+        curr = n.parent();
+    }
+    params
+}
+
+/// Drops a template argument list: `Container<int>` becomes `Container`.
+fn strip_template_args(name: &str) -> String {
+    name.split('<').next().unwrap_or(name).trim().to_string()
+}
+
 fn find_node_by_kind<'a>(node: Node<'a>, kind: &str) -> Option<Node<'a>> {
     if node.kind() == kind { return Some(node); }
     let mut cursor = node.walk();
@@ -281,6 +555,14 @@ fn find_node_by_kind<'a>(node: Node<'a>, kind: &str) -> Option<Node<'a>> {
     None
 }
 
+/// The unqualified name declared by a `function_declarator` (`draw` for `Widget::draw`).
+fn function_name(declarator: Node, content: &str) -> String {
+    let name = declarator.child_by_field_name("declarator")
+        .map(|n| get_node_text(n, content))
+        .unwrap_or_default();
+    name.rsplit("::").next().unwrap_or_default().to_string()
+}
+
 fn find_function_declarator<'a>(node: Node<'a>) -> Option<Node<'a>> {
     if node.kind() == "function_declarator" { return Some(node); }
     let mut cursor = node.walk();
@@ -290,6 +572,58 @@ fn find_function_declarator<'a>(node: Node<'a>) -> Option<Node<'a>> {
     None
 }
 
+/// The name a member variable is known by without its decoration: `name_`,
+/// `m_name` and `_name` all become `name`.
+fn field_base_name(field: &str) -> &str {
+    let name = field.strip_prefix("m_").unwrap_or(field);
+    name.trim_start_matches('_').trim_end_matches('_')
+}
+
+/// Whether `method` is `prefix` followed by `base` in camelCase (`getName`) or snake_case (`get_name`).
+fn is_accessor_of(method: &str, prefix: &str, base: &str) -> bool {
+    let Some(rest) = method.strip_prefix(prefix) else {
+        return false;
+    };
+    if let Some(snake) = rest.strip_prefix('_') {
+        return snake == base;
+    }
+    let mut chars = base.chars();
+    chars.next().is_some_and(|first| rest.starts_with(first.to_ascii_uppercase()) && rest[first.len_utf8()..] == *chars.as_str())
+}
+
+/// Replaces each private field that has a getter (`getX`/`isX`, and optionally
+/// a setter `setX`) with a property named after the field, shown with the
+/// getter's visibility. The accessors are dropped and the field's relationships
+/// are relabelled to the promoted name. `const` and reference-returning getters
+/// need no special handling since only names are compared.
+fn collapse_accessor_pairs(methods: &mut Vec<MethodInfo>, properties: &mut [PropertyInfo], relationships: &mut [Relationship]) {
+    for property in properties.iter_mut() {
+        if property.visibility != Visibility::Private {
+            continue;
+        }
+        let base = field_base_name(&property.name).to_string();
+        if base.is_empty() {
+            continue;
+        }
+        let Some(getter) = methods.iter()
+            .find(|m| m.visibility != Visibility::Private && (is_accessor_of(&m.name, "get", &base) || is_accessor_of(&m.name, "is", &base)))
+        else {
+            continue;
+        };
+        let visibility = getter.visibility;
+        methods.retain(|m| !["get", "is", "set"].iter().any(|prefix| is_accessor_of(&m.name, prefix, &base)));
+
+        for relationship in relationships.iter_mut() {
+            if relationship.label.as_deref() == Some(property.name.as_str()) {
+                relationship.label = Some(base.clone());
+                relationship.member_visibility = Some(visibility);
+            }
+        }
+        property.name = base;
+        property.visibility = visibility;
+    }
+}
+
 fn is_builtin_type(type_name: &str) -> bool {
     matches!(
         type_name,
@@ -340,6 +674,20 @@ private:
         Ok(())
     }
 
+    #[test]
+    fn test_cpp_class_location() -> Result<()> {
+        let content = "
+#include <string>
+
+namespace UI {
+    class Button {};
+}
+";
+        let classes = CppParser.parse(content)?;
+        assert_eq!((classes[0].line, classes[0].column), (5, 5));
+        Ok(())
+    }
+
     #[test]
     fn test_cpp_namespace() -> Result<()> {
         let content = "
@@ -456,6 +804,52 @@ public:
         Ok(())
     }
 
+    #[test]
+    fn test_parse_class_templates() -> Result<()> {
+        let content = "
+template<typename T>
+class Container {
+    T item;
+};
+
+template<>
+class Container<int> {};
+
+class Inventory : public Base<Item> {
+    Container<Widget> widgets;
+    std::vector<Part> parts;
+};
+";
+        let classes = CppParser.parse(content)?;
+        assert!(classes.iter().all(|c| c.name == "Container" || c.name == "Inventory"));
+
+        let container = classes.iter().find(|c| c.name == "Container").unwrap();
+        assert!(!container.relationships.iter().any(|r| r.target == "T"));
+
+        let inventory = classes.iter().find(|c| c.name == "Inventory").unwrap();
+        assert!(inventory.relationships.iter().any(|r| r.target == "Base" && r.rel_type == RelationshipType::Inheritance));
+        assert!(!inventory.relationships.iter().any(|r| r.target == "Item"));
+        assert!(inventory.relationships.iter().any(|r| r.target == "Container" && r.label.as_deref() == Some("widgets")));
+        assert!(inventory.relationships.iter().any(|r| r.target == "Widget" && r.label.as_deref() == Some("widgets")));
+        assert!(inventory.relationships.iter().any(|r| r.target == "Part"));
+        assert!(!inventory.relationships.iter().any(|r| r.target.contains('<') || r.target.starts_with("std::")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_virtual_inheritance() -> Result<()> {
+        let content = "class D : public virtual Base, public Mixin {};";
+        let classes = CppParser.parse(content)?;
+        let d = &classes[0];
+        assert!(d.relationships.iter().any(|r| r.target == "Base"
+            && r.rel_type == RelationshipType::Inheritance
+            && r.label.as_deref() == Some("virtual")));
+        assert!(d.relationships.iter().any(|r| r.target == "Mixin"
+            && r.rel_type == RelationshipType::Inheritance
+            && r.label.is_none()));
+        Ok(())
+    }
+
     #[test]
     fn test_parse_function_pointer_parameter_dependency() -> Result<()> {
         let content = "
@@ -486,4 +880,260 @@ class Handler2 {
         assert!(handler2.relationships.iter().any(|r| r.target == "ReturnType" && r.rel_type == RelationshipType::Dependency));
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse_field_qualifiers() -> Result<()> {
+        let content = "
+class Cache {
+public:
+    static const int N = 16;
+    mutable std::mutex lock;
+    volatile int hits;
+};
+";
+        let classes = CppParser.parse(content)?;
+        let cache = &classes[0];
+        let n = cache.properties.iter().find(|p| p.name == "N").unwrap();
+        assert!(n.is_static && n.is_const);
+        assert!(n.qualifiers.is_empty());
+        let lock = cache.properties.iter().find(|p| p.name == "lock").unwrap();
+        assert!(!lock.is_static && !lock.is_const);
+        assert_eq!(lock.qualifiers, vec!["mutable"]);
+        let hits = cache.properties.iter().find(|p| p.name == "hits").unwrap();
+        assert_eq!(hits.qualifiers, vec!["volatile"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_constant_fields() -> Result<()> {
+        let content = "
+class Buffer {
+public:
+    static constexpr int N = 5;
+    const int capacity = 1;
+    int size;
+};
+";
+        let classes = CppParser.parse(content)?;
+        let buffer = &classes[0];
+        assert!(buffer.properties.iter().any(|p| p.name == "N" && p.is_const));
+        assert!(buffer.properties.iter().any(|p| p.name == "capacity" && p.is_const));
+        assert!(buffer.properties.iter().any(|p| p.name == "size" && !p.is_const));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_const_pointer_fields() -> Result<()> {
+        let content = "
+class Cursor {
+    const Node* current;
+    Node* const root;
+    const Node* const sentinel;
+    volatile Node* polled;
+};
+";
+        let classes = CppParser.parse(content)?;
+        let property = |name: &str| classes[0].properties.iter().find(|p| p.name == name).unwrap();
+        // A mutable pointer to a const node
+        assert!(!property("current").is_const);
+        assert!(property("root").is_const);
+        assert!(property("sentinel").is_const);
+        assert!(property("polled").qualifiers.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_out_of_line_definitions() -> Result<()> {
+        let content = "
+namespace ui {
+void Widget::draw(Canvas* canvas) {}
+}
+Theme* ui::Widget::theme() { return nullptr; }
+";
+        let classes = CppParser.parse(content)?;
+        assert_eq!(classes.len(), 1);
+        let widget = &classes[0];
+        assert_eq!(widget.name, "ui::Widget");
+        assert!(widget.relationships.iter().any(|r| r.target == "Canvas" && r.rel_type == RelationshipType::Dependency));
+        assert!(widget.relationships.iter().any(|r| r.target == "Theme" && r.rel_type == RelationshipType::Dependency));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_anonymous_types() -> Result<()> {
+        let content = "
+namespace {
+class Helper {
+public:
+    struct {
+        int x;
+    } point;
+};
+}
+typedef struct {
+    int id;
+} Record;
+";
+        let classes = CppParser.parse(content)?;
+        let names: Vec<_> = classes.iter().map(|c| c.name.as_str()).collect();
+        assert!(!names.iter().any(|n| n.is_empty() || n.starts_with("::")));
+        assert!(names.contains(&"Helper"));
+        assert!(names.contains(&"Record"));
+        assert_eq!(classes.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_skips_local_classes() -> Result<()> {
+        let content = "
+class Sorter {
+public:
+    void sort() {
+        struct Compare {
+            bool operator()(int a, int b) const { return a < b; }
+        };
+    }
+};
+";
+        let classes = CppParser.parse(content)?;
+        let names: Vec<_> = classes.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["Sorter"]);
+
+        let options = ParseOptions { include_local_classes: true, ..ParseOptions::default() };
+        let classes = CppParser.parse_with_options(content, &options)?;
+        assert!(classes.iter().any(|c| c.name == "Sorter::Compare"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_union_members_are_public() -> Result<()> {
+        let content = "
+union Value {
+    int integer;
+    Number number;
+};
+";
+        let classes = CppParser.parse(content)?;
+        assert_eq!(classes.len(), 1);
+        let value = &classes[0];
+        assert_eq!(value.name, "Value");
+        assert_eq!(value.properties.len(), 2);
+        assert!(value.properties.iter().all(|p| p.visibility == Visibility::Public));
+        assert!(value.relationships.iter().any(|r| r.target == "Number"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_nested_class_visibility_is_scoped() -> Result<()> {
+        let content = "
+struct Outer {
+private:
+    class Inner : public Base {
+        int hidden;
+    public:
+        int shown;
+    };
+    struct Point {
+        int x;
+    };
+    int secret;
+};
+";
+        let classes = CppParser.parse(content)?;
+        let class = |name: &str| classes.iter().find(|c| c.name == name).unwrap();
+        let visibility = |class: &ClassInfo, prop: &str| {
+            class.properties.iter().find(|p| p.name == prop).unwrap().visibility
+        };
+
+        assert_eq!(visibility(class("Outer"), "secret"), Visibility::Private);
+        assert_eq!(visibility(class("Outer::Inner"), "hidden"), Visibility::Private);
+        assert_eq!(visibility(class("Outer::Inner"), "shown"), Visibility::Public);
+        assert_eq!(visibility(class("Outer::Point"), "x"), Visibility::Public);
+        // The nested class's base stays with the nested class
+        assert!(!class("Outer").relationships.iter().any(|r| r.target == "Base"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_initializer_list_composition() -> Result<()> {
+        let content = "
+class Window {
+public:
+    Window(Repository* r) : widget_(new Widget()), repo_(r)) {}
+private:
+    Widget* widget_;
+    Theme* theme_;
+    Repository* repo_;
+};
+class Dialog {
+    Button* ok_;
+};
+Dialog::Dialog() : ok_(new Button()) {}
+";
+        let classes = CppParser.parse(content)?;
+        let rel_type = |class: &str, label: &str| {
+            let class = classes.iter().find(|c| c.name == class).unwrap();
+            class.relationships.iter().find(|r| r.label.as_deref() == Some(label)).unwrap().rel_type.clone()
+        };
+        assert_eq!(rel_type("Window", "widget_"), RelationshipType::Composition);
+        assert_eq!(rel_type("Window", "theme_"), RelationshipType::Aggregation);
+        // Injected, so borrowed rather than owned
+        assert_eq!(rel_type("Window", "repo_"), RelationshipType::Aggregation);
+        assert_eq!(rel_type("Dialog", "ok_"), RelationshipType::Composition);
+        Ok(())
+    }
+
+    #[test]
+    fn test_collapse_accessor_pairs() -> Result<()> {
+        let content = "
+class Person {
+public:
+    const std::string& getName() const { return name_; }
+    void setName(const std::string& name) { name_ = name; }
+    bool isActive() const { return m_active; }
+    Address* getAddress() { return address_; }
+    void greet();
+private:
+    std::string name_;
+    bool m_active;
+    Address* address_;
+    int age_;
+};
+";
+        let options = ParseOptions { collapse_accessor_pairs: true, ..ParseOptions::default() };
+        let classes = CppParser.parse_with_options(content, &options)?;
+        let person = &classes[0];
+
+        let methods: Vec<_> = person.methods.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(methods, vec!["greet"]);
+        let properties: Vec<_> = person.properties.iter().map(|p| (p.name.as_str(), p.visibility)).collect();
+        assert_eq!(properties, vec![
+            ("name", Visibility::Public),
+            ("active", Visibility::Public),
+            ("address", Visibility::Public),
+            ("age_", Visibility::Private),
+        ]);
+        let address = person.relationships.iter().find(|r| r.target == "Address" && r.rel_type == RelationshipType::Aggregation).unwrap();
+        assert_eq!(address.label.as_deref(), Some("address"));
+        assert_eq!(address.member_visibility, Some(Visibility::Public));
+
+        // Off by default
+        let classes = CppParser.parse(content)?;
+        assert!(classes[0].methods.iter().any(|m| m.name == "getName"));
+        assert!(classes[0].properties.iter().any(|p| p.name == "name_"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_type_depth_counts_template_levels() -> Result<()> {
+        let options = ParseOptions { max_type_depth: 2, ..ParseOptions::default() };
+        let classes = CppParser.parse_with_options("
+class Holder {
+    Outer<Middle<Inner<Deepest>>> items;
+};
+", &options)?;
+        let targets: Vec<_> = classes[0].relationships.iter().map(|r| r.target.as_str()).collect();
+        assert_eq!(targets, vec!["Outer", "Middle", "Inner"]);
+        Ok(())
+    }
+}
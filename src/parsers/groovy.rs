@@ -0,0 +1,179 @@
+use crate::models::{ClassInfo, Visibility};
+use anyhow::Result;
+use super::java::JavaParser;
+use super::{LanguageParser, ParseOptions};
+
+/// Groovy, read with the Java grammar as a first cut. Groovy class bodies are
+/// close enough to Java for declarations; the few constructs that would throw
+/// the Java grammar off track (`trait`, list and map literals) are rewritten
+/// first, keeping line numbers intact.
+pub struct GroovyParser;
+
+impl LanguageParser for GroovyParser {
+    fn name(&self) -> &str {
+        "groovy"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["groovy", "gradle"]
+    }
+
+    fn parse_with_options(&self, content: &str, options: &ParseOptions) -> Result<Vec<ClassInfo>> {
+        let (source, traits) = as_java(content);
+        let mut classes = JavaParser.parse_with_options(&source, options)?;
+
+        for class in &mut classes {
+            class.language = Some(self.name().to_string());
+            let short_name = class.name.rsplit('.').next().unwrap_or(&class.name);
+            if traits.iter().any(|t| t == short_name) {
+                class.stereotype = Some("trait".to_string());
+            }
+
+            // Without an access modifier Groovy members are public, not package-private
+            for method in &mut class.methods {
+                if method.visibility == Visibility::Internal {
+                    method.visibility = Visibility::Public;
+                }
+            }
+            for property in &mut class.properties {
+                if property.visibility == Visibility::Internal {
+                    property.visibility = Visibility::Public;
+                }
+            }
+            for rel in &mut class.relationships {
+                if rel.member_visibility == Some(Visibility::Internal) {
+                    rel.member_visibility = Some(Visibility::Public);
+                }
+            }
+
+            // `def` members are dynamically typed
+            class.relationships.retain(|r| r.target != "def");
+        }
+        Ok(classes)
+    }
+}
+
+/// Rewrites Groovy source into something the Java grammar can follow, line by
+/// line: `trait T` becomes `interface T` and `= [...]` initializers become
+/// `= null`. Returns the rewritten source and the names of the traits.
+fn as_java(content: &str) -> (String, Vec<String>) {
+    let mut traits = Vec::new();
+    let lines: Vec<String> = content.split('\n')
+        .map(|line| {
+            let mut line = line.to_string();
+            if let Some((keyword, name)) = trait_declaration(&line) {
+                traits.push(name);
+                line.replace_range(keyword..keyword + "trait".len(), "interface");
+            }
+            blank_collection_literal(&line).unwrap_or(line)
+        })
+        .collect();
+    (lines.join("\n"), traits)
+}
+
+/// The byte offset of the `trait` keyword and the name declared by a
+/// `[modifiers] trait Name ...` line.
+fn trait_declaration(line: &str) -> Option<(usize, String)> {
+    let mut searched = 0;
+    let mut words = line.split_whitespace()
+        .map(|word| {
+            let at = searched + line[searched..].find(word).unwrap_or(0);
+            searched = at + word.len();
+            (at, word)
+        })
+        .skip_while(|(_, w)| matches!(*w, "public" | "protected" | "private" | "abstract" | "static") || w.starts_with('@'));
+    let (keyword, word) = words.next()?;
+    if word != "trait" {
+        return None;
+    }
+    let name: String = words.next()?.1.chars().take_while(|c| c.is_alphanumeric() || *c == '_').collect();
+    (!name.is_empty()).then_some((keyword, name))
+}
+
+/// `List<Toy> toys = [a, b]` (or `[:]`) as `List<Toy> toys = null`, when the
+/// literal closes on the same line.
+fn blank_collection_literal(line: &str) -> Option<String> {
+    let equals = line.find('=')?;
+    let rest = &line[equals + 1..];
+    let open = equals + 1 + rest.find(|c: char| !c.is_whitespace())?;
+    if !line[open..].starts_with('[') {
+        return None;
+    }
+    let mut depth = 0;
+    for (i, c) in line[open..].char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(format!("{}null{}", &line[..open], &line[open + i + 1..]));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::RelationshipType;
+
+    #[test]
+    fn test_parse_groovy_class() -> Result<()> {
+        let content = "
+package shop
+
+class Dog extends Animal implements Pet {
+    Owner owner
+    private String name
+    def tag
+    protected List<Toy> toys = []
+
+    void bark(Bone bone) {
+        println \"woof\"
+    }
+
+    def fetch(x) { x }
+}
+
+trait Swimmer {
+    String swim() { \"swimming\" }
+}
+";
+        let classes = GroovyParser.parse(content)?;
+        let dog = classes.iter().find(|c| c.name == "Dog").unwrap();
+        assert_eq!(dog.language.as_deref(), Some("groovy"));
+
+        let properties: Vec<_> = dog.properties.iter().map(|p| (p.name.as_str(), p.visibility)).collect();
+        assert_eq!(properties, vec![
+            ("owner", Visibility::Public),
+            ("name", Visibility::Private),
+            ("tag", Visibility::Public),
+            ("toys", Visibility::Protected),
+        ]);
+        let methods: Vec<_> = dog.methods.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(methods, vec!["bark", "fetch"]);
+
+        let edges: Vec<_> = dog.relationships.iter().map(|r| (r.target.as_str(), r.rel_type.clone(), r.label.as_deref())).collect();
+        assert!(edges.contains(&("Animal", RelationshipType::Inheritance, None)));
+        assert!(edges.contains(&("Pet", RelationshipType::Realization, None)));
+        assert!(edges.contains(&("Owner", RelationshipType::Aggregation, Some("owner"))));
+        assert!(edges.contains(&("Toy", RelationshipType::Aggregation, Some("toys"))));
+        assert!(edges.contains(&("Bone", RelationshipType::Dependency, None)));
+        assert!(!edges.iter().any(|(target, _, _)| *target == "def"));
+
+        let swimmer = classes.iter().find(|c| c.name == "Swimmer").unwrap();
+        assert_eq!(swimmer.stereotype.as_deref(), Some("trait"));
+        assert_eq!(swimmer.line, 17);
+        Ok(())
+    }
+
+    #[test]
+    fn test_trait_keyword_is_rewritten() {
+        let (source, traits) = as_java("@Portrait trait Framed {}\nclass Gallery {}");
+        assert_eq!(source, "@Portrait interface Framed {}\nclass Gallery {}");
+        assert_eq!(traits, vec!["Framed"]);
+    }
+}
@@ -2,27 +2,32 @@ use std::sync::OnceLock;
 use tree_sitter::{Parser, Query, QueryCursor, Node};
 use crate::models::{ClassInfo, Relationship, RelationshipType, Visibility, MethodInfo, PropertyInfo};
 use anyhow::{Result, Context};
-use super::LanguageParser;
+use super::{apply_relationship_directives, is_builtin, parse_tree, type_depth_exceeded, LanguageParser, ParseOptions};
 
 const JAVA_CLASS_QUERY_STR: &str = "
     (class_declaration) @class
     (interface_declaration) @interface
+    (record_declaration) @record
 ";
 
 pub struct JavaParser;
 
 impl LanguageParser for JavaParser {
+    fn name(&self) -> &str {
+        "java"
+    }
+
     fn extensions(&self) -> &[&str] {
         &["java"]
     }
 
-    fn parse(&self, content: &str) -> Result<Vec<ClassInfo>> {
+    fn parse_with_options(&self, content: &str, options: &ParseOptions) -> Result<Vec<ClassInfo>> {
         let mut parser = Parser::new();
         let language = tree_sitter_java::language();
         parser.set_language(language)
             .context("Error loading Java grammar")?;
 
-        let tree = parser.parse(content, None)
+        let tree = parse_tree(&mut parser, content, options)
             .context("Failed to parse Java content")?;
 
         let root_node = tree.root_node();
@@ -37,6 +42,15 @@ impl LanguageParser for JavaParser {
         let mut query_cursor = QueryCursor::new();
         let matches = query_cursor.matches(query, root_node, content.as_bytes());
 
+        let mut package_cursor = root_node.walk();
+        let package = root_node.children(&mut package_cursor)
+            .find(|n| n.kind() == "package_declaration")
+            .and_then(|n| n.named_child(0))
+            .map(|n| get_node_text(n, content));
+
+        // (permitted subtype, sealed supertype, position of the `permits` clause)
+        let mut permitted = Vec::new();
+
         for m in matches {
             let class_node = m.captures[0].node;
             
@@ -44,7 +58,7 @@ impl LanguageParser for JavaParser {
             let mut name_parts = Vec::new();
             let mut curr = Some(class_node);
             while let Some(n) = curr {
-                if n.kind() == "class_declaration" || n.kind() == "interface_declaration" {
+                if matches!(n.kind(), "class_declaration" | "interface_declaration" | "record_declaration") {
                     if let Some(name_node) = n.child_by_field_name("name") {
                         name_parts.push(get_node_text(name_node, content));
                     }
@@ -63,19 +77,64 @@ impl LanguageParser for JavaParser {
             for child in class_node.children(&mut inheritance_cursor) {
                 match child.kind() {
                     "superclass" | "super_interfaces" | "extends_interfaces" => {
-                        extract_inheritance(child, content, &mut relationships);
+                        // A class implementing an interface realizes it; interfaces extend each other
+                        let (rel_type, provenance) = match child.kind() {
+                            "super_interfaces" => (RelationshipType::Realization, "implements clause"),
+                            _ => (RelationshipType::Inheritance, "base clause"),
+                        };
+                        extract_inheritance(child, content, rel_type, provenance, &mut relationships);
                     }
                     _ => {}
                 }
             }
 
-            // 2. Body: Fields and Methods
+            // 2. Record components: implicitly public final fields
+            if class_node.kind() == "record_declaration" {
+                if let Some(params_node) = class_node.child_by_field_name("parameters") {
+                    let mut p_cursor = params_node.walk();
+                    for param in params_node.children(&mut p_cursor) {
+                        if param.kind() == "formal_parameter" {
+                            if let Some(name_node) = param.child_by_field_name("name") {
+                                let component_name = get_node_text(name_node, content);
+                                // Record components are implicitly final
+                                properties.push(PropertyInfo {
+                                    name: component_name.clone(),
+                                    visibility: Visibility::Public,
+                                    is_const: true,
+                                    is_static: false,
+                                    qualifiers: Vec::new(),
+                                });
+
+                                if let Some(type_node) = param.child_by_field_name("type") {
+                                    let mut resolved = Vec::new();
+                                    resolve_java_types(type_node, content, &mut resolved, options, 0);
+                                    for t in resolved {
+                                        relationships.push(Relationship {
+                                            target: t,
+                                            rel_type: RelationshipType::Aggregation,
+                                            label: Some(component_name.clone()),
+                                            member_visibility: Some(Visibility::Public),
+                                            provenance: Some(format!("record component {}", component_name)),
+                                            cardinality: None,
+                                        });
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            // 3. Body: Fields and Methods
             if let Some(body_node) = class_node.child_by_field_name("body") {
                 let mut cursor = body_node.walk();
                 for child in body_node.children(&mut cursor) {
                     match child.kind() {
                         "field_declaration" => {
                             let visibility = get_java_visibility(child, content);
+                            let is_const = get_modifiers_text(child, content)
+                                .split_whitespace()
+                                .any(|m| m == "final");
                             let type_node = child.child_by_field_name("type");
                             let mut cursor = child.walk();
                             for field_child in child.children(&mut cursor) {
@@ -85,13 +144,17 @@ impl LanguageParser for JavaParser {
                                         properties.push(PropertyInfo {
                                             name: field_name.clone(),
                                             visibility,
+                                            is_const,
+                                            is_static: false,
+                                            qualifiers: Vec::new(),
                                         });
 
-                                        if let Some(t_node) = type_node {
+                                        let value = field_child.child_by_field_name("value");
+                                        if let Some(t_node) = declared_or_inferred_type(type_node, value, content) {
                                             let mut resolved = Vec::new();
-                                            resolve_java_types(t_node, content, &mut resolved);
+                                            resolve_java_types(t_node, content, &mut resolved, options, 0);
                                             
-                                            let is_composition = field_child.child_by_field_name("value")
+                                            let is_composition = value
                                                 .map(|v| v.kind() == "object_creation_expression")
                                                 .unwrap_or(false);
 
@@ -100,12 +163,16 @@ impl LanguageParser for JavaParser {
                                             } else {
                                                 RelationshipType::Aggregation
                                             };
+                                            let cardinality = optional_cardinality(t_node, child, content);
 
                                             for t in resolved {
                                                 relationships.push(Relationship {
                                                     target: t,
                                                     rel_type: rel_type.clone(),
                                                     label: Some(field_name.clone()),
+                                                    member_visibility: Some(visibility),
+                                                    provenance: Some(format!("field {}: {}", field_name, get_node_text(t_node, content))),
+                                                    cardinality: cardinality.clone(),
                                                 });
                                             }
                                         }
@@ -120,8 +187,10 @@ impl LanguageParser for JavaParser {
                                 
                                 if child.kind() == "method_declaration" {
                                     methods.push(MethodInfo {
-                                        name: method_name,
+                                        name: method_name.clone(),
                                         visibility,
+                                        is_abstract: is_abstract_method(child, class_node, content),
+                                        annotations: get_annotations(child, content),
                                     });
                                 }
 
@@ -132,7 +201,7 @@ impl LanguageParser for JavaParser {
                                         if param.kind() == "formal_parameter" {
                                             if let Some(type_node) = param.child_by_field_name("type") {
                                                 let mut resolved = Vec::new();
-                                                resolve_java_types(type_node, content, &mut resolved);
+                                                resolve_java_types(type_node, content, &mut resolved, options, 0);
                                                 for t in resolved {
                                                     let rel_type = if child.kind() == "constructor_declaration" {
                                                         RelationshipType::Aggregation
@@ -143,6 +212,9 @@ impl LanguageParser for JavaParser {
                                                         target: t,
                                                         rel_type,
                                                         label: None,
+                                                        member_visibility: Some(visibility),
+                                                        provenance: Some(format!("param in method {}", method_name)),
+                                                        cardinality: optional_cardinality(type_node, param, content),
                                                     });
                                                 }
                                             }
@@ -153,15 +225,62 @@ impl LanguageParser for JavaParser {
                                 // Return type for Dependency
                                 if let Some(ret_type_node) = child.child_by_field_name("type") {
                                     let mut resolved = Vec::new();
-                                    resolve_java_types(ret_type_node, content, &mut resolved);
+                                    resolve_java_types(ret_type_node, content, &mut resolved, options, 0);
                                     for t in resolved {
                                         relationships.push(Relationship {
                                             target: t,
                                             rel_type: RelationshipType::Dependency,
                                             label: None,
+                                            member_visibility: Some(visibility),
+                                            provenance: Some(format!("return type of method {}", method_name)),
+                                            cardinality: None,
                                         });
                                     }
                                 }
+
+                                // Declared exceptions: `throws PaymentException, java.io.IOException`
+                                let mut t_cursor = child.walk();
+                                let throws = child.children(&mut t_cursor).find(|c| c.kind() == "throws");
+                                if let Some(throws) = throws {
+                                    let mut e_cursor = throws.walk();
+                                    for exception in throws.named_children(&mut e_cursor) {
+                                        // A qualified name depends on its last segment only
+                                        let type_node = match exception.kind() {
+                                            "scoped_type_identifier" => exception.named_child(exception.named_child_count().saturating_sub(1)).unwrap_or(exception),
+                                            _ => exception,
+                                        };
+                                        let mut resolved = Vec::new();
+                                        resolve_java_types(type_node, content, &mut resolved, options, 0);
+                                        for t in resolved {
+                                            relationships.push(Relationship {
+                                                target: t,
+                                                rel_type: RelationshipType::Dependency,
+                                                label: Some("throws".to_string()),
+                                                member_visibility: Some(visibility),
+                                                provenance: Some(format!("throws clause of method {}", method_name)),
+                                                cardinality: None,
+                                            });
+                                        }
+                                    }
+                                }
+
+                                // Instantiations inside the body (opt-in)
+                                if options.deep_deps {
+                                    if let Some(body) = child.child_by_field_name("body") {
+                                        let mut resolved = Vec::new();
+                                        collect_instantiated_types(body, content, &mut resolved, options);
+                                        for t in resolved {
+                                            relationships.push(Relationship {
+                                                target: t,
+                                                rel_type: RelationshipType::Dependency,
+                                                label: None,
+                                                member_visibility: Some(visibility),
+                                                provenance: Some(format!("new expression in method {}", method_name)),
+                                                cardinality: None,
+                                            });
+                                        }
+                                    }
+                                }
                             }
                         }
                         _ => {}
@@ -169,14 +288,74 @@ impl LanguageParser for JavaParser {
                 }
             }
 
+            apply_relationship_directives(class_node, content, &["class_declaration", "interface_declaration", "record_declaration", "enum_declaration"], &mut relationships);
+
+            // 4. Sealed hierarchies: `permits` lists the only allowed subtypes
+            let is_sealed = get_modifiers_text(class_node, content)
+                .split_whitespace()
+                .any(|m| m == "sealed");
+            if let Some(permits) = class_node.child_by_field_name("permits") {
+                let mut p_cursor = permits.walk();
+                for type_list in permits.children(&mut p_cursor).filter(|c| c.kind() == "type_list") {
+                    let mut t_cursor = type_list.walk();
+                    for subtype in type_list.named_children(&mut t_cursor) {
+                        permitted.push((get_node_text(subtype, content), full_name.clone(), permits.start_position()));
+                    }
+                }
+            }
+
+            let stereotype = match (is_sealed, class_node.kind() == "interface_declaration") {
+                (true, true) => Some("sealed interface".to_string()),
+                (true, false) => Some("sealed".to_string()),
+                (false, true) => Some("interface".to_string()),
+                (false, false) => None,
+            };
+
             classes.push(ClassInfo {
                 name: full_name,
                 methods,
                 properties,
                 relationships,
+                stereotype,
+                language: Some(self.name().to_string()),
+                line: class_node.start_position().row as u32 + 1,
+                column: class_node.start_position().column as u32 + 1,
+                out_of_line: false,
+                package: package.clone(),
             });
         }
 
+        // Permitted subtypes inherit from the sealed type; they may live in other files,
+        // in which case they get a member-less entry carrying just the edge
+        for (subtype, sealed, position) in permitted {
+            let edge = Relationship {
+                target: sealed,
+                rel_type: RelationshipType::Inheritance,
+                label: None,
+                member_visibility: None,
+                provenance: Some("permits clause".to_string()),
+                cardinality: None,
+            };
+            if let Some(class) = classes.iter_mut().find(|c| c.name == subtype) {
+                if !class.relationships.iter().any(|r| r.target == edge.target && r.rel_type.is_generalization()) {
+                    class.relationships.push(edge);
+                }
+            } else {
+                classes.push(ClassInfo {
+                    name: subtype,
+                    methods: Vec::new(),
+                    properties: Vec::new(),
+                    relationships: vec![edge],
+                    stereotype: None,
+                    language: Some(self.name().to_string()),
+                    line: position.row as u32 + 1,
+                    column: position.column as u32 + 1,
+                    out_of_line: false,
+                    package: package.clone(),
+                });
+            }
+        }
+
         Ok(classes)
     }
 }
@@ -186,19 +365,61 @@ fn get_java_visibility(node: Node, content: &str) -> Visibility {
     for child in node.children(&mut cursor) {
         if child.kind() == "modifiers" {
             let text = get_node_text(child, content);
-            if text.contains("public") {
-                return Visibility::Public;
-            } else if text.contains("protected") {
-                return Visibility::Protected;
-            } else if text.contains("private") {
-                return Visibility::Private;
+            if let Some(visibility) = text.split_whitespace().find_map(Visibility::from_keyword) {
+                return visibility;
             }
         }
     }
     Visibility::Internal
 }
 
-fn extract_inheritance(node: Node, content: &str, relationships: &mut Vec<Relationship>) {
+/// A method is abstract when it has no body and is either declared in an
+/// interface or explicitly marked `abstract` (bodiless `native` methods are not).
+fn is_abstract_method(method_node: Node, class_node: Node, content: &str) -> bool {
+    method_node.child_by_field_name("body").is_none()
+        && (class_node.kind() == "interface_declaration"
+            || get_modifiers_text(method_node, content).contains("abstract"))
+}
+
+/// Names of the annotations in a declaration's modifiers; `@java.lang.Deprecated` yields "Deprecated".
+fn get_annotations(node: Node, content: &str) -> Vec<String> {
+    let mut annotations = Vec::new();
+    let mut cursor = node.walk();
+    for modifiers in node.children(&mut cursor).filter(|c| c.kind() == "modifiers") {
+        let mut m_cursor = modifiers.walk();
+        for annotation in modifiers.children(&mut m_cursor) {
+            if annotation.kind() == "marker_annotation" || annotation.kind() == "annotation" {
+                if let Some(name_node) = annotation.child_by_field_name("name") {
+                    let name = get_node_text(name_node, content);
+                    annotations.push(name.rsplit('.').next().unwrap_or_default().to_string());
+                }
+            }
+        }
+    }
+    annotations
+}
+
+/// `0..1` for a field or parameter typed `Optional<T>` or annotated `@Nullable`.
+fn optional_cardinality(type_node: Node, declaration: Node, content: &str) -> Option<String> {
+    let is_optional = type_node.kind() == "generic_type"
+        && type_node.named_child(0).is_some_and(|base| {
+            let name = get_node_text(base, content);
+            name.rsplit('.').next() == Some("Optional")
+        });
+    let is_nullable = get_annotations(declaration, content).iter().any(|a| a == "Nullable");
+    (is_optional || is_nullable).then(|| "0..1".to_string())
+}
+
+fn get_modifiers_text(node: Node, content: &str) -> String {
+    let mut cursor = node.walk();
+    let modifiers = node.children(&mut cursor)
+        .find(|child| child.kind() == "modifiers")
+        .map(|child| get_node_text(child, content))
+        .unwrap_or_default();
+    modifiers
+}
+
+fn extract_inheritance(node: Node, content: &str, rel_type: RelationshipType, provenance: &str, relationships: &mut Vec<Relationship>) {
     let mut cursor = node.walk();
     for child in node.children(&mut cursor) {
         if child.kind() == "type_list" {
@@ -208,8 +429,11 @@ fn extract_inheritance(node: Node, content: &str, relationships: &mut Vec<Relati
                     let parent = get_node_text(type_node, content);
                     relationships.push(Relationship {
                         target: parent,
-                        rel_type: RelationshipType::Inheritance,
+                        rel_type: rel_type.clone(),
                         label: None,
+                        member_visibility: None,
+                        provenance: Some(provenance.to_string()),
+                        cardinality: None,
                     });
                 }
             }
@@ -217,32 +441,72 @@ fn extract_inheritance(node: Node, content: &str, relationships: &mut Vec<Relati
             let parent = get_node_text(child, content);
             relationships.push(Relationship {
                 target: parent,
-                rel_type: RelationshipType::Inheritance,
+                rel_type: rel_type.clone(),
                 label: None,
+                member_visibility: None,
+                provenance: Some(provenance.to_string()),
+                cardinality: None,
             });
         }
     }
 }
 
-fn resolve_java_types(node: Node, content: &str, types: &mut Vec<String>) {
+fn resolve_java_types(node: Node, content: &str, types: &mut Vec<String>, options: &ParseOptions, depth: usize) {
+    if type_depth_exceeded(node, depth, options) {
+        return;
+    }
     match node.kind() {
         "type_identifier" => {
             let name = get_node_text(node, content);
-            match name.as_str() {
-                "byte" | "short" | "int" | "long" | "float" | "double" | "char" | "boolean" | "void" |
-                "String" | "Object" | "List" | "ArrayList" | "Map" | "HashMap" | "Set" | "HashSet" | "Optional" => {},
-                _ => types.push(name),
+            if !is_builtin(&name, is_java_builtin, options) {
+                types.push(name);
             }
         }
         _ => {
+            // Only generic argument lists count as a level of nesting
+            let depth = if node.kind() == "type_arguments" { depth + 1 } else { depth };
             let mut cursor = node.walk();
             for child in node.children(&mut cursor) {
-                resolve_java_types(child, content, types);
+                resolve_java_types(child, content, types, options, depth);
             }
         }
     }
 }
 
+fn is_java_builtin(name: &str) -> bool {
+    matches!(
+        name,
+        "byte" | "short" | "int" | "long" | "float" | "double" | "char" | "boolean" | "void" |
+        "String" | "Object" | "List" | "ArrayList" | "Map" | "HashMap" | "Set" | "HashSet" | "Optional"
+    )
+}
+
+/// The type to read relationships from: the declared type, or for `var` the
+/// type of the `new T(...)` initializer. Diamond initializers (`new HashMap<>()`)
+/// leave the element types to the declared type.
+fn declared_or_inferred_type<'a>(type_node: Option<Node<'a>>, value: Option<Node<'a>>, content: &str) -> Option<Node<'a>> {
+    let type_node = type_node?;
+    if get_node_text(type_node, content) != "var" {
+        return Some(type_node);
+    }
+    value
+        .filter(|v| v.kind() == "object_creation_expression")
+        .and_then(|v| v.child_by_field_name("type"))
+}
+
+/// Collects the types of every `new T(...)` expression below `node`.
+fn collect_instantiated_types(node: Node, content: &str, types: &mut Vec<String>, options: &ParseOptions) {
+    if node.kind() == "object_creation_expression" {
+        if let Some(type_node) = node.child_by_field_name("type") {
+            resolve_java_types(type_node, content, types, options, 0);
+        }
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_instantiated_types(child, content, types, options);
+    }
+}
+
 fn get_node_text(node: Node, content: &str) -> String {
     node.utf8_text(content.as_bytes())
         .ok()
@@ -282,6 +546,23 @@ public class User {
         Ok(())
     }
 
+    #[test]
+    fn test_parse_java_class_location() -> Result<()> {
+        let content = "
+package app;
+
+public class User {
+    class Session {}
+}
+";
+        let classes = JavaParser.parse(content)?;
+        let user = classes.iter().find(|c| c.name == "User").unwrap();
+        assert_eq!((user.line, user.column), (4, 1));
+        let session = classes.iter().find(|c| c.name == "User.Session").unwrap();
+        assert_eq!((session.line, session.column), (5, 5));
+        Ok(())
+    }
+
     #[test]
     fn test_parse_nested_java() -> Result<()> {
         let content = "
@@ -347,6 +628,99 @@ public class House {
         Ok(())
     }
 
+    #[test]
+    fn test_parse_java_sealed_permits() -> Result<()> {
+        let content = "
+public sealed interface Shape permits Circle, Square {}
+
+final class Circle implements Shape {}
+";
+        let classes = JavaParser.parse(content)?;
+        let shape = classes.iter().find(|c| c.name == "Shape").unwrap();
+        assert_eq!(shape.stereotype.as_deref(), Some("sealed interface"));
+        assert!(shape.is_interface());
+
+        for subtype in ["Circle", "Square"] {
+            let class = classes.iter().find(|c| c.name == subtype).unwrap();
+            let edges = class.relationships.iter()
+                .filter(|r| r.target == "Shape" && r.rel_type.is_generalization())
+                .count();
+            assert_eq!(edges, 1, "{} should inherit Shape once", subtype);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_java_method_annotations() -> Result<()> {
+        let content = "
+public class Task implements Runnable {
+    @Override
+    public void run() {}
+
+    @java.lang.Deprecated
+    public void start() {}
+}
+";
+        let classes = JavaParser.parse(content)?;
+        let task = &classes[0];
+        let run = task.methods.iter().find(|m| m.name == "run").unwrap();
+        assert_eq!(run.annotations, vec!["Override"]);
+        let start = task.methods.iter().find(|m| m.name == "start").unwrap();
+        assert_eq!(start.annotations, vec!["Deprecated"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_java_compose_directive() -> Result<()> {
+        let content = "
+public class Car {
+    private Engine engine; // marco-polo: compose Engine
+    private Wheel wheel;
+}
+";
+        let classes = JavaParser.parse(content)?;
+        let car = &classes[0];
+        assert!(car.relationships.iter().any(|r| r.target == "Engine" && r.rel_type == RelationshipType::Composition));
+        assert!(car.relationships.iter().any(|r| r.target == "Wheel" && r.rel_type == RelationshipType::Aggregation));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_java_deep_deps() -> Result<()> {
+        let content = "
+public class Checkout {
+    public void pay() {
+        Processor p = new PaymentProcessor();
+        p.run(new ArrayList<String>());
+    }
+}
+";
+        let classes = JavaParser.parse(content)?;
+        assert!(!classes[0].relationships.iter().any(|r| r.target == "PaymentProcessor"));
+
+        let options = ParseOptions { deep_deps: true, ..ParseOptions::default() };
+        let classes = JavaParser.parse_with_options(content, &options)?;
+        let checkout = &classes[0];
+        assert!(checkout.relationships.iter().any(|r| r.target == "PaymentProcessor" && r.rel_type == RelationshipType::Dependency));
+        assert!(!checkout.relationships.iter().any(|r| r.target == "ArrayList"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_java_constant_field() -> Result<()> {
+        let content = "
+public class Limits {
+    public static final int MAX = 10;
+    private int count;
+}
+";
+        let classes = JavaParser.parse(content)?;
+        let limits = &classes[0];
+        assert!(limits.properties.iter().any(|p| p.name == "MAX" && p.is_const));
+        assert!(limits.properties.iter().any(|p| p.name == "count" && !p.is_const));
+        Ok(())
+    }
+
     #[test]
     fn test_parse_java_relationships() -> Result<()> {
         let content = "
@@ -361,10 +735,10 @@ public class Admin extends User implements Auth, Loggable {
         
         let rels = &admin.relationships;
         
-        // Inheritance
+        // Inheritance and realization
         assert!(rels.iter().any(|r| r.target == "User" && r.rel_type == RelationshipType::Inheritance));
-        assert!(rels.iter().any(|r| r.target == "Auth" && r.rel_type == RelationshipType::Inheritance));
-        assert!(rels.iter().any(|r| r.target == "Loggable" && r.rel_type == RelationshipType::Inheritance));
+        assert!(rels.iter().any(|r| r.target == "Auth" && r.rel_type == RelationshipType::Realization));
+        assert!(rels.iter().any(|r| r.target == "Loggable" && r.rel_type == RelationshipType::Realization));
         
         // Aggregation (Field + Constructor)
         assert!(rels.iter().any(|r| r.target == "Logger" && r.rel_type == RelationshipType::Aggregation));
@@ -374,4 +748,130 @@ public class Admin extends User implements Auth, Loggable {
         
         Ok(())
     }
+
+    #[test]
+    fn test_parse_java_record_components() -> Result<()> {
+        let content = "public record Order(Customer customer, int total) {}";
+        let classes = JavaParser.parse(content)?;
+        assert_eq!(classes.len(), 1);
+        let order = &classes[0];
+        assert_eq!(order.name, "Order");
+
+        let customer = order.properties.iter().find(|p| p.name == "customer").unwrap();
+        assert_eq!(customer.visibility, Visibility::Public);
+        assert!(customer.is_const);
+        assert!(order.properties.iter().any(|p| p.name == "total"));
+
+        assert!(order.relationships.iter().any(|r| r.target == "Customer"
+            && r.rel_type == RelationshipType::Aggregation
+            && r.label.as_deref() == Some("customer")));
+        assert!(!order.relationships.iter().any(|r| r.target == "int"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_java_abstract_methods() -> Result<()> {
+        let content = "
+@FunctionalInterface
+public interface Handler {
+    void handle(Event event);
+    default void log() {}
+    static Handler noop() { return e -> {}; }
+}
+";
+        let classes = JavaParser.parse(content)?;
+        let handler = &classes[0];
+        assert_eq!(handler.stereotype.as_deref(), Some("interface"));
+
+        let abstract_methods: Vec<_> = handler.methods.iter()
+            .filter(|m| m.is_abstract)
+            .map(|m| m.name.as_str())
+            .collect();
+        assert_eq!(abstract_methods, vec!["handle"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_deeply_nested_generics_are_bounded() -> Result<()> {
+        let depth = 500;
+        let nested = format!("{}Payload{}", "Box<".repeat(depth), ">".repeat(depth));
+        let content = format!("class Holder {{ private Map<Owner, {}> items; }}", nested);
+
+        let classes = JavaParser.parse(&content)?;
+        let rels = &classes[0].relationships;
+        assert!(rels.iter().any(|r| r.target == "Owner"));
+        assert!(rels.iter().any(|r| r.target == "Box"));
+        assert!(!rels.iter().any(|r| r.target == "Payload"));
+
+        // The limit counts generic levels, not syntax nodes
+        let options = ParseOptions { max_type_depth: 2, ..ParseOptions::default() };
+        let classes = JavaParser.parse_with_options("class Holder { private Outer<Middle<Inner<Deepest>>> items; }", &options)?;
+        let targets: Vec<_> = classes[0].relationships.iter().map(|r| r.target.as_str()).collect();
+        assert_eq!(targets, vec!["Outer", "Middle", "Inner"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_throws_dependencies() -> Result<()> {
+        let content = "
+public class Checkout {
+    public void pay() throws PaymentException, java.io.IOException {}
+}
+";
+        let classes = JavaParser.parse(content)?;
+        let rels = &classes[0].relationships;
+        assert!(rels.iter().any(|r| {
+            r.target == "PaymentException"
+                && r.rel_type == RelationshipType::Dependency
+                && r.label.as_deref() == Some("throws")
+        }));
+        assert!(rels.iter().any(|r| r.target == "IOException" && r.label.as_deref() == Some("throws")));
+        assert!(!rels.iter().any(|r| r.target == "java" || r.target == "io"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_diamond_and_var_initialized_fields() -> Result<()> {
+        let content = "
+public class Shop {
+    private Map<String, Order> orders = new HashMap<>();
+    private List<Item> items = new ArrayList<Item>();
+    private var repo = new UserRepository();
+}
+";
+        let classes = JavaParser.parse(content)?;
+        let mut compositions: Vec<_> = classes[0].relationships.iter()
+            .filter(|r| r.rel_type == RelationshipType::Composition)
+            .map(|r| (r.target.as_str(), r.label.as_deref().unwrap()))
+            .collect();
+        compositions.sort();
+        assert_eq!(compositions, vec![("Item", "items"), ("Order", "orders"), ("UserRepository", "repo")]);
+        assert!(classes[0].relationships.iter().all(|r| r.target != "var"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_optional_and_nullable_cardinality() -> Result<()> {
+        let content = "
+public class Customer {
+    private Optional<Account> account;
+    @Nullable private Address address;
+    private Profile profile;
+
+    public void notify(Optional<Channel> channel, Message message) {}
+}
+";
+        let classes = JavaParser.parse(content)?;
+        let edges: Vec<_> = classes[0].relationships.iter()
+            .map(|r| (r.target.as_str(), r.rel_type.clone(), r.cardinality.as_deref()))
+            .collect();
+        assert_eq!(edges, vec![
+            ("Account", RelationshipType::Aggregation, Some("0..1")),
+            ("Address", RelationshipType::Aggregation, Some("0..1")),
+            ("Profile", RelationshipType::Aggregation, None),
+            ("Channel", RelationshipType::Dependency, Some("0..1")),
+            ("Message", RelationshipType::Dependency, None),
+        ]);
+        Ok(())
+    }
 }
@@ -0,0 +1,400 @@
+use std::collections::HashMap;
+use tree_sitter::{Parser, Node};
+use crate::models::{ClassInfo, Relationship, RelationshipType, Visibility, MethodInfo, PropertyInfo};
+use anyhow::{Result, Context};
+use super::{parse_tree, LanguageParser, ParseOptions};
+
+/// Lua has no classes; this recognises the usual table-based idiom:
+///
+/// ```lua
+/// local Animal = {}
+/// Animal.__index = Animal
+/// function Animal.new(name) ... end
+/// function Animal:speak() ... end
+///
+/// local Dog = setmetatable({}, {__index = Animal})
+/// ```
+///
+/// A table assigned to a name at the top level of a file is a class once it has
+/// a method (`function Foo:m()`/`function Foo.m()`) or takes part in the
+/// metatable idiom (`Foo.__index = ...`, `setmetatable`). `setmetatable(Child,
+/// {__index = Parent})`, or `setmetatable(Child, Parent)`, is read as inheritance.
+pub struct LuaParser;
+
+/// A named table seen so far and whether it looks like a class.
+struct Table {
+    class: ClassInfo,
+    is_class: bool,
+}
+
+impl LanguageParser for LuaParser {
+    fn name(&self) -> &str {
+        "lua"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["lua"]
+    }
+
+    fn parse_with_options(&self, content: &str, options: &ParseOptions) -> Result<Vec<ClassInfo>> {
+        let mut parser = Parser::new();
+        let language = tree_sitter_lua::language();
+        parser.set_language(language)
+            .context("Error loading Lua grammar")?;
+
+        let tree = parse_tree(&mut parser, content, options)
+            .context("Failed to parse Lua content")?;
+
+        let root_node = tree.root_node();
+        let mut tables: Vec<Table> = Vec::new();
+        let mut index: HashMap<String, usize> = HashMap::new();
+
+        let mut cursor = root_node.walk();
+        for statement in root_node.named_children(&mut cursor) {
+            match statement.kind() {
+                "variable_declaration" | "assignment_statement" => {
+                    let assignment = if statement.kind() == "variable_declaration" {
+                        match named_child_of_kind(statement, "assignment_statement") {
+                            Some(a) => a,
+                            None => continue,
+                        }
+                    } else {
+                        statement
+                    };
+                    let Some((target, value)) = single_assignment(assignment) else {
+                        continue;
+                    };
+                    handle_assignment(target, value, content, &mut tables, &mut index);
+                }
+                "function_declaration" => {
+                    let Some(name_node) = statement.child_by_field_name("name") else {
+                        continue;
+                    };
+                    let (table_field, method_field) = match name_node.kind() {
+                        "method_index_expression" => ("table", "method"),
+                        "dot_index_expression" => ("table", "field"),
+                        // `local function helper()` and globals are not methods
+                        _ => continue,
+                    };
+                    let (Some(table_node), Some(method_node)) = (
+                        name_node.child_by_field_name(table_field),
+                        name_node.child_by_field_name(method_field),
+                    ) else {
+                        continue;
+                    };
+                    let Some(&i) = index.get(&get_node_text(table_node, content)) else {
+                        continue;
+                    };
+                    let table = &mut tables[i];
+                    table.is_class = true;
+                    add_method(&mut table.class, get_node_text(method_node, content));
+                    if let Some(body) = statement.child_by_field_name("body") {
+                        collect_self_fields(body, content, &mut table.class);
+                    }
+                }
+                "function_call" => {
+                    // `setmetatable(Child, {__index = Parent})`
+                    let Some((child, parent)) = setmetatable_args(statement, content) else {
+                        continue;
+                    };
+                    let Some(&i) = index.get(&get_node_text(child, content)) else {
+                        continue;
+                    };
+                    tables[i].is_class = true;
+                    if let Some(parent) = metatable_parent(parent, content) {
+                        add_inheritance(&mut tables[i].class, parent);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(tables.into_iter().filter(|t| t.is_class).map(|t| t.class).collect())
+    }
+}
+
+/// Records what a top-level `target = value` says about the tables seen so far.
+fn handle_assignment(target: Node, value: Node, content: &str, tables: &mut Vec<Table>, index: &mut HashMap<String, usize>) {
+    let name = get_node_text(target, content);
+
+    // `local Foo = {}` or `local Foo = setmetatable({}, {__index = Base})`
+    let (constructor, parent, uses_metatable) = match value.kind() {
+        "table_constructor" => (Some(value), None, false),
+        "function_call" => match setmetatable_args(value, content) {
+            Some((first, second)) if first.kind() == "table_constructor" => {
+                (Some(first), metatable_parent(second, content), true)
+            }
+            _ => (None, None, false),
+        },
+        _ => (None, None, false),
+    };
+    if let Some(constructor) = constructor {
+        let mut class = ClassInfo {
+            name: name.clone(),
+            methods: Vec::new(),
+            properties: Vec::new(),
+            relationships: Vec::new(),
+            stereotype: None,
+            language: Some("lua".to_string()),
+            line: target.start_position().row as u32 + 1,
+            column: target.start_position().column as u32 + 1,
+            out_of_line: false,
+            package: None,
+        };
+        let mut is_class = uses_metatable;
+        if let Some(parent) = parent {
+            add_inheritance(&mut class, parent);
+        }
+        // `{ count = 0, speak = function(self) end }`
+        let mut field_cursor = constructor.walk();
+        for field in constructor.named_children(&mut field_cursor) {
+            let (Some(field_name), Some(field_value)) = (field.child_by_field_name("name"), field.child_by_field_name("value")) else {
+                continue;
+            };
+            let field_name = get_node_text(field_name, content);
+            if field_value.kind() == "function_definition" {
+                is_class = true;
+                add_method(&mut class, field_name);
+            } else {
+                add_property(&mut class, field_name);
+            }
+        }
+        index.insert(name, tables.len());
+        tables.push(Table { class, is_class });
+        return;
+    }
+
+    // `Foo.__index = Foo`, `Foo.count = 0` or `Foo.new = function() ... end`
+    if target.kind() != "dot_index_expression" {
+        return;
+    }
+    let (Some(table_node), Some(field_node)) = (target.child_by_field_name("table"), target.child_by_field_name("field")) else {
+        return;
+    };
+    let Some(&i) = index.get(&get_node_text(table_node, content)) else {
+        return;
+    };
+    let table = &mut tables[i];
+    let field = get_node_text(field_node, content);
+    if field.starts_with("__") {
+        // Metamethods and `__index` belong to the idiom itself, not the model
+        table.is_class = true;
+    } else if value.kind() == "function_definition" {
+        table.is_class = true;
+        add_method(&mut table.class, field);
+        if let Some(body) = value.child_by_field_name("body") {
+            collect_self_fields(body, content, &mut table.class);
+        }
+    } else {
+        add_property(&mut table.class, field);
+    }
+}
+
+/// The target and value of a single `a = b` assignment; multiple assignment
+/// (`a, b = 1, 2`) is ignored.
+fn single_assignment(assignment: Node) -> Option<(Node, Node)> {
+    let variables = named_child_of_kind(assignment, "variable_list")?;
+    let values = named_child_of_kind(assignment, "expression_list")?;
+    if variables.named_child_count() != 1 || values.named_child_count() != 1 {
+        return None;
+    }
+    Some((variables.named_child(0)?, values.named_child(0)?))
+}
+
+/// The two arguments of a `setmetatable(a, b)` call.
+fn setmetatable_args<'a>(call: Node<'a>, content: &str) -> Option<(Node<'a>, Node<'a>)> {
+    let name = call.child_by_field_name("name")?;
+    if get_node_text(name, content) != "setmetatable" {
+        return None;
+    }
+    let arguments = call.child_by_field_name("arguments")?;
+    if arguments.named_child_count() != 2 {
+        return None;
+    }
+    Some((arguments.named_child(0)?, arguments.named_child(1)?))
+}
+
+/// The parent named by a metatable: `{__index = Parent}` or `Parent` itself.
+fn metatable_parent(metatable: Node, content: &str) -> Option<String> {
+    match metatable.kind() {
+        "identifier" | "dot_index_expression" => Some(get_node_text(metatable, content)),
+        "table_constructor" => {
+            let mut cursor = metatable.walk();
+            let parent = metatable.named_children(&mut cursor).find_map(|field| {
+                let name = field.child_by_field_name("name")?;
+                let value = field.child_by_field_name("value")?;
+                let is_index = get_node_text(name, content) == "__index";
+                let is_name = matches!(value.kind(), "identifier" | "dot_index_expression");
+                (is_index && is_name).then(|| get_node_text(value, content))
+            });
+            parent
+        }
+        _ => None,
+    }
+}
+
+/// Adds a property for every `self.x = ...` inside a method body.
+fn collect_self_fields(node: Node, content: &str, class: &mut ClassInfo) {
+    if node.kind() == "assignment_statement" {
+        if let Some(variables) = named_child_of_kind(node, "variable_list") {
+            let mut cursor = variables.walk();
+            for target in variables.named_children(&mut cursor) {
+                if target.kind() != "dot_index_expression" {
+                    continue;
+                }
+                let (Some(table), Some(field)) = (target.child_by_field_name("table"), target.child_by_field_name("field")) else {
+                    continue;
+                };
+                if get_node_text(table, content) == "self" {
+                    add_property(class, get_node_text(field, content));
+                }
+            }
+        }
+    }
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        // Nested functions have their own `self`
+        if child.kind() != "function_definition" && child.kind() != "function_declaration" {
+            collect_self_fields(child, content, class);
+        }
+    }
+}
+
+fn add_method(class: &mut ClassInfo, name: String) {
+    if class.methods.iter().any(|m| m.name == name) {
+        return;
+    }
+    class.methods.push(MethodInfo {
+        visibility: visibility(&name),
+        name,
+        is_abstract: false,
+        annotations: Vec::new(),
+    });
+}
+
+fn add_property(class: &mut ClassInfo, name: String) {
+    if class.properties.iter().any(|p| p.name == name) {
+        return;
+    }
+    class.properties.push(PropertyInfo {
+        visibility: visibility(&name),
+        name,
+        is_const: false,
+        is_static: false,
+        qualifiers: Vec::new(),
+    });
+}
+
+fn add_inheritance(class: &mut ClassInfo, parent: String) {
+    if parent == class.name || class.relationships.iter().any(|r| r.rel_type == RelationshipType::Inheritance && r.target == parent) {
+        return;
+    }
+    class.relationships.push(Relationship {
+        target: parent,
+        rel_type: RelationshipType::Inheritance,
+        label: None,
+        member_visibility: None,
+        provenance: Some("setmetatable".to_string()),
+        cardinality: None,
+    });
+}
+
+/// Lua has no access control; a leading underscore is the usual "private" marker.
+fn visibility(name: &str) -> Visibility {
+    if name.starts_with('_') {
+        Visibility::Private
+    } else {
+        Visibility::Public
+    }
+}
+
+fn named_child_of_kind<'a>(node: Node<'a>, kind: &str) -> Option<Node<'a>> {
+    let mut cursor = node.walk();
+    let child = node.named_children(&mut cursor).find(|c| c.kind() == kind);
+    child
+}
+
+fn get_node_text(node: Node, content: &str) -> String {
+    node.utf8_text(content.as_bytes())
+        .map(str::to_string)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str) -> Result<Vec<ClassInfo>> {
+        LuaParser.parse(content)
+    }
+
+    fn find<'a>(classes: &'a [ClassInfo], name: &str) -> &'a ClassInfo {
+        classes.iter().find(|c| c.name == name).unwrap()
+    }
+
+    #[test]
+    fn test_method_definition_idiom() -> Result<()> {
+        let content = "
+local Animal = {}
+Animal.__index = Animal
+
+function Animal.new(name)
+  local self = setmetatable({}, Animal)
+  self.name = name
+  return self
+end
+
+function Animal:speak()
+  return self.sound
+end
+
+function Animal:_breathe() end
+
+local function helper() end
+
+local config = { debug = true }
+";
+        let classes = parse(content)?;
+        assert_eq!(classes.len(), 1, "plain data tables are not classes");
+
+        let animal = find(&classes, "Animal");
+        let methods: Vec<_> = animal.methods.iter().map(|m| (m.name.as_str(), m.visibility)).collect();
+        assert_eq!(methods, vec![("new", Visibility::Public), ("speak", Visibility::Public), ("_breathe", Visibility::Private)]);
+        let properties: Vec<_> = animal.properties.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(properties, vec!["name"]);
+        assert_eq!(animal.line, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_metatable_inheritance() -> Result<()> {
+        let content = "
+local Animal = {}
+Animal.__index = Animal
+function Animal:speak() end
+
+local Dog = setmetatable({}, {__index = Animal})
+Dog.__index = Dog
+function Dog:bark() end
+
+local Cat = {}
+setmetatable(Cat, {__index = Animal})
+
+local Puppy = {}
+setmetatable(Puppy, Dog)
+";
+        let classes = parse(content)?;
+        let parents = |name: &str| -> Vec<String> {
+            find(&classes, name).relationships.iter()
+                .filter(|r| r.rel_type == RelationshipType::Inheritance)
+                .map(|r| r.target.clone())
+                .collect()
+        };
+        assert!(parents("Animal").is_empty());
+        assert_eq!(parents("Dog"), vec!["Animal"]);
+        assert_eq!(parents("Cat"), vec!["Animal"]);
+        assert_eq!(parents("Puppy"), vec!["Dog"]);
+        assert_eq!(find(&classes, "Dog").methods[0].name, "bark");
+        Ok(())
+    }
+}
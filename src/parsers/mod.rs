@@ -1,26 +1,333 @@
-use crate::models::ClassInfo;
-use anyhow::Result;
+use crate::models::{ClassInfo, Relationship, RelationshipType};
+use anyhow::{bail, Result};
+use std::fmt::{self, Write};
+use std::time::Duration;
+use tree_sitter::{Node, Parser, Tree};
 
 pub mod python;
 pub mod java;
 pub mod cpp;
+pub mod c;
 pub mod ruby;
+pub mod lua;
+pub mod groovy;
+
+/// Default nesting limit for recursive type resolution.
+pub const DEFAULT_MAX_TYPE_DEPTH: usize = 64;
+
+/// Parser behaviour toggles shared by every language.
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    /// Read YARD `@param`/`@return` tags in Ruby method comments
+    pub ruby_yard: bool,
+    /// How deep type resolution may descend into nested generics/templates
+    pub max_type_depth: usize,
+    /// Walk Java method bodies for `new T()` and record dependencies on `T`
+    pub deep_deps: bool,
+    /// Type names filtered out in addition to each language's builtins
+    pub extra_builtins: Vec<String>,
+    /// Type names kept even when a language lists them as builtins
+    pub builtin_unfilter: Vec<String>,
+    /// Keep C++ classes defined inside function bodies
+    pub include_local_classes: bool,
+    /// Fold C++ `getX()`/`setX()`/`isX()` methods into the private field they wrap
+    pub collapse_accessor_pairs: bool,
+    /// Give up on a file whose parse takes longer than this
+    pub timeout: Option<Duration>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            ruby_yard: false,
+            max_type_depth: DEFAULT_MAX_TYPE_DEPTH,
+            deep_deps: false,
+            extra_builtins: Vec::new(),
+            builtin_unfilter: Vec::new(),
+            include_local_classes: false,
+            collapse_accessor_pairs: false,
+            timeout: None,
+        }
+    }
+}
 
 pub trait LanguageParser {
+    /// Human readable language name (e.g., "python")
+    fn name(&self) -> &str;
+
     /// The file extensions this parser handles (e.g., ["py", "py3"])
-    #[allow(dead_code)]
     fn extensions(&self) -> &[&str];
 
     /// The core parsing logic
-    fn parse(&self, content: &str) -> Result<Vec<ClassInfo>>;
+    fn parse_with_options(&self, content: &str, options: &ParseOptions) -> Result<Vec<ClassInfo>>;
+
+    /// Parses with the default options
+    #[allow(dead_code)]
+    fn parse(&self, content: &str) -> Result<Vec<ClassInfo>> {
+        self.parse_with_options(content, &ParseOptions::default())
+    }
+}
+
+/// A parse abandoned after [`ParseOptions::timeout`]; the file is skipped.
+#[derive(Debug)]
+pub struct ParseTimeout(pub Duration);
+
+impl fmt::Display for ParseTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "parsing took longer than {} ms", self.0.as_millis())
+    }
+}
+
+impl std::error::Error for ParseTimeout {}
+
+/// Runs tree-sitter on `content`, bounded by the configured timeout. Running out
+/// of time fails with [`ParseTimeout`].
+pub fn parse_tree(parser: &mut Parser, content: &str, options: &ParseOptions) -> Result<Tree> {
+    parser.set_timeout_micros(options.timeout.map_or(0, |t| t.as_micros() as u64));
+    match (parser.parse(content, None), options.timeout) {
+        (Some(tree), _) => Ok(tree),
+        (None, Some(timeout)) => Err(ParseTimeout(timeout).into()),
+        (None, None) => bail!("tree-sitter returned no syntax tree"),
+    }
+}
+
+/// Whether relationships to `name` are filtered out: the language's own builtin
+/// list plus `--extra-builtins`, minus anything in `--builtin-unfilter`.
+pub fn is_builtin(name: &str, language_builtin: fn(&str) -> bool, options: &ParseOptions) -> bool {
+    if options.builtin_unfilter.iter().any(|b| b == name) {
+        return false;
+    }
+    language_builtin(name) || options.extra_builtins.iter().any(|b| b == name)
+}
+
+/// Guards recursive type resolution against pathologically nested types.
+/// `depth` counts the generic/template argument lists entered, not syntax
+/// nodes; returns true (after reporting it) once it exceeds the configured limit.
+pub fn type_depth_exceeded(node: Node, depth: usize, options: &ParseOptions) -> bool {
+    if depth <= options.max_type_depth {
+        return false;
+    }
+    log::debug!(
+        "type nesting at line {} exceeds {} levels; skipping the rest of it",
+        node.start_position().row + 1,
+        options.max_type_depth
+    );
+    true
+}
+
+/// Every supported parser, in the order they are listed to users.
+pub fn all_parsers() -> Vec<Box<dyn LanguageParser>> {
+    vec![
+        Box::new(python::PythonParser),
+        Box::new(java::JavaParser),
+        Box::new(cpp::CppParser),
+        Box::new(c::CParser),
+        Box::new(ruby::RubyParser),
+        Box::new(lua::LuaParser),
+        Box::new(groovy::GroovyParser),
+    ]
 }
 
 pub fn get_parser(extension: &str) -> Option<Box<dyn LanguageParser>> {
-    match extension {
-        "py" => Some(Box::new(python::PythonParser)),
-        "java" => Some(Box::new(java::JavaParser)),
-        "cpp" | "cc" | "cxx" | "h" | "hpp" => Some(Box::new(cpp::CppParser)),
-        "rb" => Some(Box::new(ruby::RubyParser)),
-        _ => None,
+    all_parsers()
+        .into_iter()
+        .find(|parser| parser.extensions().contains(&extension))
+}
+
+/// Looks a parser up by its language name (e.g. "c").
+pub fn get_parser_by_name(name: &str) -> Option<Box<dyn LanguageParser>> {
+    all_parsers()
+        .into_iter()
+        .find(|parser| parser.name() == name)
+}
+
+/// Marker that introduces a relationship override in a source comment,
+/// e.g. `// marco-polo: compose Engine` or `# marco-polo: aggregate Wheel`.
+const DIRECTIVE_MARKER: &str = "marco-polo:";
+
+/// Applies `marco-polo: <compose|aggregate|depend> <Target>` comment directives
+/// to the inferred relationships. Only comments of the class itself count, not
+/// those of classes nested in it (`nested_class_kinds`). A directive changes the
+/// edges to `Target` from the member it annotates (the declaration it trails, or
+/// the one below it) and from constructor parameters, which carry no member name.
+/// Inheritance and realization edges are never overridden.
+pub fn apply_relationship_directives(class_node: Node, content: &str, nested_class_kinds: &[&str], relationships: &mut [Relationship]) {
+    let mut comments = Vec::new();
+    collect_comments(class_node, nested_class_kinds, &mut comments);
+    for comment in comments {
+        let text = &content[comment.byte_range()];
+        let Some(pos) = text.find(DIRECTIVE_MARKER) else {
+            continue;
+        };
+        let mut words = text[pos + DIRECTIVE_MARKER.len()..].split_whitespace();
+        let (Some(kind), Some(target)) = (words.next(), words.next()) else {
+            continue;
+        };
+        let rel_type = match kind {
+            "compose" => RelationshipType::Composition,
+            "aggregate" => RelationshipType::Aggregation,
+            "depend" => RelationshipType::Dependency,
+            _ => {
+                log::warn!("unknown marco-polo directive '{}'", kind);
+                continue;
+            }
+        };
+        let member = annotated_member(comment).map_or("", |m| &content[m.byte_range()]);
+        let member_names: Vec<&str> = member.split(|c: char| !c.is_alphanumeric() && c != '_').collect();
+        for rel in relationships.iter_mut() {
+            let from_member = match &rel.label {
+                Some(label) => member_names.contains(&label.as_str()),
+                None => matches!(rel.rel_type, RelationshipType::Aggregation | RelationshipType::Composition),
+            };
+            if rel.target == target && from_member && !rel.rel_type.is_generalization() {
+                rel.rel_type = rel_type.clone();
+                let directive = format!("marco-polo: {} directive", kind);
+                rel.provenance = Some(match rel.provenance.take() {
+                    Some(source) => format!("{}, overridden by {}", source, directive),
+                    None => directive,
+                });
+            }
+        }
+    }
+}
+
+/// Comment nodes inside `node`, leaving out those of nested classes.
+fn collect_comments<'a>(node: Node<'a>, nested_class_kinds: &[&str], comments: &mut Vec<Node<'a>>) {
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        if child.kind().ends_with("comment") {
+            comments.push(child);
+        } else if !nested_class_kinds.contains(&child.kind()) {
+            collect_comments(child, nested_class_kinds, comments);
+        }
+    }
+}
+
+/// The declaration a comment annotates: the one it trails on the same line,
+/// otherwise the next one below it.
+fn annotated_member(comment: Node) -> Option<Node> {
+    let row = comment.start_position().row;
+    if let Some(previous) = comment.prev_named_sibling().filter(|p| p.end_position().row == row) {
+        return Some(previous);
+    }
+    let mut next = comment.next_named_sibling();
+    while let Some(n) = next {
+        if !n.kind().ends_with("comment") {
+            return Some(n);
+        }
+        next = n.next_named_sibling();
+    }
+    None
+}
+
+/// Lists each supported language with its extensions, marking the extensions
+/// scanned by default.
+pub fn languages_report(default_extensions: &[&str]) -> String {
+    let mut report = String::new();
+    for parser in all_parsers() {
+        let extensions: Vec<String> = parser.extensions().iter()
+            .map(|ext| {
+                if default_extensions.contains(ext) {
+                    format!("{} (default)", ext)
+                } else {
+                    ext.to_string()
+                }
+            })
+            .collect();
+        writeln!(&mut report, "{:<8} {}", parser.name(), extensions.join(", ")).unwrap();
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_parser_by_extension() {
+        assert_eq!(get_parser("py").unwrap().name(), "python");
+        assert_eq!(get_parser("pyi").unwrap().name(), "python");
+        assert_eq!(get_parser("hpp").unwrap().name(), "cpp");
+        assert!(get_parser("txt").is_none());
+        assert_eq!(get_parser("c").unwrap().name(), "c");
+        assert_eq!(get_parser_by_name("c").unwrap().name(), "c");
+    }
+
+    #[test]
+    fn test_apply_relationship_directives() -> Result<()> {
+        let classes = cpp::CppParser.parse("
+class Car : public Vehicle {
+public:
+    // marco-polo: depend Vehicle
+    Vehicle* towing;
+    Engine* engine; // marco-polo: compose Engine
+    Engine* spare;
+    void repair(Engine* part);
+    const char* help = \"marco-polo: compose Spare\";
+    Spare* backup;
+    class Garage {
+        Backup* backup_engine; // marco-polo: compose Backup
+    };
+    Backup* backup_engine;
+};
+")?;
+        let car = classes.iter().find(|c| c.name == "Car").unwrap();
+        let edge = |target: &str, label: Option<&str>| {
+            car.relationships.iter()
+                .find(|r| r.target == target && r.label.as_deref() == label)
+                .map(|r| r.rel_type.clone())
+        };
+        assert_eq!(edge("Engine", Some("engine")), Some(RelationshipType::Composition));
+        assert_eq!(edge("Engine", Some("spare")), Some(RelationshipType::Aggregation));
+        assert_eq!(edge("Engine", None), Some(RelationshipType::Dependency));
+        assert_eq!(edge("Vehicle", None), Some(RelationshipType::Inheritance));
+        assert_eq!(edge("Vehicle", Some("towing")), Some(RelationshipType::Dependency));
+        assert_eq!(edge("Spare", Some("backup")), Some(RelationshipType::Aggregation));
+        assert_eq!(edge("Backup", Some("backup_engine")), Some(RelationshipType::Aggregation));
+        Ok(())
+    }
+
+    #[test]
+    fn test_languages_report() {
+        let report = languages_report(&["py", "java"]);
+        assert!(report.contains("python"));
+        assert!(report.contains("py (default)"));
+        assert!(report.contains("ruby"));
+        assert!(report.contains("rb"));
+        assert!(!report.contains("rb (default)"));
+    }
+
+    #[test]
+    fn test_builtin_overrides() -> Result<()> {
+        let content = "
+public class Greeter {
+    public void greet(Foo foo, String name, Bar bar) {}
+}
+";
+        let parser = get_parser("java").unwrap();
+        let targets = |options: &ParseOptions| -> Result<Vec<String>> {
+            let classes = parser.parse_with_options(content, options)?;
+            Ok(classes[0].relationships.iter().map(|r| r.target.clone()).collect())
+        };
+
+        assert_eq!(targets(&ParseOptions::default())?, vec!["Foo", "Bar"]);
+
+        let options = ParseOptions {
+            extra_builtins: vec!["Foo".to_string()],
+            builtin_unfilter: vec!["String".to_string()],
+            ..ParseOptions::default()
+        };
+        assert_eq!(targets(&options)?, vec!["String", "Bar"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_timeout() {
+        let content: String = (0..100_000).map(|i| format!("class C{}:\n    pass\n", i)).collect();
+        let options = ParseOptions { timeout: Some(Duration::from_micros(1)), ..ParseOptions::default() };
+        let err = python::PythonParser.parse_with_options(&content, &options).unwrap_err();
+        assert!(err.downcast_ref::<ParseTimeout>().is_some());
+
+        assert!(python::PythonParser.parse_with_options("class A:\n    pass\n", &ParseOptions::default()).is_ok());
     }
 }
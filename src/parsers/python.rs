@@ -1,30 +1,35 @@
+use std::collections::{HashMap, HashSet};
 use std::sync::OnceLock;
 use tree_sitter::{Parser, Query, QueryCursor, Node};
 use crate::models::{ClassInfo, Relationship, RelationshipType, Visibility, MethodInfo, PropertyInfo};
 use anyhow::{Result, Context};
-use std::collections::HashSet;
-use super::LanguageParser;
+use super::{apply_relationship_directives, is_builtin, parse_tree, type_depth_exceeded, LanguageParser, ParseOptions};
 
 const CLASS_QUERY_STR: &str = "(class_definition) @class";
 const PROP_QUERY_STR: &str = "
     (assignment left: (attribute object: (identifier) @obj attribute: (identifier) @attr))
     (assignment left: (pattern_list (attribute object: (identifier) @obj attribute: (identifier) @attr)))
+    (assignment left: (tuple_pattern (attribute object: (identifier) @obj attribute: (identifier) @attr)))
 ";
 
 pub struct PythonParser;
 
 impl LanguageParser for PythonParser {
+    fn name(&self) -> &str {
+        "python"
+    }
+
     fn extensions(&self) -> &[&str] {
-        &["py"]
+        &["py", "pyi"]
     }
 
-    fn parse(&self, content: &str) -> Result<Vec<ClassInfo>> {
+    fn parse_with_options(&self, content: &str, options: &ParseOptions) -> Result<Vec<ClassInfo>> {
         let mut parser = Parser::new();
         let language = tree_sitter_python::language();
         parser.set_language(language)
             .context("Error loading Python grammar")?;
 
-        let tree = parser.parse(content, None)
+        let tree = parse_tree(&mut parser, content, options)
             .context("Failed to parse Python content")?;
 
         let root_node = tree.root_node();
@@ -44,6 +49,19 @@ impl LanguageParser for PythonParser {
                 .expect("Static property query is invalid")
         });
 
+        let imports = Imports::collect(root_node, content);
+        let aliases = type_aliases(root_node, content, &imports, options);
+        let type_vars = type_vars(root_node, content);
+
+        // Protocols and ABCs declared in this file
+        let mut interface_cursor = QueryCursor::new();
+        let interfaces: HashSet<String> = interface_cursor.matches(query, root_node, content.as_bytes())
+            .map(|m| m.captures[0].node)
+            .filter(|class_node| is_interface(*class_node, content, &type_vars))
+            .filter_map(|class_node| class_node.child_by_field_name("name"))
+            .map(|name| get_node_text(name, content))
+            .collect();
+
         let mut query_cursor = QueryCursor::new();
         let matches = query_cursor.matches(query, root_node, content.as_bytes());
 
@@ -65,32 +83,97 @@ impl LanguageParser for PythonParser {
             let full_name = name_parts.join(".");
 
             // Extract Parents (Superclasses)
-            let mut parents = Vec::new();
-            if let Some(superclasses_node) = class_node.child_by_field_name("superclasses") {
-                let mut cursor = superclasses_node.walk();
-                for child in superclasses_node.children(&mut cursor) {
-                    if child.kind() == "identifier" || child.kind() == "attribute" || child.kind() == "subscript" {
-                         parents.push(get_node_text(child, content));
-                    }
-                }
-            }
+            let parents = class_bases(class_node, content, &type_vars);
 
-            let mut methods = Vec::new();
-            let mut properties = Vec::new();
+            let mut methods: Vec<MethodInfo> = Vec::new();
+            let mut properties: Vec<PropertyInfo> = Vec::new();
             let mut relationships = Vec::new();
 
-            // 1. Relationships from inheritance
+            // 1. Relationships from inheritance; a concrete class subclassing a Protocol or ABC realizes it
+            let is_concrete = !is_interface(class_node, content, &type_vars);
             for parent in &parents {
+                let short_name = parent.rsplit('.').next().unwrap_or(parent);
+                let rel_type = if is_concrete && interfaces.contains(short_name) {
+                    RelationshipType::Realization
+                } else {
+                    RelationshipType::Inheritance
+                };
                 relationships.push(Relationship {
                     target: parent.clone(),
-                    rel_type: RelationshipType::Inheritance,
+                    rel_type,
                     label: None,
+                    member_visibility: None,
+                    provenance: Some("base clause".to_string()),
+                    cardinality: None,
                 });
             }
 
+            let is_enum = parents.iter().any(|p| is_enum_base(p));
+
             if let Some(body_node) = class_node.child_by_field_name("body") {
                 let mut cursor = body_node.walk();
                 for child in body_node.children(&mut cursor) {
+                    // `RED = auto()` or `RED = 1`; the value is never a type
+                    if is_enum {
+                        if let Some(member) = enum_member(child, content) {
+                            if !properties.iter().any(|p| p.name == member) {
+                                properties.push(PropertyInfo {
+                                    name: member,
+                                    visibility: Visibility::Public,
+                                    is_const: false,
+                                    is_static: false,
+                                    qualifiers: Vec::new(),
+                                });
+                            }
+                            continue;
+                        }
+                    }
+
+                    // `__slots__ = ("x", "y")` declares the instance attributes
+                    if let Some(slots) = slot_names(child, content) {
+                        for slot in slots {
+                            if !properties.iter().any(|p| p.name == slot) {
+                                properties.push(PropertyInfo {
+                                    visibility: get_python_visibility(&slot),
+                                    name: slot,
+                                    is_const: false,
+                                    is_static: false,
+                                    qualifiers: Vec::new(),
+                                });
+                            }
+                        }
+                        continue;
+                    }
+
+                    // Class-scope annotations (`name: str`), as declared in stubs
+                    if let Some((attr_name, type_node)) = annotated_class_attribute(child, content) {
+                        let (is_static, inner_type) = unwrap_class_var(type_node, content);
+                        if !properties.iter().any(|p| p.name == attr_name) {
+                            properties.push(PropertyInfo {
+                                name: attr_name.clone(),
+                                visibility: get_python_visibility(&attr_name),
+                                is_const: false,
+                                is_static,
+                                qualifiers: Vec::new(),
+                            });
+                        }
+                        let mut resolved = Vec::new();
+                        if let Some(inner_type) = inner_type {
+                            resolve_types(inner_type, content, &mut resolved, options, 0);
+                        }
+                        for t in resolved {
+                            relationships.push(Relationship {
+                                target: t,
+                                rel_type: RelationshipType::Aggregation,
+                                label: Some(attr_name.clone()),
+                                member_visibility: Some(get_python_visibility(&attr_name)),
+                                provenance: Some(format!("field {}: {}", attr_name, get_node_text(type_node, content))),
+                                cardinality: None,
+                            });
+                        }
+                        continue;
+                    }
+
                     let func_node = match child.kind() {
                         "function_definition" | "async_function_definition" => Some(child),
                         "decorated_definition" => {
@@ -109,10 +192,11 @@ impl LanguageParser for PythonParser {
                             if let Some(params_node) = fn_node.child_by_field_name("parameters") {
                                 let mut p_cursor = params_node.walk();
                                 for param in params_node.children(&mut p_cursor) {
-                                    if param.kind() == "typed_parameter" {
+                                    // `x: Foo` and `x: Foo = None`; bare names and `*`/`/` separators carry no type
+                                    if param.kind() == "typed_parameter" || param.kind() == "typed_default_parameter" {
                                         if let Some(type_node) = param.child_by_field_name("type") {
                                             let mut resolved = Vec::new();
-                                            resolve_types(type_node, content, &mut resolved);
+                                            resolve_types(type_node, content, &mut resolved, options, 0);
                                             for t in resolved {
                                                 let rel_type = if method_name == "__init__" {
                                                     RelationshipType::Aggregation
@@ -123,6 +207,9 @@ impl LanguageParser for PythonParser {
                                                     target: t,
                                                     rel_type,
                                                     label: None,
+                                                    member_visibility: Some(visibility),
+                                                    provenance: Some(format!("param in method {}", method_name)),
+                                                    cardinality: None,
                                                 });
                                             }
                                         }
@@ -133,12 +220,15 @@ impl LanguageParser for PythonParser {
                             // Return type (for Dependency)
                             if let Some(ret_type_node) = fn_node.child_by_field_name("return_type") {
                                 let mut resolved = Vec::new();
-                                resolve_types(ret_type_node, content, &mut resolved);
+                                resolve_types(ret_type_node, content, &mut resolved, options, 0);
                                 for t in resolved {
                                     relationships.push(Relationship {
                                         target: t,
                                         rel_type: RelationshipType::Dependency,
                                         label: None,
+                                        member_visibility: Some(visibility),
+                                        provenance: Some(format!("return type of method {}", method_name)),
+                                        cardinality: None,
                                     });
                                 }
                             }
@@ -156,11 +246,17 @@ impl LanguageParser for PythonParser {
                                     let attr_name = get_node_text(attr_node, content);
                                     
                                     if obj_name == "self" {
-                                        let prop_visibility = get_python_visibility(&attr_name);
-                                        properties.push(PropertyInfo {
-                                            name: attr_name.clone(),
-                                            visibility: prop_visibility,
-                                        });
+                                        // Re-assigning an attribute must not list it twice
+                                        if !properties.iter().any(|p| p.name == attr_name) {
+                                            let prop_visibility = get_python_visibility(&attr_name);
+                                            properties.push(PropertyInfo {
+                                                name: attr_name.clone(),
+                                                visibility: prop_visibility,
+                                                is_const: false,
+                                                is_static: false,
+                                                qualifiers: Vec::new(),
+                                            });
+                                        }
 
                                         // Try to find type hint for this property
                                         let mut parent = obj_node.parent();
@@ -168,12 +264,15 @@ impl LanguageParser for PythonParser {
                                             if p.kind() == "assignment" {
                                                 if let Some(type_node) = p.child_by_field_name("type") {
                                                     let mut resolved = Vec::new();
-                                                    resolve_types(type_node, content, &mut resolved);
+                                                    resolve_types(type_node, content, &mut resolved, options, 0);
                                                     for t in resolved {
                                                         relationships.push(Relationship {
                                                             target: t,
                                                             rel_type: RelationshipType::Aggregation,
                                                             label: Some(attr_name.clone()),
+                                                            member_visibility: Some(get_python_visibility(&attr_name)),
+                                                            provenance: Some(format!("field {}: {}", attr_name, get_node_text(type_node, content))),
+                                                            cardinality: None,
                                                         });
                                                     }
                                                 }
@@ -189,21 +288,65 @@ impl LanguageParser for PythonParser {
                             // but for class diagram we might want to show them if they aren't __init__
                             // Following the rule: only show if not starting with _ (unless requested)
                             if method_name != "__init__" {
-                                methods.push(MethodInfo {
-                                    name: method_name,
-                                    visibility,
+                                let is_overload = decorators(fn_node).into_iter()
+                                    .any(|d| decorator_name(d, content) == "overload");
+                                let overloaded = methods.iter().position(|m| {
+                                    m.name == method_name && m.annotations.iter().any(|a| a == "overload")
                                 });
+                                match overloaded {
+                                    // Further `@overload` stubs collapse into the first entry
+                                    Some(_) if is_overload => {}
+                                    // The implementation after the stubs is the canonical entry
+                                    Some(index) => {
+                                        methods[index] = MethodInfo {
+                                            name: method_name,
+                                            visibility,
+                                            is_abstract: false,
+                                            annotations: vec!["overload".to_string()],
+                                        };
+                                    }
+                                    None => methods.push(MethodInfo {
+                                        name: method_name,
+                                        visibility,
+                                        is_abstract: false,
+                                        annotations: if is_overload { vec!["overload".to_string()] } else { Vec::new() },
+                                    }),
+                                }
                             }
                         }
                     }
                 }
             }
 
+            // `models.User` and `from models import User as Account` both name the class `User`;
+            // a type alias stands for the types it names (none for an alias of a primitive)
+            let mut relationships: Vec<Relationship> = relationships.into_iter()
+                .flat_map(|mut rel| {
+                    rel.target = imports.resolve(&rel.target);
+                    match aliases.get(&rel.target) {
+                        Some(targets) => targets.iter()
+                            .map(|target| Relationship { target: target.clone(), ..rel.clone() })
+                            .collect(),
+                        None => vec![rel],
+                    }
+                })
+                .collect();
+            relationships.retain(|r| r.rel_type.is_generalization() || !is_builtin(&r.target, is_python_builtin, options));
+
+            apply_relationship_directives(class_node, content, &["class_definition"], &mut relationships);
+
             classes.push(ClassInfo {
                 name: full_name,
                 methods,
                 properties,
                 relationships,
+                stereotype: dataclass_stereotype(class_node, content)
+                    .or_else(|| is_enum.then(|| "enumeration".to_string())),
+                language: Some(self.name().to_string()),
+                line: class_node.start_position().row as u32 + 1,
+                column: class_node.start_position().column as u32 + 1,
+                out_of_line: false,
+                package: None,
             });
         }
 
@@ -211,6 +354,191 @@ impl LanguageParser for PythonParser {
     }
 }
 
+/// Returns the decorator expressions applied to a definition, e.g. the
+/// `dataclass(frozen=True)` call of `@dataclass(frozen=True)`.
+fn decorators<'a>(definition: Node<'a>) -> Vec<Node<'a>> {
+    let Some(parent) = definition.parent().filter(|p| p.kind() == "decorated_definition") else {
+        return Vec::new();
+    };
+    let mut cursor = parent.walk();
+    let decorators = parent.children(&mut cursor)
+        .filter(|child| child.kind() == "decorator")
+        .filter_map(|decorator| decorator.named_child(0))
+        .collect();
+    decorators
+}
+
+/// The name a decorator refers to: `dataclass` for `@dataclasses.dataclass(...)`.
+fn decorator_name(decorator: Node, content: &str) -> String {
+    let target = match decorator.kind() {
+        "call" => decorator.child_by_field_name("function").unwrap_or(decorator),
+        _ => decorator,
+    };
+    let target = match target.kind() {
+        "attribute" => target.child_by_field_name("attribute").unwrap_or(target),
+        _ => target,
+    };
+    get_node_text(target, content)
+}
+
+/// The source text of a keyword argument passed to a decorator call.
+fn decorator_argument(decorator: Node, content: &str, key: &str) -> Option<String> {
+    let arguments = decorator.child_by_field_name("arguments")?;
+    let mut cursor = arguments.walk();
+    let value = arguments.children(&mut cursor)
+        .filter(|arg| arg.kind() == "keyword_argument")
+        .find(|arg| arg.child_by_field_name("name").map(|n| get_node_text(n, content)).as_deref() == Some(key))
+        .and_then(|arg| arg.child_by_field_name("value"))
+        .map(|v| get_node_text(v, content));
+    value
+}
+
+/// `<<frozen>>` for `@dataclass(frozen=True)` value objects, `<<dataclass>>` otherwise.
+fn dataclass_stereotype(class_node: Node, content: &str) -> Option<String> {
+    let dataclass = decorators(class_node).into_iter()
+        .find(|d| decorator_name(*d, content) == "dataclass")?;
+    if decorator_argument(dataclass, content, "frozen").as_deref() == Some("True") {
+        Some("frozen".to_string())
+    } else {
+        Some("dataclass".to_string())
+    }
+}
+
+/// Attribute names listed in a `__slots__` assignment (string, tuple, list or set of strings).
+/// Dunder slots such as `__weakref__` are skipped.
+fn slot_names(node: Node, content: &str) -> Option<Vec<String>> {
+    if node.kind() != "expression_statement" {
+        return None;
+    }
+    let assignment = node.named_child(0).filter(|n| n.kind() == "assignment")?;
+    let left = assignment.child_by_field_name("left")?;
+    if get_node_text(left, content) != "__slots__" {
+        return None;
+    }
+    let right = assignment.child_by_field_name("right")?;
+
+    let strings = if right.kind() == "string" {
+        vec![right]
+    } else {
+        let mut cursor = right.walk();
+        let strings = right.children(&mut cursor).filter(|n| n.kind() == "string").collect();
+        strings
+    };
+    let names = strings.into_iter()
+        .map(|s| get_node_text(s, content).trim_matches(|c| c == '"' || c == '\'').to_string())
+        .filter(|name| !(name.starts_with("__") && name.ends_with("__")))
+        .collect();
+    Some(names)
+}
+
+/// The base classes listed in a class header, as written. `Generic[T]` and bare
+/// type variables are left out.
+fn class_bases(class_node: Node, content: &str, type_vars: &HashSet<String>) -> Vec<String> {
+    let mut parents = Vec::new();
+    let Some(superclasses_node) = class_node.child_by_field_name("superclasses") else {
+        return parents;
+    };
+    let mut cursor = superclasses_node.walk();
+    for child in superclasses_node.children(&mut cursor) {
+        if !matches!(child.kind(), "identifier" | "attribute" | "subscript") {
+            continue;
+        }
+        // `Generic[T]` only declares type parameters, and `Protocol[T]` is a `Protocol`
+        let generic = match child.kind() {
+            "subscript" => child.child_by_field_name("value")
+                .map(|value| (value, decorator_name(value, content))),
+            _ => None,
+        };
+        let name = match generic {
+            Some((_, base)) if base == "Generic" => continue,
+            Some((value, base)) if base == "Protocol" => get_node_text(value, content),
+            _ => get_node_text(child, content),
+        };
+        // A bare type variable is no class
+        if type_vars.contains(&name) {
+            continue;
+        }
+        parents.push(name);
+    }
+    parents
+}
+
+/// Whether a class declares an interface: it derives from `Protocol` or `ABC`,
+/// or uses `metaclass=ABCMeta`.
+fn is_interface(class_node: Node, content: &str, type_vars: &HashSet<String>) -> bool {
+    let has_interface_base = class_bases(class_node, content, type_vars).iter()
+        .any(|base| matches!(base.rsplit('.').next().unwrap_or(base), "Protocol" | "ABC"));
+    let Some(superclasses_node) = class_node.child_by_field_name("superclasses") else {
+        return has_interface_base;
+    };
+    let mut cursor = superclasses_node.walk();
+    let has_abc_metaclass = superclasses_node.children(&mut cursor)
+        .filter(|child| child.kind() == "keyword_argument")
+        .any(|arg| {
+            arg.child_by_field_name("name").is_some_and(|n| get_node_text(n, content) == "metaclass")
+                && arg.child_by_field_name("value").is_some_and(|v| decorator_name(v, content) == "ABCMeta")
+        });
+    has_interface_base || has_abc_metaclass
+}
+
+/// Whether a base class (`Enum`, `enum.IntEnum`, ...) makes the class an enum.
+fn is_enum_base(parent: &str) -> bool {
+    let name = parent.rsplit('.').next().unwrap_or(parent);
+    matches!(name, "Enum" | "IntEnum" | "StrEnum" | "Flag" | "IntFlag")
+}
+
+/// The member defined by an unannotated enum body assignment (`RED = auto()`).
+/// `_sunder_` names such as `_ignore_` configure the enum and are not members.
+fn enum_member(node: Node, content: &str) -> Option<String> {
+    if node.kind() != "expression_statement" {
+        return None;
+    }
+    let assignment = node.named_child(0).filter(|n| n.kind() == "assignment")?;
+    if assignment.child_by_field_name("type").is_some() {
+        return None;
+    }
+    let left = assignment.child_by_field_name("left").filter(|n| n.kind() == "identifier")?;
+    let name = get_node_text(left, content);
+    (!(name.starts_with('_') && name.ends_with('_'))).then_some(name)
+}
+
+/// Returns the name and type of an annotated class-level attribute (`name: Type [= value]`).
+fn annotated_class_attribute<'a>(node: Node<'a>, content: &str) -> Option<(String, Node<'a>)> {
+    if node.kind() != "expression_statement" {
+        return None;
+    }
+    let assignment = node.named_child(0).filter(|n| n.kind() == "assignment")?;
+    let left = assignment.child_by_field_name("left").filter(|n| n.kind() == "identifier")?;
+    // Only the annotation names a type; a `field(default_factory=list)` value does not
+    let type_node = assignment.child_by_field_name("type")?;
+    Some((get_node_text(left, content), type_node))
+}
+
+/// Whether an annotation is `ClassVar[T]` (or `typing.ClassVar[T]`), with the
+/// type to read relationships from: `T`, the annotation itself when it is not a
+/// `ClassVar`, or nothing for a bare `ClassVar`.
+fn unwrap_class_var<'a>(type_node: Node<'a>, content: &str) -> (bool, Option<Node<'a>>) {
+    let is_class_var = |node: Node| {
+        let name = get_node_text(node, content);
+        name == "ClassVar" || name.ends_with(".ClassVar")
+    };
+    let node = if type_node.kind() == "type" { type_node.named_child(0) } else { Some(type_node) };
+    let Some(node) = node else {
+        return (false, Some(type_node));
+    };
+    match node.kind() {
+        "generic_type" if node.named_child(0).is_some_and(is_class_var) => {
+            let inner = node.named_child(1).and_then(|params| params.named_child(0));
+            (true, inner)
+        }
+        "subscript" if node.child_by_field_name("value").is_some_and(is_class_var) => {
+            (true, node.child_by_field_name("subscript"))
+        }
+        _ if is_class_var(node) => (true, None),
+        _ => (false, Some(type_node)),
+    }
+}
+
 fn get_python_visibility(name: &str) -> Visibility {
     if name.starts_with("__") && !name.ends_with("__") {
         Visibility::Private
@@ -221,25 +549,175 @@ fn get_python_visibility(name: &str) -> Visibility {
     }
 }
 
-fn resolve_types(node: Node, content: &str, types: &mut Vec<String>) {
+fn resolve_types(node: Node, content: &str, types: &mut Vec<String>, options: &ParseOptions, depth: usize) {
+    if type_depth_exceeded(node, depth, options) {
+        return;
+    }
     match node.kind() {
         "identifier" => {
             let name = get_node_text(node, content);
-            let primitives: HashSet<&str> = ["str", "int", "float", "bool", "bytes", "None", "Any", "List", "Dict", "Set", "Optional", "Union", "Tuple"].iter().cloned().collect();
-            
-            if !primitives.contains(name.as_str()) {
+            if !is_builtin(&name, is_python_builtin, options) {
                 types.push(name);
             }
         }
+        // Module-qualified names (`models.User`) are resolved against the file's imports
+        "attribute" => {
+            let name = get_node_text(node, content);
+            if !is_builtin(&name, is_python_builtin, options) {
+                types.push(name);
+            }
+        }
+        // `typing.Callable[[Event], Response]` relates to its argument and return types only
+        "generic_type" | "subscript" if node.named_child(0).is_some_and(|base| decorator_name(base, content) == "Callable") => {
+            let mut cursor = node.walk();
+            for child in node.named_children(&mut cursor).skip(1) {
+                resolve_types(child, content, types, options, depth + 1);
+            }
+        }
+        // Forward references: `-> "Engine"` or `x: "List[Wheel]"`
+        "string" => {
+            let text = get_node_text(node, content);
+            for word in text.split(|c: char| !c.is_alphanumeric() && c != '_') {
+                if word.starts_with(|c: char| c.is_uppercase()) && !is_builtin(word, is_python_builtin, options) {
+                    types.push(word.to_string());
+                }
+            }
+        }
         _ => {
+            // Only generic arguments (`Foo` in `List[Foo]`) count as a level of nesting
+            let value = node.child_by_field_name("value");
             let mut cursor = node.walk();
             for child in node.children(&mut cursor) {
-                resolve_types(child, content, types);
+                let is_argument = node.kind() == "type_parameter" || (node.kind() == "subscript" && Some(child) != value);
+                resolve_types(child, content, types, options, if is_argument { depth + 1 } else { depth });
             }
         }
     }
 }
 
+/// Names bound by a file's module-level imports.
+#[derive(Debug, Default)]
+struct Imports {
+    /// Local name to imported class name: `from models import User as Account` maps `Account` to `User`
+    names: HashMap<String, String>,
+    /// Local module names usable as a prefix: `import pkg.models as m` binds `m`
+    modules: HashSet<String>,
+}
+
+impl Imports {
+    fn collect(root: Node, content: &str) -> Self {
+        let mut imports = Imports::default();
+        let mut cursor = root.walk();
+        for statement in root.children(&mut cursor) {
+            let mut n_cursor = statement.walk();
+            match statement.kind() {
+                "import_statement" => {
+                    for name in statement.children_by_field_name("name", &mut n_cursor) {
+                        let (_, local) = import_binding(name, content);
+                        imports.modules.insert(local);
+                    }
+                }
+                "import_from_statement" => {
+                    for name in statement.children_by_field_name("name", &mut n_cursor) {
+                        let (imported, local) = import_binding(name, content);
+                        // `from pkg import models` may bind a module rather than a class
+                        imports.modules.insert(local.clone());
+                        imports.names.insert(local, imported);
+                    }
+                }
+                _ => {}
+            }
+        }
+        imports
+    }
+
+    /// Maps a referenced type to the name of the class it refers to.
+    fn resolve(&self, target: &str) -> String {
+        if let Some(imported) = self.names.get(target) {
+            return imported.clone();
+        }
+        match target.rsplit_once('.') {
+            Some((module, name)) if self.modules.contains(module) => name.to_string(),
+            _ => target.to_string(),
+        }
+    }
+}
+
+/// Module-scope type aliases (`type Handle = Widget` or `Handle: TypeAlias = Widget`)
+/// mapped to the non-builtin types they name.
+fn type_aliases(root: Node, content: &str, imports: &Imports, options: &ParseOptions) -> HashMap<String, Vec<String>> {
+    let mut aliases = HashMap::new();
+    let mut cursor = root.walk();
+    for statement in root.children(&mut cursor) {
+        let (name, value) = match statement.kind() {
+            "type_alias_statement" => (statement.named_child(0), statement.named_child(1)),
+            "expression_statement" => {
+                let Some(assignment) = statement.named_child(0).filter(|n| n.kind() == "assignment") else {
+                    continue;
+                };
+                // `TypeAlias` or `typing.TypeAlias`, named like a decorator
+                let is_alias = assignment.child_by_field_name("type")
+                    .is_some_and(|t| decorator_name(t.named_child(0).unwrap_or(t), content) == "TypeAlias");
+                if !is_alias {
+                    continue;
+                }
+                (assignment.child_by_field_name("left"), assignment.child_by_field_name("right"))
+            }
+            _ => continue,
+        };
+        let (Some(name), Some(value)) = (name, value) else {
+            continue;
+        };
+
+        let mut targets = Vec::new();
+        resolve_types(value, content, &mut targets, options, 0);
+        let targets = targets.iter()
+            .map(|t| imports.resolve(t))
+            .filter(|t| !is_builtin(t, is_python_builtin, options))
+            .collect();
+        aliases.insert(get_node_text(name, content), targets);
+    }
+    aliases
+}
+
+/// Module-level type variables: `T = TypeVar("T")`, plus `ParamSpec` and `TypeVarTuple`.
+fn type_vars(root: Node, content: &str) -> HashSet<String> {
+    let mut cursor = root.walk();
+    let type_vars = root.children(&mut cursor)
+        .filter(|statement| statement.kind() == "expression_statement")
+        .filter_map(|statement| statement.named_child(0).filter(|n| n.kind() == "assignment"))
+        .filter(|assignment| {
+            assignment.child_by_field_name("right")
+                .filter(|call| call.kind() == "call")
+                .and_then(|call| call.child_by_field_name("function"))
+                .is_some_and(|f| matches!(decorator_name(f, content).as_str(), "TypeVar" | "ParamSpec" | "TypeVarTuple"))
+        })
+        .filter_map(|assignment| assignment.child_by_field_name("left"))
+        .map(|name| get_node_text(name, content))
+        .collect();
+    type_vars
+}
+
+/// The imported dotted name and the local name it is bound to (its alias, if any).
+fn import_binding(name: Node, content: &str) -> (String, String) {
+    match name.kind() {
+        "aliased_import" => {
+            let imported = name.child_by_field_name("name").map(|n| get_node_text(n, content)).unwrap_or_default();
+            let alias = name.child_by_field_name("alias").map(|n| get_node_text(n, content)).unwrap_or_default();
+            (imported, alias)
+        }
+        _ => {
+            let imported = get_node_text(name, content);
+            (imported.clone(), imported)
+        }
+    }
+}
+
+fn is_python_builtin(name: &str) -> bool {
+    const PRIMITIVES: &[&str] = &["str", "int", "float", "bool", "bytes", "None", "Any", "List", "Dict", "Set", "Optional", "Union", "Tuple", "Callable"];
+    PRIMITIVES.contains(&name)
+}
+
 fn get_node_text(node: Node, content: &str) -> String {
     node.utf8_text(content.as_bytes())
         .ok()
@@ -290,6 +768,29 @@ class Dog:
         Ok(())
     }
 
+    #[test]
+    fn test_parse_class_location() -> Result<()> {
+        let content = "
+import os
+
+class Dog:
+    class Puppy: pass
+";
+        let classes = parse(content)?;
+        let dog = classes.iter().find(|c| c.name == "Dog").unwrap();
+        assert_eq!((dog.line, dog.column), (4, 1));
+        let puppy = classes.iter().find(|c| c.name == "Dog.Puppy").unwrap();
+        assert_eq!((puppy.line, puppy.column), (5, 5));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_sets_language() -> Result<()> {
+        let classes = parse("class User: pass")?;
+        assert_eq!(classes[0].language.as_deref(), Some("python"));
+        Ok(())
+    }
+
     #[test]
     fn test_parse_nested_classes() -> Result<()> {
         let content = "
@@ -332,6 +833,128 @@ class User:
         Ok(())
     }
 
+    #[test]
+    fn test_parse_slots() -> Result<()> {
+        let content = "
+class Account:
+    __slots__ = (\"name\", \"_secret\", \"__weakref__\")
+
+    def __init__(self, name):
+        self.name = name
+";
+        let classes = PythonParser.parse(content)?;
+        let account = &classes[0];
+        assert_eq!(account.properties.len(), 2);
+        assert!(account.properties.iter().any(|p| p.name == "name" && p.visibility == Visibility::Public));
+        assert!(account.properties.iter().any(|p| p.name == "_secret" && p.visibility == Visibility::Protected));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_keyword_only_default_parameters() -> Result<()> {
+        let content = "
+class Car:
+    def __init__(self, wheels, /, *, engine: Engine = None):
+        self.engine = engine
+
+    def tune(self, *args: Part, tuner: Tuner = None, **extras: Extra):
+        pass
+";
+        let classes = PythonParser.parse(content)?;
+        let car = &classes[0];
+        assert!(car.relationships.iter().any(|r| r.target == "Engine" && r.rel_type == RelationshipType::Aggregation));
+        for target in ["Part", "Tuner", "Extra"] {
+            assert!(car.relationships.iter().any(|r| r.target == target && r.rel_type == RelationshipType::Dependency));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_forward_reference_strings() -> Result<()> {
+        let content = "
+class Factory:
+    wheels: \"List[Wheel]\"
+
+    def build(self) -> \"Engine\":
+        pass
+";
+        let classes = PythonParser.parse(content)?;
+        let factory = &classes[0];
+        assert!(factory.relationships.iter().any(|r| r.target == "Engine" && r.rel_type == RelationshipType::Dependency));
+        assert!(factory.relationships.iter().any(|r| r.target == "Wheel" && r.rel_type == RelationshipType::Aggregation));
+        assert!(!factory.relationships.iter().any(|r| r.target == "List"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_dataclass_stereotypes() -> Result<()> {
+        let content = "
+@dataclass(frozen=True)
+class Money:
+    amount: int
+
+@dataclasses.dataclass
+class Order:
+    total: Money
+
+class Plain:
+    pass
+";
+        let classes = PythonParser.parse(content)?;
+        let stereotype = |name: &str| classes.iter().find(|c| c.name == name).unwrap().stereotype.clone();
+        assert_eq!(stereotype("Money").as_deref(), Some("frozen"));
+        assert_eq!(stereotype("Order").as_deref(), Some("dataclass"));
+        assert_eq!(stereotype("Plain"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_compose_directive() -> Result<()> {
+        let content = "
+class Car:
+    def __init__(self, engine: Engine):
+        self.engine = engine  # marco-polo: compose Engine
+";
+        let classes = PythonParser.parse(content)?;
+        assert!(classes[0].relationships.iter().all(|r| r.target != "Engine" || r.rel_type == RelationshipType::Composition));
+        assert!(classes[0].relationships.iter().any(|r| r.target == "Engine"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_class_scope_annotations() -> Result<()> {
+        let content = "
+class Point:
+    x: int
+    _owner: Canvas = None
+    count = 0
+";
+        let classes = PythonParser.parse(content)?;
+        let point = &classes[0];
+        assert!(point.properties.iter().any(|p| p.name == "x" && p.visibility == Visibility::Public));
+        assert!(point.properties.iter().any(|p| p.name == "_owner" && p.visibility == Visibility::Protected));
+        assert!(!point.properties.iter().any(|p| p.name == "count"));
+        assert!(point.relationships.iter().any(|r| r.target == "Canvas" && r.label.as_deref() == Some("_owner")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_dataclass_field_defaults() -> Result<()> {
+        let content = "
+@dataclass
+class Order:
+    x: List[Item] = field(default_factory=list)
+    tags: Dict[str, Tag] = dataclasses.field(default_factory=dict, repr=False)
+";
+        let classes = PythonParser.parse(content)?;
+        let order = &classes[0];
+        assert!(order.properties.iter().any(|p| p.name == "x"));
+        assert!(order.relationships.iter().any(|r| r.target == "Item" && r.label.as_deref() == Some("x")));
+        assert!(order.relationships.iter().any(|r| r.target == "Tag" && r.label.as_deref() == Some("tags")));
+        assert!(!order.relationships.iter().any(|r| r.target.contains("field") || r.target == "list"));
+        Ok(())
+    }
+
     #[test]
     fn test_parse_multiple_classes() -> Result<()> {
         let content = "
@@ -378,6 +1001,35 @@ class MathUtils:
         Ok(())
     }
 
+    #[test]
+    fn test_parse_overloads_collapse() -> Result<()> {
+        let content = "
+import typing
+from typing import overload
+
+class Parser:
+    @overload
+    def parse(self, data: str) -> Document: ...
+    @typing.overload
+    def parse(self, data: bytes) -> Document: ...
+    @overload
+    def parse(self, data: Stream) -> Document: ...
+    def parse(self, data):
+        return Document(data)
+
+    def close(self):
+        pass
+";
+        let classes = parse(content)?;
+        let parser = &classes[0];
+        let parses: Vec<_> = parser.methods.iter().filter(|m| m.name == "parse").collect();
+        assert_eq!(parses.len(), 1);
+        assert_eq!(parses[0].annotations, vec!["overload"]);
+        assert!(parser.methods.iter().any(|m| m.name == "close" && m.annotations.is_empty()));
+        assert!(parser.relationships.iter().any(|r| r.target == "Stream"));
+        Ok(())
+    }
+
     #[test]
     fn test_parse_async_methods() -> Result<()> {
         let content = "
@@ -466,4 +1118,263 @@ class Generic(List[int]): pass
 
         Ok(())
     }
+
+    #[test]
+    fn test_parse_tuple_assigned_properties() -> Result<()> {
+        let content = "
+class Point:
+    def __init__(self):
+        self.x, self.y = f()
+        (self.z, self.w) = 0, 0
+";
+        let classes = parse(content)?;
+        let names: Vec<_> = classes[0].properties.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["x", "y", "z", "w"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_reassigned_properties_once() -> Result<()> {
+        let content = "
+class Counter:
+    def __init__(self):
+        self.count = 0
+        self.count += 1
+        self.count = self.count * 2
+";
+        let classes = parse(content)?;
+        let counts = classes[0].properties.iter().filter(|p| p.name == "count").count();
+        assert_eq!(counts, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_resolves_imported_names() -> Result<()> {
+        let models = parse("
+class User:
+    pass
+")?;
+        let service = parse("
+import models
+import typing
+from models import User as Account
+from .billing import Invoice
+
+class Service(models.Base):
+    def find(self, user_id: int) -> Account: ...
+    def owner(self, invoice: Invoice) -> models.User: ...
+    def maybe(self) -> typing.Optional[models.Order]: ...
+")?;
+        let user = &models[0];
+        let rels = &service[0].relationships;
+        assert!(rels.iter().any(|r| r.target == user.name && r.rel_type == RelationshipType::Dependency));
+        assert!(rels.iter().any(|r| r.target == "Base" && r.rel_type == RelationshipType::Inheritance));
+        assert!(rels.iter().any(|r| r.target == "Invoice"));
+        assert!(rels.iter().any(|r| r.target == "Order"));
+        assert!(!rels.iter().any(|r| r.target.contains('.') || r.target == "Account" || r.target == "Optional"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_resolves_type_aliases() -> Result<()> {
+        let content = "
+from typing import TypeAlias
+
+type Handle = Widget
+Handles: TypeAlias = Optional[Gadget]
+UserId: TypeAlias = int
+
+class Panel:
+    h: Handle
+    others: Handles
+    owner: UserId
+";
+        let classes = parse(content)?;
+        let rels = &classes[0].relationships;
+        assert!(rels.iter().any(|r| r.target == "Widget" && r.label.as_deref() == Some("h")));
+        assert!(rels.iter().any(|r| r.target == "Gadget" && r.label.as_deref() == Some("others")));
+        assert!(!rels.iter().any(|r| ["Handle", "Handles", "UserId"].contains(&r.target.as_str())));
+        assert_eq!(rels.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_enum_auto_members() -> Result<()> {
+        let content = "
+from enum import Enum, auto
+
+class Color(Enum):
+    _ignore_ = ['tmp']
+    RED = auto()
+    GREEN = auto()
+
+    def describe(self):
+        return self.name
+";
+        let classes = PythonParser.parse(content)?;
+        let color = &classes[0];
+        assert_eq!(color.stereotype.as_deref(), Some("enumeration"));
+        let members: Vec<_> = color.properties.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(members, vec!["RED", "GREEN"]);
+        assert!(color.relationships.iter().all(|r| r.target != "auto"));
+        assert_eq!(color.methods[0].name, "describe");
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_class_var_is_static() -> Result<()> {
+        let content = "
+from typing import ClassVar
+import typing
+
+class Counter:
+    count: ClassVar[int] = 0
+    registry: typing.ClassVar[Registry]
+    name: str
+
+    def __init__(self):
+        self.value = 0
+";
+        let classes = PythonParser.parse(content)?;
+        let counter = &classes[0];
+        let properties: Vec<_> = counter.properties.iter().map(|p| (p.name.as_str(), p.is_static)).collect();
+        assert_eq!(properties, vec![("count", true), ("registry", true), ("name", false), ("value", false)]);
+
+        let targets: Vec<_> = counter.relationships.iter().map(|r| r.target.as_str()).collect();
+        assert_eq!(targets, vec!["Registry"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_generic_bases() -> Result<()> {
+        let content = "
+from typing import Generic, Protocol, TypeVar
+
+T = TypeVar(\"T\")
+
+class Repo(Generic[T], BaseRepo):
+    pass
+
+class Reader(Protocol[T]):
+    pass
+
+class Cache(Mapping[str, T], T):
+    pass
+";
+        let classes = PythonParser.parse(content)?;
+        let parents = |name: &str| -> Vec<String> {
+            let class = classes.iter().find(|c| c.name == name).unwrap();
+            class.relationships.iter()
+                .filter(|r| r.rel_type == RelationshipType::Inheritance)
+                .map(|r| r.target.clone())
+                .collect()
+        };
+        assert_eq!(parents("Repo"), vec!["BaseRepo"]);
+        assert_eq!(parents("Reader"), vec!["Protocol"]);
+        assert_eq!(parents("Cache"), vec!["Mapping[str, T]"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_dataclass_realization() -> Result<()> {
+        let content = "
+from abc import ABC, ABCMeta
+from dataclasses import dataclass
+from typing import Protocol
+
+class Priced(Protocol):
+    def price(self) -> int: ...
+
+class Shipped(metaclass=ABCMeta):
+    pass
+
+class Discounted(Priced, Protocol):
+    pass
+
+@dataclass
+class Item:
+    name: str
+
+@dataclass
+class Book(Item, Priced, Shipped):
+    isbn: str
+";
+        let classes = PythonParser.parse(content)?;
+        let edges = |name: &str| -> Vec<(String, RelationshipType)> {
+            let class = classes.iter().find(|c| c.name == name).unwrap();
+            class.relationships.iter()
+                .filter(|r| r.rel_type.is_generalization())
+                .map(|r| (r.target.clone(), r.rel_type.clone()))
+                .collect()
+        };
+
+        let book = classes.iter().find(|c| c.name == "Book").unwrap();
+        assert_eq!(book.stereotype.as_deref(), Some("dataclass"));
+        assert_eq!(edges("Book"), vec![
+            ("Item".to_string(), RelationshipType::Inheritance),
+            ("Priced".to_string(), RelationshipType::Realization),
+            ("Shipped".to_string(), RelationshipType::Realization),
+        ]);
+        // A protocol extending another protocol inherits from it
+        assert_eq!(edges("Discounted"), vec![
+            ("Priced".to_string(), RelationshipType::Inheritance),
+            ("Protocol".to_string(), RelationshipType::Inheritance),
+        ]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_inner_class_of_decorated_outer() -> Result<()> {
+        let content = "
+@dataclass
+class Outer:
+    @dataclass(frozen=True)
+    class Inner:
+        value: int
+
+    class Plain:
+        pass
+";
+        let classes = PythonParser.parse(content)?;
+        let names: Vec<_> = classes.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["Outer", "Outer.Inner", "Outer.Plain"]);
+        let inner = classes.iter().find(|c| c.name == "Outer.Inner").unwrap();
+        assert_eq!(inner.stereotype.as_deref(), Some("frozen"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_callable_types() -> Result<()> {
+        let content = "
+import typing
+from typing import Callable
+
+class Bus:
+    def subscribe(self, on: Callable[[Event], Response]) -> typing.Callable[[Request], Reply]:
+        pass
+
+    def fallback(self, handler: Callable[..., Result], hook: Callable):
+        pass
+";
+        let classes = PythonParser.parse(content)?;
+        let mut targets: Vec<_> = classes[0].relationships.iter()
+            .filter(|r| r.rel_type == RelationshipType::Dependency)
+            .map(|r| r.target.as_str())
+            .collect();
+        targets.sort();
+        assert_eq!(targets, vec!["Event", "Reply", "Request", "Response", "Result"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_type_depth_counts_generic_levels() -> Result<()> {
+        let options = ParseOptions { max_type_depth: 2, ..ParseOptions::default() };
+        let classes = PythonParser.parse_with_options("
+class Holder:
+    items: Outer[Middle[Inner[Deepest]]]
+", &options)?;
+        let targets: Vec<_> = classes[0].relationships.iter().map(|r| r.target.as_str()).collect();
+        assert_eq!(targets, vec!["Outer", "Middle", "Inner"]);
+        Ok(())
+    }
 }
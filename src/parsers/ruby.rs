@@ -1,51 +1,72 @@
+use std::sync::OnceLock;
 use tree_sitter::{Parser, Query, QueryCursor, Node};
 use crate::models::{ClassInfo, Relationship, RelationshipType, Visibility, MethodInfo, PropertyInfo};
 use anyhow::{Result, Context};
-use std::collections::HashSet;
-use super::LanguageParser;
+use super::{is_builtin, parse_tree, LanguageParser, ParseOptions};
+
+const RUBY_ENTITY_QUERY_STR: &str = "[(class) (module)] @entity";
+
+/// The class/module query, compiled once and shared by every parse.
+fn entity_query() -> &'static Query {
+    static ENTITY_QUERY: OnceLock<Query> = OnceLock::new();
+    ENTITY_QUERY.get_or_init(|| {
+        Query::new(tree_sitter_ruby::language(), RUBY_ENTITY_QUERY_STR)
+            .expect("Static Ruby entity query is invalid")
+    })
+}
 
 pub struct RubyParser;
 
 impl LanguageParser for RubyParser {
+    fn name(&self) -> &str {
+        "ruby"
+    }
+
     fn extensions(&self) -> &[&str] {
         &["rb"]
     }
 
-    fn parse(&self, content: &str) -> Result<Vec<ClassInfo>> {
+    fn parse_with_options(&self, content: &str, options: &ParseOptions) -> Result<Vec<ClassInfo>> {
         let mut parser = Parser::new();
         let language = tree_sitter_ruby::language();
         parser.set_language(language)
             .context("Error loading Ruby grammar")?;
 
-        let tree = parser.parse(content, None)
+        let tree = parse_tree(&mut parser, content, options)
             .context("Failed to parse Ruby content")?;
 
         let root_node = tree.root_node();
         let mut classes = Vec::new();
 
         // 1. Find all classes and modules
-        let query_str = "
-            [(class) (module)] @entity
-        ";
-        let query = Query::new(language, query_str).expect("Invalid Ruby entity query");
         let mut cursor = QueryCursor::new();
-        let matches = cursor.matches(&query, root_node, content.as_bytes());
+        let matches = cursor.matches(entity_query(), root_node, content.as_bytes());
 
         for m in matches {
             let entity_node = m.captures[0].node;
             
             // Extract Full Name (Namespace Aware)
             let mut name_parts = Vec::new();
+            // The outermost enclosing modules act as the package; a class stops it
+            let mut namespaces = Vec::new();
             let mut curr = Some(entity_node);
             while let Some(n) = curr {
                 if n.kind() == "class" || n.kind() == "module" {
                     if let Some(name_node) = n.child_by_field_name("name") {
                         name_parts.push(get_node_text(name_node, content));
+                        if n != entity_node {
+                            if n.kind() == "class" {
+                                namespaces.clear();
+                            } else {
+                                namespaces.push(get_node_text(name_node, content));
+                            }
+                        }
                     }
                 }
                 curr = n.parent();
             }
             name_parts.reverse();
+            namespaces.reverse();
             let full_name = name_parts.join("::");
 
             // Extract Superclass
@@ -55,7 +76,9 @@ impl LanguageParser for RubyParser {
                     let mut super_cursor = super_node.walk();
                     for child in super_node.children(&mut super_cursor) {
                         if child.kind() == "constant" || child.kind() == "scope_resolution" {
-                            superclass = Some(get_node_text(child, content));
+                            // Keep the whole scoped constant; `::Base` is anchored at the top level
+                            let text = get_node_text(child, content);
+                            superclass = Some(text.trim_start_matches("::").to_string());
                             break;
                         }
                     }
@@ -71,12 +94,20 @@ impl LanguageParser for RubyParser {
                     target,
                     rel_type: RelationshipType::Inheritance,
                     label: None,
+                    member_visibility: None,
+                    provenance: Some("base clause".to_string()),
+                    cardinality: None,
                 });
             }
 
             // Process body with visibility tracking
             if let Some(body) = entity_node.child_by_field_name("body") {
                 let mut current_visibility = Visibility::Public;
+                // `module_function` with no arguments turns the following methods into module methods
+                let mut module_function_mode = false;
+                let mut module_functions: Vec<String> = Vec::new();
+                // `extend self` makes the module's public instance methods callable on the module
+                let mut extends_self = false;
                 let mut body_cursor = body.walk();
                 for child in body.children(&mut body_cursor) {
                     match child.kind() {
@@ -84,9 +115,14 @@ impl LanguageParser for RubyParser {
                             if let Some(name_node) = child.child_by_field_name("name") {
                                 let m_name = get_node_text(name_node, content);
                                 
+                                if module_function_mode {
+                                    module_functions.push(m_name.clone());
+                                }
                                 methods.push(MethodInfo {
                                     name: m_name.clone(),
                                     visibility: current_visibility,
+                                    is_abstract: false,
+                                    annotations: Vec::new(),
                                 });
                                 
                                 // Heuristic: Check parameters for relationships
@@ -102,7 +138,7 @@ impl LanguageParser for RubyParser {
                                             if !IGNORED_PARAMS.contains(&p_text.as_str()) {
                                                 let target = to_pascal_case(&p_text);
                                                 
-                                                if !is_ruby_builtin(&target) {
+                                                if !is_builtin(&target, is_ruby_builtin, options) {
                                                     let rel_type = if m_name == "initialize" {
                                                         RelationshipType::Aggregation
                                                     } else {
@@ -112,12 +148,36 @@ impl LanguageParser for RubyParser {
                                                         target,
                                                         rel_type,
                                                         label: Some(p_text.clone()),
+                                                        member_visibility: Some(current_visibility),
+                                                        provenance: Some(format!("param {} in method {}", p_text, m_name)),
+                                                        cardinality: None,
                                                     });
                                                 }
                                             }
                                         }
                                     }
                                 }
+
+                                if options.ruby_yard {
+                                    for target in yard_types(child, content, options) {
+                                        relationships.push(Relationship {
+                                            target,
+                                            rel_type: RelationshipType::Dependency,
+                                            label: None,
+                                            member_visibility: Some(current_visibility),
+                                            provenance: Some(format!("YARD tag in method {}", m_name)),
+                                            cardinality: None,
+                                        });
+                                    }
+                                }
+                            }
+                        }
+                        // `alias save! save`: the new name is a method like the original
+                        "alias" => {
+                            let name = child.child_by_field_name("name").map(|n| get_node_text(n, content));
+                            let original = child.child_by_field_name("alias").map(|n| get_node_text(n, content));
+                            if let (Some(name), Some(original)) = (name, original) {
+                                add_alias(&mut methods, name, &original, current_visibility);
                             }
                         }
                         "singleton_method" => {
@@ -125,6 +185,8 @@ impl LanguageParser for RubyParser {
                                 methods.push(MethodInfo {
                                     name: format!("self.{}", get_node_text(name_node, content)),
                                     visibility: Visibility::Public,
+                                    is_abstract: false,
+                                    annotations: Vec::new(),
                                 });
                             }
                         }
@@ -139,11 +201,7 @@ impl LanguageParser for RubyParser {
 
                             match cmd.as_str() {
                                 "private" | "protected" | "public" => {
-                                    let new_visibility = match cmd.as_str() {
-                                        "private" => Visibility::Private,
-                                        "protected" => Visibility::Protected,
-                                        _ => Visibility::Public,
-                                    };
+                                    let new_visibility = Visibility::from_keyword(&cmd).unwrap_or(Visibility::Public);
 
                                     if let Some(args) = child.child_by_field_name("arguments") {
                                         // Handles `private :foo, :bar`
@@ -163,6 +221,34 @@ impl LanguageParser for RubyParser {
                                     } else {
                                         // Handles block-style `private`
                                         current_visibility = new_visibility;
+                                        module_function_mode = false;
+                                    }
+                                }
+                                "module_function" => {
+                                    if let Some(args) = child.child_by_field_name("arguments") {
+                                        // `module_function :foo` may come before or after `def foo`
+                                        let mut arg_cursor = args.walk();
+                                        for arg in args.children(&mut arg_cursor) {
+                                            if arg.kind().ends_with("symbol") || arg.kind() == "string" {
+                                                let text = get_node_text(arg, content);
+                                                module_functions.push(text.trim_start_matches(':').trim_matches('"').to_string());
+                                            }
+                                        }
+                                    } else {
+                                        module_function_mode = true;
+                                    }
+                                }
+                                // `alias_method :save!, :save`; its symbols never name classes
+                                "alias_method" => {
+                                    if let Some(args) = child.child_by_field_name("arguments") {
+                                        let mut arg_cursor = args.walk();
+                                        let names: Vec<String> = args.named_children(&mut arg_cursor)
+                                            .filter(|arg| arg.kind().ends_with("symbol") || arg.kind() == "string")
+                                            .map(|arg| get_node_text(arg, content).trim_start_matches(':').trim_matches('"').to_string())
+                                            .collect();
+                                        if let [name, original] = names.as_slice() {
+                                            add_alias(&mut methods, name.clone(), original, current_visibility);
+                                        }
                                     }
                                 }
                                 "attr_accessor" | "attr_reader" | "attr_writer" => {
@@ -173,6 +259,9 @@ impl LanguageParser for RubyParser {
                                             properties.push(PropertyInfo {
                                                 name: arg_text.trim_start_matches(':').to_string(),
                                                 visibility: current_visibility,
+                                                is_const: false,
+                                                is_static: false,
+                                                qualifiers: Vec::new(),
                                             });
                                         }
                                     }
@@ -181,11 +270,18 @@ impl LanguageParser for RubyParser {
                                     if let Some(args) = child.child_by_field_name("arguments") {
                                         let mut arg_cursor = args.walk();
                                         for arg in args.children(&mut arg_cursor) {
+                                            if arg.kind() == "self" {
+                                                extends_self |= cmd == "extend";
+                                                continue;
+                                            }
                                             let arg_text = get_node_text(arg, content);
                                             relationships.push(Relationship {
                                                 target: arg_text,
                                                 rel_type: RelationshipType::Dependency,
                                                 label: Some(cmd.clone()),
+                                                member_visibility: None,
+                                                provenance: Some(format!("{} statement", cmd)),
+                                                cardinality: None,
                                             });
                                         }
                                     }
@@ -196,6 +292,17 @@ impl LanguageParser for RubyParser {
                         _ => {}
                     }
                 }
+
+                // Module functions are public module-level methods, listed like `def self.x`
+                for method in methods.iter_mut().filter(|m| module_functions.contains(&m.name)) {
+                    method.name = format!("self.{}", method.name);
+                    method.visibility = Visibility::Public;
+                }
+                if extends_self && entity_node.kind() == "module" {
+                    for method in methods.iter_mut().filter(|m| m.visibility == Visibility::Public && !m.name.starts_with("self.")) {
+                        method.name = format!("self.{}", method.name);
+                    }
+                }
             }
 
             classes.push(ClassInfo {
@@ -203,6 +310,12 @@ impl LanguageParser for RubyParser {
                 methods,
                 properties,
                 relationships,
+                stereotype: None,
+                language: Some(self.name().to_string()),
+                line: entity_node.start_position().row as u32 + 1,
+                column: entity_node.start_position().column as u32 + 1,
+                out_of_line: false,
+                package: (!namespaces.is_empty()).then(|| namespaces.join("::")),
             });
         }
 
@@ -210,12 +323,78 @@ impl LanguageParser for RubyParser {
     }
 }
 
+/// Lists an aliased method under its new name, with the original's visibility
+/// when the original is already known.
+fn add_alias(methods: &mut Vec<MethodInfo>, name: String, original: &str, current_visibility: Visibility) {
+    if methods.iter().any(|m| m.name == name) {
+        return;
+    }
+    let visibility = methods.iter()
+        .find(|m| m.name == original)
+        .map_or(current_visibility, |m| m.visibility);
+    methods.push(MethodInfo {
+        name,
+        visibility,
+        is_abstract: false,
+        annotations: Vec::new(),
+    });
+}
+
 fn is_ruby_builtin(name: &str) -> bool {
-    let builtins: HashSet<&str> = [
+    const BUILTINS: &[&str] = &[
         "String", "Integer", "Float", "Array", "Hash", "Symbol", "TrueClass", "FalseClass", "NilClass",
         "Object", "Kernel", "Module", "Class", "Numeric", "Range", "Regexp", "Proc", "Method", "IO", "File", "Dir", "Time"
-    ].iter().cloned().collect();
-    builtins.contains(name) || name == "Data" || name == "Arg"
+    ];
+    BUILTINS.contains(&name) || name == "Data" || name == "Arg"
+}
+
+/// Extracts the non-builtin constants named in the YARD `@param [Type]` and
+/// `@return [Type]` tags of the comment block directly above a method.
+fn yard_types(method_node: Node, content: &str, options: &ParseOptions) -> Vec<String> {
+    let mut types = Vec::new();
+    for comment in leading_comments(method_node, content) {
+        let tag = comment.trim_start_matches('#').trim_start();
+        if !tag.starts_with("@param") && !tag.starts_with("@return") {
+            continue;
+        }
+        let Some(start) = tag.find('[') else { continue };
+        let Some(len) = tag[start..].find(']') else { continue };
+
+        let spec = &tag[start + 1..start + len];
+        for name in spec.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == ':')) {
+            if name.starts_with(|c: char| c.is_ascii_uppercase()) && !is_builtin(name, is_ruby_builtin, options) && name != "Boolean" {
+                types.push(name.to_string());
+            }
+        }
+    }
+    types
+}
+
+/// Collects the contiguous comment lines directly above `node`, in source order.
+fn leading_comments(node: Node, content: &str) -> Vec<String> {
+    // Comments before the first statement of a body are attached to the enclosing
+    // class/module rather than to the `body_statement`.
+    fn previous(node: Node) -> Option<Node> {
+        node.prev_sibling().or_else(|| {
+            node.parent()
+                .filter(|p| p.kind() == "body_statement")
+                .and_then(|p| p.prev_sibling())
+        })
+    }
+
+    let mut comments = Vec::new();
+    let mut next_row = node.start_position().row;
+    let mut curr = previous(node);
+    while let Some(n) = curr {
+        if n.kind() != "comment" || n.end_position().row + 1 != next_row {
+            break;
+        }
+        comments.push(get_node_text(n, content));
+        next_row = n.start_position().row;
+        curr = previous(n);
+    }
+    comments.reverse();
+    comments
 }
 
 fn get_node_text(node: Node, content: &str) -> String {
@@ -273,6 +452,23 @@ end
         Ok(())
     }
 
+    #[test]
+    fn test_ruby_class_location() -> Result<()> {
+        let content = "
+require 'json'
+
+module UI
+  class Button; end
+end
+";
+        let classes = parse(content)?;
+        let ui = classes.iter().find(|c| c.name == "UI").unwrap();
+        assert_eq!((ui.line, ui.column), (4, 1));
+        let button = classes.iter().find(|c| c.name == "UI::Button").unwrap();
+        assert_eq!((button.line, button.column), (5, 3));
+        Ok(())
+    }
+
     #[test]
     fn test_ruby_namespace() -> Result<()> {
         let content = "
@@ -303,6 +499,82 @@ end
         Ok(())
     }
 
+    #[test]
+    fn test_parse_scoped_superclass() -> Result<()> {
+        let content = "
+class Worker < ActiveJob::Base; end
+class Job < ::Queue::Job; end
+";
+        let classes = parse(content)?;
+        let worker = classes.iter().find(|c| c.name == "Worker").unwrap();
+        assert!(worker.relationships.iter().any(|r|
+            r.target == "ActiveJob::Base" && r.rel_type == RelationshipType::Inheritance
+        ));
+        let job = classes.iter().find(|c| c.name == "Job").unwrap();
+        assert!(job.relationships.iter().any(|r| r.target == "Queue::Job"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_entity_query_is_compiled_once() -> Result<()> {
+        let first: *const Query = entity_query();
+        for _ in 0..50 {
+            parse("class Cat < Animal; end")?;
+        }
+        assert!(std::ptr::eq(first, entity_query()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_module_functions() -> Result<()> {
+        let content = "
+module Util
+  def self.configure; end
+
+  module_function :helper
+
+  def helper; end
+
+  def plain; end
+
+  module_function
+
+  def format_name; end
+end
+";
+        let classes = parse(content)?;
+        let util = &classes[0];
+        let names: Vec<_> = util.methods.iter().map(|m| m.name.as_str()).collect();
+        assert!(names.contains(&"self.configure"));
+        assert!(names.contains(&"self.helper"));
+        assert!(names.contains(&"plain"));
+        assert!(names.contains(&"self.format_name"));
+        assert!(util.methods.iter().all(|m| m.visibility == Visibility::Public));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_extend_self() -> Result<()> {
+        let content = "
+module Slug
+  extend self
+
+  def slugify(text); end
+
+  private
+
+  def normalize(text); end
+end
+";
+        let classes = parse(content)?;
+        let slug = &classes[0];
+        let names: Vec<_> = slug.methods.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["self.slugify", "normalize"]);
+        assert_eq!(slug.methods[1].visibility, Visibility::Private);
+        assert!(slug.relationships.iter().all(|r| r.target != "self"));
+        Ok(())
+    }
+
     #[test]
     fn test_parse_modules_and_mixins() -> Result<()> {
         let content = "
@@ -413,4 +685,77 @@ end
         ));
         Ok(())
     }
+
+    #[test]
+    fn test_parse_yard_types() -> Result<()> {
+        let content = "
+class Car
+  # Drives somewhere.
+  # @param [Engine] e the engine
+  # @param [Array<Wheel>, nil] wheels
+  # @return [Trip]
+  def drive(e, wheels)
+  end
+
+  # @param [String] label
+  def tag(label)
+  end
+end
+";
+        let options = ParseOptions { ruby_yard: true, ..ParseOptions::default() };
+        let classes = RubyParser.parse_with_options(content, &options)?;
+        let car = &classes[0];
+        for target in ["Engine", "Wheel", "Trip"] {
+            assert!(car.relationships.iter().any(|r| r.target == target && r.rel_type == RelationshipType::Dependency));
+        }
+        assert!(!car.relationships.iter().any(|r| r.target == "String" || r.target == "Array"));
+
+        let classes = parse(content)?;
+        assert!(!classes[0].relationships.iter().any(|r| r.target == "Engine"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_aliases() -> Result<()> {
+        let content = "
+class Record
+  def save; end
+  alias_method :save!, :save
+
+  private
+
+  def destroy; end
+  alias destroy! destroy
+end
+";
+        let classes = RubyParser.parse(content)?;
+        let record = &classes[0];
+        assert!(record.relationships.is_empty());
+
+        let visibility = |name: &str| record.methods.iter().find(|m| m.name == name).unwrap().visibility;
+        assert_eq!(visibility("save!"), Visibility::Public);
+        assert_eq!(visibility("destroy!"), Visibility::Private);
+        assert_eq!(record.methods.len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_compact_classes() -> Result<()> {
+        let content = "
+class A; end; class B; end
+class C < A; attr_reader :x; def run; end; end
+";
+        let classes = RubyParser.parse(content)?;
+        let names: Vec<_> = classes.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["A", "B", "C"]);
+        assert!(classes[0].methods.is_empty() && classes[1].methods.is_empty());
+        assert_eq!((classes[0].line, classes[1].line), (2, 2));
+        assert!(classes[1].column > classes[0].column);
+
+        let c = &classes[2];
+        assert!(c.relationships.iter().any(|r| r.target == "A" && r.rel_type == RelationshipType::Inheritance));
+        assert_eq!(c.properties.iter().map(|p| p.name.as_str()).collect::<Vec<_>>(), vec!["x"]);
+        assert_eq!(c.methods.iter().map(|m| m.name.as_str()).collect::<Vec<_>>(), vec!["run"]);
+        Ok(())
+    }
 }
@@ -0,0 +1,60 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::Duration;
+
+/// Wall-clock parse time and file count per language, reported by `--profile`.
+#[derive(Debug, Default)]
+pub struct ParseProfile {
+    languages: BTreeMap<String, (usize, Duration)>,
+}
+
+impl ParseProfile {
+    pub fn record(&mut self, language: &str, elapsed: Duration) {
+        let entry = self.languages.entry(language.to_string()).or_default();
+        entry.0 += 1;
+        entry.1 += elapsed;
+    }
+
+    /// One line per language, followed by the total.
+    pub fn summary(&self) -> String {
+        let mut summary = String::from("Parse profile:\n");
+        let mut total_files = 0;
+        let mut total_time = Duration::ZERO;
+        for (language, (files, elapsed)) in &self.languages {
+            writeln!(&mut summary, "  {:<8} {:>6} files {:>12.3?}", language, files, elapsed).unwrap();
+            total_files += files;
+            total_time += *elapsed;
+        }
+        writeln!(&mut summary, "  {:<8} {:>6} files {:>12.3?}", "total", total_files, total_time).unwrap();
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::get_parser;
+    use crate::scanner::find_source_files;
+    use anyhow::Result;
+    use std::fs;
+    use std::path::Path;
+    use std::time::Instant;
+
+    #[test]
+    fn test_profile_reports_scanned_languages() -> Result<()> {
+        let mut profile = ParseProfile::default();
+        for path in find_source_files(Path::new("tests/ruby"), &["rb"], false, None)? {
+            let parser = get_parser("rb").unwrap();
+            let content = fs::read_to_string(&path)?;
+            let start = Instant::now();
+            parser.parse(&content)?;
+            profile.record(parser.name(), start.elapsed());
+        }
+
+        let summary = profile.summary();
+        assert!(summary.contains("ruby"));
+        assert!(summary.contains("total"));
+        assert!(!summary.contains("python"));
+        Ok(())
+    }
+}
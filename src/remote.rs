@@ -0,0 +1,115 @@
+use anyhow::{bail, Context, Result};
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A temporary shallow clone, deleted when dropped (including on error paths).
+pub struct Checkout {
+    dir: PathBuf,
+}
+
+impl Checkout {
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+}
+
+impl Drop for Checkout {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Arguments for `git clone`; `git_ref` may be a branch or a tag.
+pub fn clone_args(url: &str, git_ref: Option<&str>, dest: &Path) -> Vec<OsString> {
+    let mut args: Vec<OsString> = vec!["clone".into(), "--quiet".into(), "--depth".into(), "1".into()];
+    if let Some(git_ref) = git_ref {
+        args.push("--branch".into());
+        args.push(git_ref.into());
+    }
+    // `--` keeps a URL starting with `-` from being read as an option
+    args.push("--".into());
+    args.push(url.into());
+    args.push(dest.into());
+    args
+}
+
+/// Shallow-clones `url` into a fresh temporary directory.
+pub fn shallow_clone(url: &str, git_ref: Option<&str>) -> Result<Checkout> {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or_default();
+    let checkout = Checkout {
+        dir: std::env::temp_dir().join(format!("marco-polo-{}-{}", std::process::id(), nanos)),
+    };
+
+    log::info!("Cloning {}...", url);
+    let output = Command::new("git")
+        .args(clone_args(url, git_ref, checkout.path()))
+        .output()
+        .context("Failed to run `git`; is it installed and on PATH?")?;
+    if !output.status.success() {
+        bail!("git clone of {} failed: {}", url, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(checkout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::{python::PythonParser, LanguageParser};
+    use crate::scanner;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_clone_args() {
+        let dest = Path::new("/tmp/checkout");
+        let args = clone_args("https://example.com/x/y", None, dest);
+        assert_eq!(args, ["clone", "--quiet", "--depth", "1", "--", "https://example.com/x/y", "/tmp/checkout"]);
+
+        let args = clone_args("https://example.com/x/y", Some("v1.2"), dest);
+        assert_eq!(args, ["clone", "--quiet", "--depth", "1", "--branch", "v1.2", "--", "https://example.com/x/y", "/tmp/checkout"]);
+    }
+
+    #[test]
+    fn test_shallow_clone_is_scanned_and_removed() -> Result<()> {
+        if Command::new("git").arg("--version").output().is_err() {
+            eprintln!("Skipping: git is not installed");
+            return Ok(());
+        }
+
+        // A local repository served over file:// exercises the same clone path without network access
+        let temp = TempDir::new("origin");
+        let origin = temp.path();
+        fs::copy("tests/python/animals.py", origin.join("animals.py"))?;
+        let git = |args: &[&str]| -> Result<()> {
+            let output = Command::new("git").current_dir(origin).args(args).output()?;
+            assert!(output.status.success(), "git {:?} failed: {}", args, String::from_utf8_lossy(&output.stderr));
+            Ok(())
+        };
+        git(&["init", "--quiet", "--initial-branch", "main"])?;
+        git(&["add", "."])?;
+        git(&["-c", "user.name=test", "-c", "user.email=test@example.com", "commit", "--quiet", "-m", "init"])?;
+
+        let url = format!("file://{}", origin.display());
+        let checkout = shallow_clone(&url, Some("main"))?;
+
+        let files = scanner::find_source_files(checkout.path(), &["py"], false, None)?;
+        let classes = PythonParser.parse(&fs::read_to_string(&files[0])?)?;
+        assert!(classes.iter().any(|c| c.name == "Dog"));
+
+        let dir = checkout.path().to_path_buf();
+        drop(checkout);
+        assert!(!dir.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_clone_reports_git_error() {
+        if Command::new("git").arg("--version").output().is_err() {
+            return;
+        }
+        let err = shallow_clone("file:///nonexistent/marco-polo-repo", None).err().unwrap();
+        assert!(err.to_string().contains("git clone"));
+    }
+}
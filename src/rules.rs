@@ -0,0 +1,137 @@
+use crate::models::{ClassInfo, Relationship, RelationshipType};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+/// Looked up in the scanned root when `--config` is not given.
+pub const CONFIG_FILE: &str = "marco_polo.toml";
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+}
+
+/// A `[[rules]]` entry: fields whose name matches `pattern` (one optional `*`
+/// wildcard) get a synthetic edge to `target`, where `{}` is replaced with the
+/// wildcard match in PascalCase (`user_repo` against `*_repo` gives `User`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct Rule {
+    pub pattern: String,
+    pub target: String,
+    pub relationship: RelationshipType,
+}
+
+impl Rule {
+    /// The target class for a field name, if the name matches.
+    fn target_for(&self, field: &str) -> Option<String> {
+        let captured = match self.pattern.split_once('*') {
+            Some((prefix, suffix)) => {
+                if field.len() < prefix.len() + suffix.len() {
+                    return None;
+                }
+                field.strip_prefix(prefix)?.strip_suffix(suffix)?
+            }
+            None => (field == self.pattern).then_some(field)?,
+        };
+        Some(self.target.replace("{}", &pascal_case(captured)))
+    }
+}
+
+pub fn load_config(path: &Path) -> Result<Config> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config {:?}", path))?;
+    toml::from_str(&text).with_context(|| format!("Invalid config {:?}", path))
+}
+
+/// Adds the relationships implied by `rules` for every property, labelled with the
+/// property name and carrying its visibility. Existing identical edges are not repeated.
+pub fn apply_rules(classes: &mut [ClassInfo], rules: &[Rule]) {
+    for class in classes {
+        for prop in &class.properties {
+            for rule in rules {
+                let Some(target) = rule.target_for(&prop.name) else {
+                    continue;
+                };
+                let exists = class.relationships.iter().any(|r| {
+                    r.target == target && r.rel_type == rule.relationship && r.label.as_deref() == Some(&prop.name)
+                });
+                if !exists {
+                    class.relationships.push(Relationship {
+                        target,
+                        rel_type: rule.relationship.clone(),
+                        label: Some(prop.name.clone()),
+                        member_visibility: Some(prop.visibility),
+                        provenance: Some(format!("rule {} -> {}", rule.pattern, rule.target)),
+                        cardinality: None,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// `user_account` -> `UserAccount`.
+fn pascal_case(name: &str) -> String {
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars.next().map(|c| c.to_uppercase().chain(chars).collect::<String>()).unwrap_or_default()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::{python::PythonParser, LanguageParser};
+
+    #[test]
+    fn test_rule_maps_repo_field_to_repository() -> Result<()> {
+        let config: Config = toml::from_str(r#"
+[[rules]]
+pattern = "*_repo"
+target = "{}Repository"
+relationship = "aggregation"
+"#)?;
+        let mut classes = PythonParser.parse("
+class UserService:
+    def __init__(self, user_repo, audit_log_repo):
+        self.user_repo = user_repo
+        self.audit_log_repo = audit_log_repo
+        self.cache = {}
+")?;
+        apply_rules(&mut classes, &config.rules);
+
+        let rels = &classes[0].relationships;
+        assert!(rels.iter().any(|r| {
+            r.target == "UserRepository"
+                && r.rel_type == RelationshipType::Aggregation
+                && r.label.as_deref() == Some("user_repo")
+        }));
+        assert!(rels.iter().any(|r| r.target == "AuditLogRepository"));
+        assert_eq!(rels.len(), 2);
+
+        // Applying twice adds nothing new
+        apply_rules(&mut classes, &config.rules);
+        assert_eq!(classes[0].relationships.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rule_pattern_matching() {
+        let rule = Rule {
+            pattern: "repo_*".to_string(),
+            target: "{}Store".to_string(),
+            relationship: RelationshipType::Dependency,
+        };
+        assert_eq!(rule.target_for("repo_orders").as_deref(), Some("OrdersStore"));
+        assert_eq!(rule.target_for("orders"), None);
+
+        let exact = Rule { pattern: "db".to_string(), ..rule };
+        assert_eq!(exact.target_for("db").as_deref(), Some("DbStore"));
+        assert_eq!(exact.target_for("dbx"), None);
+    }
+}
@@ -1,8 +1,15 @@
 use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use ignore::WalkBuilder;
 use anyhow::Result;
 
-pub fn find_source_files(root: &Path, extensions: &[&str]) -> Result<Vec<PathBuf>> {
+/// Directory names that hold tests.
+const TEST_DIRS: &[&str] = &["test", "tests", "spec", "__tests__"];
+
+/// Finds files with the given extensions under `root`, skipping test code
+/// (see [`is_test_path`]) unless `include_tests` is set, and files last
+/// modified before `since` when given.
+pub fn find_source_files(root: &Path, extensions: &[&str], include_tests: bool, since: Option<SystemTime>) -> Result<Vec<PathBuf>> {
     let mut files = Vec::new();
 
     for entry in WalkBuilder::new(root).build() {
@@ -11,7 +18,10 @@ pub fn find_source_files(root: &Path, extensions: &[&str]) -> Result<Vec<PathBuf
 
         if path.is_file() {
             if let Some(ext) = path.extension().and_then(|s| s.to_str()) {
-                if extensions.contains(&ext) {
+                if extensions.contains(&ext)
+                    && (include_tests || !is_test_path(root, path))
+                    && since.is_none_or(|since| modified_since(path, since))
+                {
                     files.push(path.to_path_buf());
                 }
             }
@@ -21,17 +31,109 @@ pub fn find_source_files(root: &Path, extensions: &[&str]) -> Result<Vec<PathBuf
     Ok(files)
 }
 
+/// Files whose modification time cannot be read are kept.
+fn modified_since(path: &Path, since: SystemTime) -> bool {
+    path.metadata()
+        .and_then(|m| m.modified())
+        .map_or(true, |modified| modified >= since)
+}
+
+/// Parses `--since`: a duration back from now (`30s`, `15m`, `24h`, `7d`, `2w`)
+/// or a UTC date (`2024-05-01`, optionally with a time: `2024-05-01T12:30:00`).
+pub fn parse_since(value: &str) -> Result<SystemTime, String> {
+    since_time(value, SystemTime::now())
+}
+
+fn since_time(value: &str, now: SystemTime) -> Result<SystemTime, String> {
+    if value.contains('-') {
+        return parse_date(value).ok_or_else(|| format!("invalid date `{}`; expected YYYY-MM-DD[THH:MM[:SS]]", value));
+    }
+
+    let unit_at = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (amount, unit) = value.split_at(unit_at);
+    let amount: u64 = amount.parse().map_err(|_| format!("invalid duration `{}`; expected e.g. 7d or 24h", value))?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(format!("unknown unit in `{}`; use s, m, h, d or w", value)),
+    };
+    let total = amount.checked_mul(seconds).ok_or_else(|| format!("duration `{}` is out of range", value))?;
+    Ok(now.checked_sub(Duration::from_secs(total)).unwrap_or(UNIX_EPOCH))
+}
+
+fn parse_date(value: &str) -> Option<SystemTime> {
+    let (date, time) = value.split_once('T').unwrap_or((value, "00:00"));
+
+    let mut date_parts = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (date_parts.next()??, date_parts.next()??, date_parts.next()??);
+    if !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day) {
+        return None;
+    }
+
+    let mut time_parts = time.splitn(3, ':').map(|p| p.parse::<i64>().ok());
+    let hour = time_parts.next()??;
+    let minute = time_parts.next()??;
+    let second = time_parts.next().unwrap_or(Some(0))?;
+    if hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+
+    let seconds = days_from_civil(year, month, day) * 86_400 + hour * 3_600 + minute * 60 + second;
+    u64::try_from(seconds).ok().map(|s| UNIX_EPOCH + Duration::from_secs(s))
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date (Howard Hinnant's algorithm).
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Whether `path` looks like test code: it sits in a `test`/`tests`/`spec`/`__tests__`
+/// directory below `root`, or is named like `*_test.*`, `*Test.java`, `test_*.py` or `*_spec.rb`.
+pub fn is_test_path(root: &Path, path: &Path) -> bool {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let in_test_dir = relative.parent()
+        .map(|dir| dir.components().any(|c| TEST_DIRS.contains(&c.as_os_str().to_str().unwrap_or(""))))
+        .unwrap_or(false);
+
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+    let test_name = stem.ends_with("_test")
+        || (ext == "java" && stem.len() > 4 && (stem.ends_with("Test") || stem.ends_with("Tests")))
+        || (ext == "py" && stem.starts_with("test_"))
+        || (ext == "rb" && stem.ends_with("_spec"));
+
+    in_test_dir || test_name
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::TempDir;
 
     #[test]
     fn test_find_python_files() -> Result<()> {
         let root = Path::new("tests/python");
-        let files = find_source_files(root, &["py"])?;
+        let files = find_source_files(root, &["py"], false, None)?;
 
         // Should find at least `tests/python/animals.py`.
-        assert!(files.len() >= 1, "Should find at least one .py file");
+        assert!(!files.is_empty(), "Should find at least one .py file");
         assert!(
             files.iter().any(|p| p.ends_with("tests/python/animals.py")),
             "The found files should include animals.py"
@@ -43,10 +145,10 @@ mod tests {
     #[test]
     fn test_find_cpp_files() -> Result<()> {
         let root = Path::new("tests/cpp");
-        let files = find_source_files(root, &["cpp"])?;
+        let files = find_source_files(root, &["cpp"], false, None)?;
 
         // Should find at least `tests/cpp/Animals.cpp`.
-        assert!(files.len() >= 1, "Should find at least one .cpp file");
+        assert!(!files.is_empty(), "Should find at least one .cpp file");
         assert!(
             files.iter().any(|p| p.ends_with("tests/cpp/Animals.cpp")),
             "The found files should include Animals.cpp"
@@ -58,9 +160,9 @@ mod tests {
     #[test]
     fn test_find_ruby_files() -> Result<()> {
         let root = Path::new("tests/ruby");
-        let files = find_source_files(root, &["rb"])?;
+        let files = find_source_files(root, &["rb"], false, None)?;
 
-        assert!(files.len() >= 1, "Should find at least one .rb file");
+        assert!(!files.is_empty(), "Should find at least one .rb file");
         assert!(
             files.iter().any(|p| p.ends_with("tests/ruby/animals.rb")),
             "The found files should include animals.rb"
@@ -68,5 +170,69 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_test_files_are_excluded_by_default() -> Result<()> {
+        let root = Path::new("tests/java");
+        let files = find_source_files(root, &["java"], false, None)?;
+        assert!(files.iter().any(|p| p.ends_with("Animals.java")));
+        assert!(!files.iter().any(|p| p.ends_with("ShapeTest.java")));
+
+        let files = find_source_files(root, &["java"], true, None)?;
+        assert!(files.iter().any(|p| p.ends_with("ShapeTest.java")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_test_path() {
+        let root = Path::new("project");
+        assert!(is_test_path(root, Path::new("project/src/test/java/Foo.java")));
+        assert!(is_test_path(root, Path::new("project/app/test_models.py")));
+        assert!(is_test_path(root, Path::new("project/lib/user_spec.rb")));
+        assert!(is_test_path(root, Path::new("project/pkg/parser_test.cpp")));
+        assert!(!is_test_path(root, Path::new("project/src/Contest.java")));
+        assert!(!is_test_path(root, Path::new("project/src/Test.java")));
+        // Only directories below the scanned root count
+        assert!(!is_test_path(Path::new("tests/python"), Path::new("tests/python/animals.py")));
+    }
+
+    #[test]
+    fn test_since_filters_by_mtime() -> Result<()> {
+        let temp = TempDir::new("since");
+        let dir = temp.path();
+        let now = SystemTime::now();
+        for (name, age_days) in [("old.py", 30), ("recent.py", 1)] {
+            let file = std::fs::File::create(dir.join(name))?;
+            file.set_modified(now - Duration::from_secs(age_days * 24 * 60 * 60))?;
+        }
+
+        let since = since_time("7d", now).unwrap();
+        let files = find_source_files(dir, &["py"], false, Some(since))?;
+        assert_eq!(files.len(), 1);
+        assert!(files[0].ends_with("recent.py"));
+        assert_eq!(find_source_files(dir, &["py"], false, None)?.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_since() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        assert_eq!(since_time("24h", now), Ok(now - Duration::from_secs(86_400)));
+        assert_eq!(since_time("2w", now), Ok(now - Duration::from_secs(14 * 86_400)));
+        assert_eq!(since_time("1970-01-02", now), Ok(UNIX_EPOCH + Duration::from_secs(86_400)));
+        assert_eq!(
+            since_time("2024-05-01T12:30:00", now),
+            Ok(UNIX_EPOCH + Duration::from_secs(1_714_566_600))
+        );
+        assert!(since_time("7x", now).is_err());
+        assert!(since_time("99999999999999999w", now).is_err());
+        assert!(since_time("2024-13-01", now).is_err());
+        assert!(since_time("2024-02-31", now).is_err());
+        assert!(since_time("2023-02-29", now).is_err());
+        assert!(since_time("2024-04-31", now).is_err());
+        assert!(since_time("2024-02-29", now).is_ok());
+        assert!(since_time("2000-02-29", now).is_ok());
+        assert!(since_time("1900-02-29", now).is_err());
+        assert!(since_time("soon", now).is_err());
+    }
 }
-        
\ No newline at end of file
@@ -0,0 +1,74 @@
+use crate::models::{ClassInfo, RelationshipType};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Member and relationship counts for a single class.
+#[derive(Debug, Serialize)]
+pub struct ClassStats {
+    pub name: String,
+    pub methods: usize,
+    pub properties: usize,
+    pub relationships: usize,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Totals {
+    pub classes: usize,
+    pub methods: usize,
+    pub properties: usize,
+    pub relationships: usize,
+}
+
+/// Model metrics printed by `--stats-only`.
+#[derive(Debug, Serialize)]
+pub struct Stats {
+    pub classes: Vec<ClassStats>,
+    pub relationships_by_type: BTreeMap<RelationshipType, usize>,
+    pub totals: Totals,
+}
+
+pub fn compute_stats(classes: &[ClassInfo]) -> Stats {
+    let mut relationships_by_type = BTreeMap::new();
+    let mut totals = Totals { classes: classes.len(), ..Totals::default() };
+
+    let class_stats = classes.iter()
+        .map(|class| {
+            for rel in &class.relationships {
+                *relationships_by_type.entry(rel.rel_type.clone()).or_insert(0) += 1;
+            }
+            totals.methods += class.methods.len();
+            totals.properties += class.properties.len();
+            totals.relationships += class.relationships.len();
+            ClassStats {
+                name: class.name.clone(),
+                methods: class.methods.len(),
+                properties: class.properties.len(),
+                relationships: class.relationships.len(),
+            }
+        })
+        .collect();
+
+    Stats { classes: class_stats, relationships_by_type, totals }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::{python::PythonParser, LanguageParser};
+    use anyhow::Result;
+    use std::fs;
+
+    #[test]
+    fn test_stats_for_python_fixture() -> Result<()> {
+        let classes = PythonParser.parse(&fs::read_to_string("tests/python/animals.py")?)?;
+        let stats = compute_stats(&classes);
+
+        assert_eq!(stats.totals, Totals { classes: 3, methods: 3, properties: 1, relationships: 2 });
+        assert_eq!(stats.relationships_by_type.get(&RelationshipType::Inheritance), Some(&2));
+
+        let json = serde_json::to_value(&stats)?;
+        assert_eq!(json["totals"]["classes"], 3);
+        assert_eq!(json["relationships_by_type"]["inheritance"], 2);
+        Ok(())
+    }
+}
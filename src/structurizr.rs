@@ -0,0 +1,99 @@
+use crate::mermaid::MermaidOptions;
+use crate::models::{ClassInfo, RelationshipType};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::Write;
+
+/// Renders a Structurizr DSL workspace with one component per class inside a
+/// single container, plus a component view of it. Relationships become `->`
+/// statements described by their type; edges to classes outside the scan are
+/// left out, since Structurizr rejects undeclared identifiers. Output is sorted,
+/// like [`crate::mermaid::generate_mermaid`].
+pub fn generate_structurizr(classes: &[ClassInfo], options: &MermaidOptions) -> String {
+    let mut classes: Vec<&ClassInfo> = classes.iter().collect();
+    classes.sort_by(|a, b| a.name.cmp(&b.name));
+
+    // Sanitizing can make two names collide (`a::B`, `a_B`); later ones get a numeric suffix
+    let mut ids: HashMap<&str, String> = HashMap::new();
+    let mut taken: HashSet<String> = HashSet::new();
+    for class in &classes {
+        let base = identifier(&class.name);
+        let mut id = base.clone();
+        let mut n = 2;
+        while !taken.insert(id.clone()) {
+            id = format!("{}_{}", base, n);
+            n += 1;
+        }
+        ids.insert(class.name.as_str(), id);
+    }
+
+    let mut dsl = String::new();
+    writeln!(&mut dsl, "workspace {{").unwrap();
+    writeln!(&mut dsl, "    model {{").unwrap();
+    writeln!(&mut dsl, "        codebase = softwareSystem \"Codebase\" {{").unwrap();
+    writeln!(&mut dsl, "            classes = container \"Classes\" {{").unwrap();
+    for class in &classes {
+        writeln!(&mut dsl, "                {} = component \"{}\"", ids[class.name.as_str()], class.name).unwrap();
+    }
+    writeln!(&mut dsl, "            }}").unwrap();
+    writeln!(&mut dsl, "        }}").unwrap();
+
+    let edges: BTreeSet<(&str, &str, &RelationshipType)> = classes.iter()
+        .flat_map(|class| class.relationships.iter()
+            .filter(|rel| options.include_private_in_dependencies
+                || rel.member_visibility.is_none_or(|v| options.visibilities.contains(&v)))
+            .map(move |rel| (class.name.as_str(), rel.target.as_str(), &rel.rel_type)))
+        .filter(|(source, target, _)| source != target && ids.contains_key(target))
+        .collect();
+    if !edges.is_empty() {
+        writeln!(&mut dsl).unwrap();
+    }
+    for (source, target, rel_type) in edges {
+        writeln!(&mut dsl, "        {} -> {} \"{}\"", ids[source], ids[target], rel_type.name()).unwrap();
+    }
+
+    writeln!(&mut dsl, "    }}").unwrap();
+    writeln!(&mut dsl, "    views {{").unwrap();
+    writeln!(&mut dsl, "        component classes {{").unwrap();
+    writeln!(&mut dsl, "            include *").unwrap();
+    writeln!(&mut dsl, "            autoLayout").unwrap();
+    writeln!(&mut dsl, "        }}").unwrap();
+    writeln!(&mut dsl, "    }}").unwrap();
+    writeln!(&mut dsl, "}}").unwrap();
+    dsl
+}
+
+/// DSL identifiers may only contain letters, digits and `_`; they are prefixed
+/// so they can neither start with a digit nor clash with the system and container.
+fn identifier(name: &str) -> String {
+    let sanitized: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("c_{}", sanitized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::{java::JavaParser, LanguageParser};
+    use anyhow::Result;
+
+    #[test]
+    fn test_generate_structurizr() -> Result<()> {
+        let classes = JavaParser.parse("
+public class Customer {}
+public class Order extends Document {
+    public Customer customer;
+}
+")?;
+        let output = generate_structurizr(&classes, &MermaidOptions::default());
+        assert!(output.starts_with("workspace {\n    model {\n"));
+        assert!(output.contains("                c_Order = component \"Order\"\n"));
+        assert!(output.contains("        c_Order -> c_Customer \"aggregation\"\n"));
+        // `Document` was not scanned, so it cannot be referenced
+        assert!(!output.contains("Document"));
+        assert!(output.contains("        component classes {\n"));
+
+        assert_eq!(identifier("shop::Order<T>"), "c_shop__Order_T_");
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+use anyhow::{bail, Context, Result};
+use std::fs::{self, DirBuilder, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The mermaid-cli executable looked up on PATH.
+pub const MMDC: &str = "mmdc";
+
+/// Renders a Mermaid diagram to SVG by shelling out to mermaid-cli (`mmdc`).
+pub fn render_svg(mmdc: &str, diagram: &str) -> Result<String> {
+    let dir = private_dir()?;
+    let result = render_in(&dir, mmdc, diagram);
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
+
+/// Renders through `diagram.mmd` and `diagram.svg` inside `dir`.
+fn render_in(dir: &Path, mmdc: &str, diagram: &str) -> Result<String> {
+    let input = dir.join("diagram.mmd");
+    let output = dir.join("diagram.svg");
+    OpenOptions::new().write(true).create_new(true).open(&input)
+        .and_then(|mut file| file.write_all(diagram.as_bytes()))
+        .with_context(|| format!("Failed to write {:?}", input))?;
+
+    let result = Command::new(mmdc).arg("-i").arg(&input).arg("-o").arg(&output).output();
+
+    match result {
+        Err(e) if e.kind() == ErrorKind::NotFound => bail!(
+            "`{}` (mermaid-cli) was not found on PATH; install it with `npm install -g @mermaid-js/mermaid-cli` to use --format svg",
+            mmdc
+        ),
+        Err(e) => Err(e).with_context(|| format!("Failed to run `{}`", mmdc)),
+        Ok(out) if !out.status.success() => {
+            bail!("`{}` failed: {}", mmdc, String::from_utf8_lossy(&out.stderr).trim())
+        }
+        Ok(_) => fs::read_to_string(&output).context("mermaid-cli did not produce an SVG"),
+    }
+}
+
+/// A fresh temp directory only this user can enter, so no one else can
+/// pre-plant the render's files. Unique per call, so concurrent renders in
+/// one process keep their own files.
+fn private_dir() -> Result<PathBuf> {
+    static RENDERS: AtomicUsize = AtomicUsize::new(0);
+    let mut builder = DirBuilder::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    loop {
+        let dir = std::env::temp_dir().join(format!(
+            "marco-polo-{}-{}",
+            std::process::id(),
+            RENDERS.fetch_add(1, Ordering::Relaxed)
+        ));
+        // Fails on anything already there, a symlink included
+        match builder.create(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e).with_context(|| format!("Failed to create {:?}", dir)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_render_svg_with_mmdc() -> Result<()> {
+        if Command::new(MMDC).arg("--version").output().is_err() {
+            eprintln!("Skipping: `{}` is not installed", MMDC);
+            return Ok(());
+        }
+
+        let svg = render_svg(MMDC, "classDiagram\n    class Car\n")?;
+        assert!(svg.contains("<svg"));
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_concurrent_renders_use_their_own_files() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        // Stands in for mmdc: waits so the renders overlap, then copies `-i` to `-o`
+        let temp = TempDir::new("fake-mmdc");
+        let fake = temp.path().join("mmdc");
+        fs::write(&fake, "#!/bin/sh\nsleep 0.2\ncp \"$2\" \"$4\"\n")?;
+        fs::set_permissions(&fake, fs::Permissions::from_mode(0o755))?;
+
+        let renders: Vec<_> = ["Car", "Boat", "Plane"].into_iter()
+            .map(|name| {
+                let fake = fake.clone();
+                std::thread::spawn(move || render_svg(fake.to_str().unwrap(), name))
+            })
+            .collect();
+        let outputs = renders.into_iter().map(|r| r.join().unwrap()).collect::<Result<Vec<_>>>()?;
+        assert_eq!(outputs, vec!["Car", "Boat", "Plane"]);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_private_dir_is_owner_only() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = private_dir()?;
+        let mode = fs::metadata(&dir)?.permissions().mode();
+        let other = private_dir()?;
+        fs::remove_dir(&dir)?;
+        fs::remove_dir(&other)?;
+
+        assert_eq!(mode & 0o777, 0o700);
+        assert_ne!(dir, other);
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_svg_missing_tool() {
+        let err = render_svg("marco-polo-missing-mmdc", "classDiagram\n").unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("was not found on PATH"));
+        assert!(message.contains("@mermaid-js/mermaid-cli"));
+    }
+}
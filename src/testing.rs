@@ -0,0 +1,51 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A scratch directory for tests, deleted when dropped so a failing assertion
+/// does not leave it behind.
+pub struct TempDir {
+    dir: PathBuf,
+}
+
+impl TempDir {
+    /// Creates an empty directory whose name starts with `marco-polo-{label}`,
+    /// unique across the tests running in this process.
+    pub fn new(label: &str) -> TempDir {
+        static DIRS: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "marco-polo-{}-{}-{}",
+            label,
+            std::process::id(),
+            DIRS.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("failed to create a test directory");
+        TempDir { dir }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_temp_dir_is_removed_on_drop() {
+        let temp = TempDir::new("guard");
+        let dir = temp.path().to_path_buf();
+        fs::write(dir.join("file.txt"), "x").unwrap();
+        assert!(dir.is_dir());
+        drop(temp);
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,656 @@
+use crate::models::{ClassInfo, Relationship, RelationshipType};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Merges classes that share a fully qualified name (e.g. a C++ class declared in a
+/// header and defined in a source file) into a single entry with [`ClassInfo::merge`].
+/// The first occurrence keeps its position (out-of-line C++ definitions carry no
+/// member visibility, so the header's is kept).
+pub fn merge_classes(classes: Vec<ClassInfo>) -> Vec<ClassInfo> {
+    // Group by name first so each class is merged in one pass
+    let mut groups: Vec<Vec<ClassInfo>> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for class in classes {
+        match index.get(&class.name) {
+            Some(&i) => groups[i].push(class),
+            None => {
+                index.insert(class.name.clone(), groups.len());
+                groups.push(vec![class]);
+            }
+        }
+    }
+
+    groups.into_iter()
+        .map(|group| {
+            let mut group = group.into_iter();
+            let mut merged = group.next().expect("groups are never empty");
+            merged.merge(group);
+            merged
+        })
+        .collect()
+}
+
+/// Drops classes only known from out-of-line C++ definitions when no class of that
+/// name is declared anywhere in the scan; their owner is then a namespace, as in
+/// `void utils::helper() {}`.
+pub fn drop_undeclared_out_of_line(mut classes: Vec<ClassInfo>) -> Vec<ClassInfo> {
+    let declared: HashSet<String> = classes.iter()
+        .filter(|c| !c.out_of_line)
+        .map(|c| c.name.clone())
+        .collect();
+    classes.retain(|c| !c.out_of_line || declared.contains(&c.name));
+    classes
+}
+
+/// Drops `Dependency` relationships seen fewer than `min_count` times between the
+/// same source and target. Run before merging/deduplication so repeats still count.
+pub fn drop_weak_dependencies(mut classes: Vec<ClassInfo>, min_count: usize) -> Vec<ClassInfo> {
+    let mut counts: HashMap<(String, String), usize> = HashMap::new();
+    for class in &classes {
+        for rel in class.relationships.iter().filter(|r| r.rel_type == RelationshipType::Dependency) {
+            *counts.entry((class.name.clone(), rel.target.clone())).or_default() += 1;
+        }
+    }
+
+    for class in &mut classes {
+        let name = class.name.clone();
+        class.relationships.retain(|r| {
+            r.rel_type != RelationshipType::Dependency
+                || counts[&(name.clone(), r.target.clone())] >= min_count
+        });
+    }
+    classes
+}
+
+/// Removes abstract classes and interfaces that sit in the middle of an inheritance
+/// chain (they have both a base and a subclass) and rewires their subclasses, and any
+/// other edge pointing at them, to the nearest base that is kept. Concrete classes
+/// and chain roots are never removed.
+pub fn flatten_inheritance(classes: Vec<ClassInfo>) -> Vec<ClassInfo> {
+    let bases: HashMap<String, Vec<String>> = classes.iter()
+        .map(|c| {
+            let targets = c.relationships.iter()
+                .filter(|r| r.rel_type.is_generalization())
+                .map(|r| r.target.clone())
+                .collect();
+            (c.name.clone(), targets)
+        })
+        .collect();
+    let subclassed: HashSet<&str> = bases.values().flatten().map(|t| t.as_str()).collect();
+    let intermediate: HashSet<String> = classes.iter()
+        .filter(|c| is_abstract_class(c) && !bases[&c.name].is_empty() && subclassed.contains(c.name.as_str()))
+        .map(|c| c.name.clone())
+        .collect();
+
+    classes.into_iter()
+        .filter(|c| !intermediate.contains(&c.name))
+        .map(|mut class| {
+            let mut rewired: Vec<Relationship> = Vec::new();
+            for rel in class.relationships {
+                if !intermediate.contains(&rel.target) {
+                    rewired.push(rel);
+                    continue;
+                }
+                let mut roots = Vec::new();
+                collapse_to_kept_bases(&rel.target, &bases, &intermediate, &mut HashSet::new(), &mut roots);
+                let generalization = rel.rel_type.is_generalization();
+                let label = if generalization { None } else { rel.label.clone() };
+                for target in roots {
+                    let duplicate = rewired.iter().any(|r| {
+                        r.target == target && if generalization {
+                            r.rel_type.is_generalization()
+                        } else {
+                            r.rel_type == rel.rel_type && r.label == label
+                        }
+                    });
+                    if !duplicate {
+                        rewired.push(Relationship { target, label: label.clone(), ..rel.clone() });
+                    }
+                }
+            }
+            class.relationships = rewired;
+            class
+        })
+        .collect()
+}
+
+/// Interfaces, or classes declaring at least one abstract method.
+fn is_abstract_class(class: &ClassInfo) -> bool {
+    class.is_interface() || class.methods.iter().any(|m| m.is_abstract)
+}
+
+fn collapse_to_kept_bases(
+    name: &str,
+    bases: &HashMap<String, Vec<String>>,
+    intermediate: &HashSet<String>,
+    visited: &mut HashSet<String>,
+    roots: &mut Vec<String>,
+) {
+    if !intermediate.contains(name) {
+        roots.push(name.to_string());
+        return;
+    }
+    // Guards against inheritance cycles in malformed input
+    if !visited.insert(name.to_string()) {
+        return;
+    }
+    for base in &bases[name] {
+        collapse_to_kept_bases(base, bases, intermediate, visited, roots);
+    }
+}
+
+/// Removes a leading namespace (`com.example` or `com::example`) from class
+/// names, relationship targets and recorded packages alike, so edges keep
+/// connecting. Names outside the namespace are left untouched. Java and Groovy
+/// class names never include their package, so for them only the package
+/// (used by [`split_by_package`]) changes.
+pub fn strip_prefix(mut classes: Vec<ClassInfo>, prefix: &str) -> Vec<ClassInfo> {
+    let prefix = prefix.trim_end_matches("::").trim_end_matches('.');
+    if prefix.is_empty() {
+        return classes;
+    }
+    let rest = |name: &str| -> Option<String> {
+        name.strip_prefix(prefix)
+            .and_then(|rest| rest.strip_prefix("::").or_else(|| rest.strip_prefix('.')))
+            .map(str::to_string)
+    };
+    let strip = |name: &mut String| {
+        if let Some(rest) = rest(name).filter(|rest| !rest.is_empty()) {
+            *name = rest;
+        }
+    };
+    for class in &mut classes {
+        strip(&mut class.name);
+        for rel in &mut class.relationships {
+            strip(&mut rel.target);
+        }
+        if class.package.as_deref() == Some(prefix) {
+            class.package = None;
+        } else if let Some(package) = &mut class.package {
+            strip(package);
+        }
+    }
+    classes
+}
+
+/// The class name qualified by its package when the name does not already
+/// carry it (Java's `Order` in `com.example` becomes `com.example.Order`).
+fn qualified_name(class: &ClassInfo) -> String {
+    match &class.package {
+        Some(package) if !class.name.starts_with(&format!("{}::", package)) => format!("{}.{}", package, class.name),
+        _ => class.name.clone(),
+    }
+}
+
+/// The longest namespace shared by every class (`com.example` for
+/// `com.example.Order` and `com.example.model.Item`), if any. Packages
+/// recorded apart from the name count as part of it.
+pub fn common_prefix(classes: &[ClassInfo]) -> Option<String> {
+    let names: Vec<String> = classes.iter().map(qualified_name).collect();
+    let mut namespaces = names.iter().map(|name| {
+        let end = name.rfind("::").map(|i| i + 2).into_iter()
+            .chain(name.rfind('.').map(|i| i + 1))
+            .max()?;
+        Some(&name[..end])
+    });
+    let mut common = namespaces.next()??.to_string();
+    for namespace in namespaces {
+        let namespace = namespace?;
+        let shared = common.chars().zip(namespace.chars()).take_while(|(a, b)| a == b).count();
+        common.truncate(shared);
+    }
+    // Only cut at a separator, never inside a segment
+    let end = common.rfind("::").into_iter().chain(common.rfind('.')).max()?;
+    Some(common[..end].to_string()).filter(|p| !p.is_empty())
+}
+
+/// Package used for classes declared outside any package or namespace.
+pub const DEFAULT_PACKAGE: &str = "default";
+
+/// The top-level segment of the class's [`ClassInfo::package`] (`ui` for
+/// `ui::detail`, `com` for `com.example`). Nesting in the class name
+/// (`Outer.Inner`) is not a package.
+pub fn package_of(class: &ClassInfo) -> &str {
+    match class.package.as_deref() {
+        Some(package) => {
+            let end = package.find("::").into_iter().chain(package.find('.')).min().unwrap_or(package.len());
+            &package[..end]
+        }
+        None => DEFAULT_PACKAGE,
+    }
+}
+
+/// Partitions classes by [`package_of`]. Edges between parsed classes of
+/// different packages are dropped, or with `external_stubs` kept and drawn to
+/// a member-less `<<external>>` copy of the target. Edges to types that were
+/// not parsed at all are kept, as in a single diagram.
+pub fn split_by_package(classes: &[ClassInfo], external_stubs: bool) -> BTreeMap<String, Vec<ClassInfo>> {
+    let packages: HashMap<&str, &str> = classes.iter()
+        .map(|c| (c.name.as_str(), package_of(c)))
+        .collect();
+
+    let mut split: BTreeMap<String, Vec<ClassInfo>> = BTreeMap::new();
+    for class in classes {
+        let package = package_of(class);
+        let mut class = class.clone();
+        let mut stubs = Vec::new();
+        class.relationships.retain(|r| match packages.get(r.target.as_str()) {
+            Some(&target_package) if target_package != package => {
+                if external_stubs {
+                    stubs.push(r.target.clone());
+                }
+                external_stubs
+            }
+            _ => true,
+        });
+
+        let group = split.entry(package.to_string()).or_default();
+        group.push(class);
+        for stub in stubs {
+            if !group.iter().any(|c| c.name == stub) {
+                group.push(ClassInfo {
+                    name: stub,
+                    methods: Vec::new(),
+                    properties: Vec::new(),
+                    relationships: Vec::new(),
+                    stereotype: Some("external".to_string()),
+                    language: None,
+                    line: 0,
+                    column: 0,
+                    out_of_line: false,
+                    package: None,
+                });
+            }
+        }
+    }
+    split
+}
+
+/// Keeps the `max` classes with the highest relationship degree (incoming plus
+/// outgoing edges, each distinct edge counted once), preserving their original order.
+pub fn keep_most_connected(classes: Vec<ClassInfo>, max: usize) -> Vec<ClassInfo> {
+    if classes.len() <= max {
+        return classes;
+    }
+
+    let mut degrees: HashMap<&str, usize> = HashMap::new();
+    for class in &classes {
+        let edges: HashSet<_> = class.relationships.iter()
+            .map(|r| (r.target.as_str(), &r.rel_type))
+            .collect();
+        for (target, _) in edges {
+            *degrees.entry(class.name.as_str()).or_default() += 1;
+            *degrees.entry(target).or_default() += 1;
+        }
+    }
+
+    let mut ranked: Vec<usize> = (0..classes.len()).collect();
+    ranked.sort_by_key(|&i| std::cmp::Reverse(degrees.get(classes[i].name.as_str()).copied().unwrap_or(0)));
+    let kept: HashSet<usize> = ranked.into_iter().take(max).collect();
+
+    classes.into_iter()
+        .enumerate()
+        .filter(|(i, _)| kept.contains(i))
+        .map(|(_, class)| class)
+        .collect()
+}
+
+/// Name of the shared node that [`collapse_external`] draws unparsed types into.
+pub const EXTERNAL_NODE: &str = "External";
+
+/// Points every relationship to a type that was not parsed at the single
+/// `<<external>>` node [`EXTERNAL_NODE`], keeping one edge per class and
+/// relationship type. The node is only added when some edge leads to it.
+pub fn collapse_external(mut classes: Vec<ClassInfo>) -> Vec<ClassInfo> {
+    let parsed: HashSet<String> = classes.iter().map(|c| c.name.clone()).collect();
+    // A parsed class may already be called `External`
+    let mut external = EXTERNAL_NODE.to_string();
+    while parsed.contains(&external) {
+        external.push('_');
+    }
+
+    let mut used = false;
+    for class in &mut classes {
+        let mut seen: HashSet<RelationshipType> = HashSet::new();
+        class.relationships.retain_mut(|rel| {
+            if parsed.contains(&rel.target) {
+                return true;
+            }
+            used = true;
+            rel.target = external.clone();
+            rel.label = None;
+            seen.insert(rel.rel_type.clone())
+        });
+    }
+
+    if used {
+        classes.push(ClassInfo {
+            name: external,
+            methods: Vec::new(),
+            properties: Vec::new(),
+            relationships: Vec::new(),
+            stereotype: Some("external".to_string()),
+            language: None,
+            line: 0,
+            column: 0,
+            out_of_line: false,
+            package: None,
+        });
+    }
+    classes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::MethodInfo;
+    use crate::parsers::{cpp::CppParser, java::JavaParser, python::PythonParser, LanguageParser};
+    use anyhow::Result;
+    use std::fs;
+
+    #[test]
+    fn test_merge_cpp_header_and_source() -> Result<()> {
+        let mut classes = Vec::new();
+        for path in ["tests/cpp/Widget.cpp", "tests/cpp/Widget.h"] {
+            classes.extend(CppParser.parse(&fs::read_to_string(path)?)?);
+        }
+        assert_eq!(classes.iter().filter(|c| c.name == "ui::Widget").count(), 2);
+
+        let merged = merge_classes(classes);
+        let widgets: Vec<_> = merged.iter().filter(|c| c.name == "ui::Widget").collect();
+        assert_eq!(widgets.len(), 1);
+
+        let widget = widgets[0];
+        assert!(widget.methods.iter().any(|m| m.name == "draw"));
+        assert!(widget.methods.iter().any(|m| m.name == "theme"));
+        assert!(widget.properties.iter().any(|p| p.name == "size_"));
+        assert!(widget.relationships.iter().any(|r| r.target == "Canvas" && r.rel_type == RelationshipType::Dependency));
+
+        let canvas_deps = widget.relationships.iter()
+            .filter(|r| r.target == "Canvas" && r.rel_type == RelationshipType::Dependency)
+            .count();
+        assert_eq!(canvas_deps, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_namespaced_free_function_yields_no_class() -> Result<()> {
+        let classes = CppParser.parse("
+namespace utils { void helper(); }
+void utils::helper() {}
+")?;
+        assert!(drop_undeclared_out_of_line(classes).is_empty());
+
+        let mut classes = CppParser.parse(&fs::read_to_string("tests/cpp/Widget.cpp")?)?;
+        classes.extend(CppParser.parse(&fs::read_to_string("tests/cpp/Widget.h")?)?);
+        let classes = drop_undeclared_out_of_line(classes);
+        assert_eq!(classes.iter().filter(|c| c.name == "ui::Widget").count(), 2);
+        let merged = merge_classes(classes);
+        assert!(merged.iter().all(|c| !c.out_of_line));
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_python_stub_into_runtime_class() -> Result<()> {
+        let mut classes = Vec::new();
+        for path in ["tests/python/shapes.py", "tests/python/shapes.pyi"] {
+            classes.extend(PythonParser.parse(&fs::read_to_string(path)?)?);
+        }
+
+        let merged = merge_classes(classes);
+        let circle = merged.iter().find(|c| c.name == "Circle").unwrap();
+        assert_eq!(circle.properties.iter().filter(|p| p.name == "radius").count(), 1);
+        assert!(circle.properties.iter().any(|p| p.name == "style"));
+        assert!(circle.relationships.iter().any(|r| r.target == "Style" && r.rel_type == RelationshipType::Aggregation));
+        assert!(circle.methods.iter().any(|m| m.name == "area"));
+
+        // Classes only declared in the stub are kept
+        assert!(merged.iter().any(|c| c.name == "Style"));
+        Ok(())
+    }
+
+    fn class_with_deps(name: &str, targets: &[&str]) -> ClassInfo {
+        ClassInfo {
+            name: name.to_string(),
+            methods: vec![],
+            properties: vec![],
+            relationships: targets.iter().map(|t| Relationship {
+                target: t.to_string(),
+                rel_type: RelationshipType::Dependency,
+                label: None,
+                member_visibility: None,
+                provenance: None,
+                cardinality: None,
+            }).collect(),
+            stereotype: None,
+            language: None,
+            line: 1,
+            column: 1,
+            out_of_line: false,
+            package: None,
+        }
+    }
+
+    #[test]
+    fn test_drop_weak_dependencies() {
+        let mut service = class_with_deps("Service", &["Logger", "Config", "Config"]);
+        service.relationships.push(Relationship {
+            target: "Logger".to_string(),
+            rel_type: RelationshipType::Aggregation,
+            label: Some("logger".to_string()),
+            member_visibility: None,
+            provenance: None,
+            cardinality: None,
+        });
+
+        let kept = drop_weak_dependencies(vec![service], 2);
+        let rels = &kept[0].relationships;
+        assert!(!rels.iter().any(|r| r.target == "Logger" && r.rel_type == RelationshipType::Dependency));
+        assert!(rels.iter().any(|r| r.target == "Logger" && r.rel_type == RelationshipType::Aggregation));
+        assert_eq!(rels.iter().filter(|r| r.target == "Config").count(), 2);
+    }
+
+    fn subclass(name: &str, base: &str) -> ClassInfo {
+        let mut class = class_with_deps(name, &[]);
+        class.relationships.push(Relationship {
+            target: base.to_string(),
+            rel_type: RelationshipType::Inheritance,
+            label: None,
+            member_visibility: None,
+            provenance: None,
+            cardinality: None,
+        });
+        class
+    }
+
+    #[test]
+    fn test_flatten_inheritance() {
+        let mut abstract_mid = subclass("AbstractMid", "Root");
+        abstract_mid.methods.push(MethodInfo {
+            name: "run".to_string(),
+            visibility: crate::models::Visibility::Public,
+            is_abstract: true,
+            annotations: Vec::new(),
+        });
+        let classes = vec![
+            class_with_deps("Root", &[]),
+            abstract_mid,
+            subclass("Leaf", "AbstractMid"),
+            subclass("ConcreteMid", "Root"),
+            subclass("OtherLeaf", "ConcreteMid"),
+        ];
+
+        let flattened = flatten_inheritance(classes);
+        let names: Vec<_> = flattened.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["Root", "Leaf", "ConcreteMid", "OtherLeaf"]);
+
+        let base_of = |name: &str| {
+            let class = flattened.iter().find(|c| c.name == name).unwrap();
+            class.relationships.iter().map(|r| r.target.clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(base_of("Leaf"), vec!["Root"]);
+        // Concrete intermediates are kept
+        assert_eq!(base_of("OtherLeaf"), vec!["ConcreteMid"]);
+    }
+
+    #[test]
+    fn test_flatten_inheritance_rewires_other_edges() {
+        let mut abstract_mid = subclass("AbstractMid", "Root");
+        abstract_mid.stereotype = Some("interface".to_string());
+        let mut user = class_with_deps("User", &["AbstractMid"]);
+        user.relationships.push(Relationship {
+            target: "AbstractMid".to_string(),
+            rel_type: RelationshipType::Aggregation,
+            label: Some("handler".to_string()),
+            member_visibility: None,
+            provenance: None,
+            cardinality: None,
+        });
+        let classes = vec![class_with_deps("Root", &[]), abstract_mid, subclass("Leaf", "AbstractMid"), user];
+
+        let flattened = flatten_inheritance(classes);
+        assert!(flattened.iter().all(|c| c.relationships.iter().all(|r| r.target != "AbstractMid")));
+        let user = flattened.iter().find(|c| c.name == "User").unwrap();
+        let edges: Vec<_> = user.relationships.iter().map(|r| (r.target.as_str(), r.rel_type.clone(), r.label.as_deref())).collect();
+        assert_eq!(edges, vec![
+            ("Root", RelationshipType::Dependency, None),
+            ("Root", RelationshipType::Aggregation, Some("handler")),
+        ]);
+    }
+
+    #[test]
+    fn test_strip_prefix() {
+        let classes = vec![
+            class_with_deps("com.example.Order", &["com.example.model.Item", "java.util.UUID"]),
+            class_with_deps("com.example.model.Item", &[]),
+        ];
+        assert_eq!(common_prefix(&classes).as_deref(), Some("com.example"));
+
+        let stripped = strip_prefix(classes, "com.example");
+        let names: Vec<_> = stripped.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["Order", "model.Item"]);
+        let targets: Vec<_> = stripped[0].relationships.iter().map(|r| r.target.as_str()).collect();
+        assert_eq!(targets, vec!["model.Item", "java.util.UUID"]);
+
+        // Prefixes only match whole segments
+        let stripped = strip_prefix(vec![class_with_deps("com.examples.Order", &[])], "com.example");
+        assert_eq!(stripped[0].name, "com.examples.Order");
+        assert_eq!(common_prefix(&[class_with_deps("ui::Widget", &[]), class_with_deps("util::Clock", &[])]), None);
+        assert_eq!(common_prefix(&[class_with_deps("Order", &[])]), None);
+    }
+
+    #[test]
+    fn test_strip_prefix_from_java_packages() -> Result<()> {
+        let mut classes = JavaParser.parse("package com.example.shop;\npublic class Order { public Invoice invoice; }")?;
+        classes.extend(JavaParser.parse("package com.example.billing;\npublic class Invoice {}")?);
+        let prefix = common_prefix(&classes);
+        assert_eq!(prefix.as_deref(), Some("com.example"));
+
+        let stripped = strip_prefix(classes, &prefix.unwrap());
+        let packages: Vec<_> = stripped.iter().map(|c| (c.name.as_str(), c.package.as_deref())).collect();
+        assert_eq!(packages, vec![("Order", Some("shop")), ("Invoice", Some("billing"))]);
+        let split = split_by_package(&stripped, false);
+        assert_eq!(split.keys().collect::<Vec<_>>(), vec!["billing", "shop"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_by_package() -> Result<()> {
+        let classes = CppParser.parse("
+namespace ui {
+class Canvas {};
+class Widget { public: ui::Canvas* canvas; core::Config* config; gfx::Surface* surface; };
+}
+namespace core { class Config {}; }
+class Main {};
+")?;
+
+        let split = split_by_package(&classes, false);
+        let packages: Vec<_> = split.keys().map(|k| k.as_str()).collect();
+        assert_eq!(packages, vec!["core", "default", "ui"]);
+        let widget = &split["ui"][1];
+        let targets: Vec<_> = widget.relationships.iter().map(|r| r.target.as_str()).collect();
+        assert_eq!(targets, vec!["ui::Canvas", "gfx::Surface"]);
+
+        let split = split_by_package(&classes, true);
+        let ui: Vec<_> = split["ui"].iter().map(|c| (c.name.as_str(), c.stereotype.as_deref())).collect();
+        assert_eq!(ui, vec![("ui::Canvas", None), ("ui::Widget", None), ("core::Config", Some("external"))]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_by_package_ignores_class_nesting() -> Result<()> {
+        let classes = JavaParser.parse("
+package com.example.shop;
+
+public class Outer {
+    public Inner inner;
+    public static class Inner {}
+}
+")?;
+        assert!(classes.iter().all(|c| package_of(c) == "com"));
+        let split = split_by_package(&classes, false);
+        assert_eq!(split.len(), 1);
+        let outer = split["com"].iter().find(|c| c.name == "Outer").unwrap();
+        assert!(outer.relationships.iter().any(|r| r.target == "Inner"));
+
+        let classes = PythonParser.parse("class Outer:\n    class Inner:\n        pass\n")?;
+        assert_eq!(split_by_package(&classes, false).keys().collect::<Vec<_>>(), vec!["default"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_keep_most_connected() {
+        let classes = vec![
+            class_with_deps("Leaf", &[]),
+            class_with_deps("Hub", &["Core", "Util", "Util"]),
+            class_with_deps("Core", &["Util"]),
+            class_with_deps("Util", &[]),
+            class_with_deps("Extra", &["Util"]),
+        ];
+
+        // Degrees: Util 3, Hub 2, Core 2, Extra 1, Leaf 0; ties keep source order
+        let kept = keep_most_connected(classes, 2);
+        let names: Vec<_> = kept.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["Hub", "Util"]);
+    }
+
+    #[test]
+    fn test_merge_and_render_scale_linearly() {
+        // 20k fragments of one class, each adding a new edge and repeating an old one;
+        // quadratic lookups would take minutes here. No wall-clock assertion, which
+        // would be flaky on a loaded machine: a regression shows as a stalled test
+        let fragments = 20_000;
+        let classes: Vec<ClassInfo> = (1..=fragments)
+            .map(|i| class_with_deps("Hub", &[&format!("T{}", i), &format!("T{}", i - 1)]))
+            .collect();
+
+        let merged = merge_classes(classes);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].relationships.len(), fragments + 1);
+
+        let diagram = crate::mermaid::generate_mermaid(&merged, &crate::mermaid::MermaidOptions::default());
+        assert_eq!(diagram.matches(" ..> Hub").count(), fragments + 1);
+    }
+
+    #[test]
+    fn test_collapse_external() {
+        let classes = vec![
+            class_with_deps("Order", &["Customer", "Decimal", "uuid.UUID", "Decimal"]),
+            class_with_deps("Customer", &["Order"]),
+        ];
+        let collapsed = collapse_external(classes);
+        let names: Vec<_> = collapsed.iter().map(|c| (c.name.as_str(), c.stereotype.as_deref())).collect();
+        assert_eq!(names, vec![("Order", None), ("Customer", None), ("External", Some("external"))]);
+        let targets: Vec<_> = collapsed[0].relationships.iter().map(|r| r.target.as_str()).collect();
+        assert_eq!(targets, vec!["Customer", "External"]);
+        assert_eq!(collapsed[1].relationships[0].target, "Order");
+
+        // Nothing external: no node is added
+        let internal = collapse_external(vec![class_with_deps("A", &["B"]), class_with_deps("B", &[])]);
+        assert_eq!(internal.len(), 2);
+
+        let clash = collapse_external(vec![class_with_deps("External", &["Decimal"])]);
+        assert_eq!(clash[0].relationships[0].target, "External_");
+        assert_eq!(clash[1].name, "External_");
+    }
+}
@@ -0,0 +1,235 @@
+use crate::mermaid::{self, MermaidOptions};
+use crate::models::ClassInfo;
+use anyhow::{bail, Context, Result};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::io::IsTerminal;
+
+/// The browsing state behind `--tui`: the classes matching the search query
+/// and the one selected among them. Kept apart from the terminal so it can be
+/// driven directly.
+pub struct Browser<'a> {
+    classes: Vec<&'a ClassInfo>,
+    query: String,
+    matches: Vec<usize>,
+    selected: usize,
+}
+
+impl<'a> Browser<'a> {
+    pub fn new(classes: &'a [ClassInfo]) -> Self {
+        let mut classes: Vec<&ClassInfo> = classes.iter().collect();
+        classes.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut browser = Self { classes, query: String::new(), matches: Vec::new(), selected: 0 };
+        browser.refilter();
+        browser
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    /// Replaces the search query; classes match when their name contains it, ignoring case.
+    pub fn set_query(&mut self, query: &str) {
+        self.query = query.to_string();
+        self.refilter();
+    }
+
+    fn refilter(&mut self) {
+        let query = self.query.to_lowercase();
+        self.matches = (0..self.classes.len())
+            .filter(|&i| self.classes[i].name.to_lowercase().contains(&query))
+            .collect();
+        self.selected = 0;
+    }
+
+    /// The classes matching the query, sorted by name.
+    pub fn matches(&self) -> impl Iterator<Item = &'a ClassInfo> + '_ {
+        self.matches.iter().map(|&i| self.classes[i])
+    }
+
+    pub fn selected(&self) -> Option<&'a ClassInfo> {
+        self.matches.get(self.selected).map(|&i| self.classes[i])
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.matches.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Members and relationships of the selected class, one per line.
+    pub fn details(&self) -> Vec<String> {
+        let Some(class) = self.selected() else {
+            return Vec::new();
+        };
+        let mut lines = vec![class.name.clone()];
+        if let Some(stereotype) = &class.stereotype {
+            lines.push(format!("<<{}>>", stereotype));
+        }
+        lines.push(String::new());
+        lines.extend(class.properties.iter().map(|p| format!("{}{}", p.visibility.uml_symbol(), p.name)));
+        lines.extend(class.methods.iter().map(|m| format!("{}{}()", m.visibility.uml_symbol(), m.name)));
+        lines.push(String::new());
+        lines.extend(class.relationships.iter().map(|r| match &r.label {
+            Some(label) => format!("{} {} ({})", r.rel_type.name(), r.target, label),
+            None => format!("{} {}", r.rel_type.name(), r.target),
+        }));
+        lines
+    }
+
+    /// A Mermaid diagram of the selected class and the parsed classes it is
+    /// directly related to, in either direction.
+    pub fn focus_diagram(&self, options: &MermaidOptions) -> String {
+        let Some(class) = self.selected() else {
+            return String::new();
+        };
+        let neighbours: Vec<ClassInfo> = self.classes.iter()
+            .filter(|c| {
+                c.name == class.name
+                    || class.relationships.iter().any(|r| r.target == c.name)
+                    || c.relationships.iter().any(|r| r.target == class.name)
+            })
+            .map(|c| (*c).clone())
+            .collect();
+        mermaid::generate_mermaid(&neighbours, options)
+    }
+}
+
+/// Runs the browser until `q` (or Esc outside the search box) is pressed.
+/// `/` edits the search query, Enter or Esc leaves it; arrows or `j`/`k` move.
+/// Fails without an interactive terminal; the terminal is restored on every exit.
+pub fn run(classes: &[ClassInfo], options: &MermaidOptions) -> Result<()> {
+    if !std::io::stdout().is_terminal() {
+        bail!("--tui needs an interactive terminal");
+    }
+    let mut terminal = match ratatui::try_init() {
+        Ok(terminal) => terminal,
+        Err(err) => {
+            // Raw mode may already be on when entering the alternate screen fails
+            ratatui::restore();
+            return Err(err).context("Failed to set up the terminal for --tui");
+        }
+    };
+    let result = event_loop(&mut terminal, &mut Browser::new(classes), options);
+    ratatui::restore();
+    result
+}
+
+fn event_loop(terminal: &mut DefaultTerminal, browser: &mut Browser, options: &MermaidOptions) -> Result<()> {
+    let mut searching = false;
+    loop {
+        terminal.draw(|frame| draw(frame, browser, options, searching))?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Up => browser.select_previous(),
+            KeyCode::Down => browser.select_next(),
+            KeyCode::Enter | KeyCode::Esc if searching => searching = false,
+            KeyCode::Backspace if searching => {
+                let mut query = browser.query().to_string();
+                query.pop();
+                browser.set_query(&query);
+            }
+            KeyCode::Char(c) if searching => browser.set_query(&format!("{}{}", browser.query(), c)),
+            KeyCode::Char('/') => searching = true,
+            KeyCode::Char('j') => browser.select_next(),
+            KeyCode::Char('k') => browser.select_previous(),
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            _ => {}
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, browser: &Browser, options: &MermaidOptions, searching: bool) {
+    let [list_area, detail_area] = Layout::horizontal([Constraint::Percentage(30), Constraint::Percentage(70)])
+        .areas(frame.area());
+    let [members_area, diagram_area] = Layout::vertical([Constraint::Percentage(40), Constraint::Percentage(60)])
+        .areas(detail_area);
+
+    let title = if searching || !browser.query().is_empty() {
+        format!(" Classes /{} ", browser.query())
+    } else {
+        " Classes (/ search, q quit) ".to_string()
+    };
+    let items: Vec<ListItem> = browser.matches().map(|c| ListItem::new(c.name.clone())).collect();
+    let list = List::new(items)
+        .block(Block::bordered().title(title))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default().with_selected(browser.selected().map(|_| browser.selected));
+    frame.render_stateful_widget(list, list_area, &mut state);
+
+    frame.render_widget(
+        Paragraph::new(browser.details().join("\n")).block(Block::bordered().title(" Members ")),
+        members_area,
+    );
+    frame.render_widget(
+        Paragraph::new(browser.focus_diagram(options)).block(Block::bordered().title(" Diagram ")),
+        diagram_area,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::{java::JavaParser, LanguageParser};
+
+    #[test]
+    fn test_browser_filters_and_focuses() -> Result<()> {
+        let classes = JavaParser.parse("
+public class OrderService {
+    public OrderRepository repository;
+    public void place(Order order) {}
+}
+public class OrderRepository {}
+public class Order {}
+public class Invoice {}
+")?;
+        let mut browser = Browser::new(&classes);
+        let names: Vec<_> = browser.matches().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["Invoice", "Order", "OrderRepository", "OrderService"]);
+
+        browser.set_query("order");
+        let names: Vec<_> = browser.matches().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["Order", "OrderRepository", "OrderService"]);
+
+        browser.select_next();
+        browser.select_next();
+        browser.select_next();
+        assert_eq!(browser.selected().unwrap().name, "OrderService");
+        let details = browser.details();
+        assert!(details.contains(&"+repository".to_string()));
+        assert!(details.contains(&"+place()".to_string()));
+        assert!(details.contains(&"aggregation OrderRepository (repository)".to_string()));
+
+        let diagram = browser.focus_diagram(&MermaidOptions::default());
+        assert!(diagram.contains("class OrderRepository"));
+        assert!(diagram.contains("class Order {"));
+        assert!(!diagram.contains("Invoice"));
+
+        browser.set_query("nothing");
+        assert!(browser.selected().is_none());
+        assert!(browser.details().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_without_terminal_fails() {
+        // Only checkable when the test run itself is not attached to a terminal
+        if std::io::stdout().is_terminal() {
+            return;
+        }
+        let err = run(&[], &MermaidOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("interactive terminal"));
+    }
+}
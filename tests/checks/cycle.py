@@ -0,0 +1,13 @@
+class Order:
+    def __init__(self, customer: "Customer"):
+        self.customer = customer
+
+
+class Customer:
+    def latest_order(self) -> Order:
+        pass
+
+
+class Invoice:
+    def __init__(self, order: Order):
+        self.order = order
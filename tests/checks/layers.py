@@ -0,0 +1,14 @@
+class Repository:
+    def find(self, key: str):
+        pass
+
+
+class Service:
+    def __init__(self, repository: Repository):
+        self.repository = repository
+
+
+class Controller:
+    def __init__(self, service: Service, repository: Repository):
+        self.service = service
+        self.repository = repository
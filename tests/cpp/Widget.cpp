@@ -0,0 +1,15 @@
+#include "Widget.h"
+
+namespace ui {
+
+Widget::Widget() : size_(0), theme_(nullptr) {}
+
+void Widget::draw(Canvas* canvas) {
+    // Rendering logic
+}
+
+}
+
+ui::Theme* ui::Widget::theme() {
+    return theme_;
+}
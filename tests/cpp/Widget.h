@@ -0,0 +1,19 @@
+#pragma once
+
+namespace ui {
+
+class Canvas;
+class Theme;
+
+class Widget {
+public:
+    Widget();
+    void draw(Canvas* canvas);
+    Theme* theme();
+
+private:
+    int size_;
+    Theme* theme_;
+};
+
+}
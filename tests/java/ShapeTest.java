@@ -0,0 +1,5 @@
+public class ShapeTest {
+    private Shape shape;
+
+    public void testArea() {}
+}
@@ -0,0 +1,8 @@
+class Circle:
+    radius: float
+    style: Style
+    def __init__(self, radius: float) -> None: ...
+    def area(self) -> float: ...
+
+class Style:
+    color: str